@@ -0,0 +1,312 @@
+//! Tracking which parts of a buffer have been validated.
+//!
+//! Formats which store offsets to other data in the same buffer must check
+//! that the data they point to is in bounds and properly aligned. They must
+//! also make sure that no two values claim the same bytes, since otherwise a
+//! single byte could be validated as two different types.
+//!
+//! A [`BoundsContext`] provides those checks. The [`Bounds`] context tracks
+//! the bytes of a buffer which have not been claimed yet, which always start
+//! after the end of the last claimed range. As a result, subtrees must be
+//! claimed in the order of their addresses, and a subtree which overlaps or
+//! comes before a previously claimed range is rejected.
+//!
+//! ```
+//! use core::alloc::Layout;
+//!
+//! use bytecheck::{
+//!     bounds::{check_subtree, Bounds},
+//!     rancor::{Failure, Strategy},
+//! };
+//!
+//! #[repr(C, align(4))]
+//! struct Buffer([u8; 8]);
+//!
+//! let buffer = Buffer([1, 0, 0, 0, 0, 0, 0, 0]);
+//! let bytes = &buffer.0[..];
+//! let mut bounds = Bounds::new(bytes);
+//! let context = Strategy::<_, Failure>::wrap(&mut bounds);
+//!
+//! let first = bytes.as_ptr().cast::<u32>();
+//! let layout = Layout::new::<u32>();
+//! unsafe {
+//!     assert!(check_subtree(first, layout, context).is_ok());
+//!     // The first four bytes have already been claimed.
+//!     assert!(check_subtree(first, layout, context).is_err());
+//!     assert!(check_subtree(first.add(1), layout, context).is_ok());
+//! }
+//! ```
+
+use core::{alloc::Layout, fmt, marker::PhantomData, ops::Range};
+
+use rancor::{fail, Fallible, Source, Strategy};
+
+use crate::{
+    budget::{BudgetContext, Budgeted},
+    CheckBytes, LocationContext,
+};
+
+/// A context which knows the bounds of the buffer being validated.
+pub trait BoundsContext<E = <Self as Fallible>::Error> {
+    /// Checks that the bytes described by `layout` at `ptr` are within the
+    /// buffer and that `ptr` is properly aligned.
+    fn check_subtree_ptr(
+        &mut self,
+        ptr: *const u8,
+        layout: &Layout,
+    ) -> Result<(), E>;
+
+    /// Claims the bytes described by `layout` at `ptr`, checking them as with
+    /// [`check_subtree_ptr`](BoundsContext::check_subtree_ptr).
+    ///
+    /// Returns an error if any of the bytes have already been claimed, or if
+    /// the bytes come before a previously-claimed range.
+    fn claim_subtree(
+        &mut self,
+        ptr: *const u8,
+        layout: &Layout,
+    ) -> Result<(), E>;
+}
+
+impl<T, E> BoundsContext<E> for Strategy<T, E>
+where
+    T: BoundsContext<E> + ?Sized,
+{
+    #[inline]
+    fn check_subtree_ptr(
+        &mut self,
+        ptr: *const u8,
+        layout: &Layout,
+    ) -> Result<(), E> {
+        T::check_subtree_ptr(self, ptr, layout)
+    }
+
+    #[inline]
+    fn claim_subtree(
+        &mut self,
+        ptr: *const u8,
+        layout: &Layout,
+    ) -> Result<(), E> {
+        T::claim_subtree(self, ptr, layout)
+    }
+}
+
+impl<C: BoundsContext<E>, E> BoundsContext<E> for Budgeted<C> {
+    #[inline]
+    fn check_subtree_ptr(
+        &mut self,
+        ptr: *const u8,
+        layout: &Layout,
+    ) -> Result<(), E> {
+        self.inner_mut().check_subtree_ptr(ptr, layout)
+    }
+
+    #[inline]
+    fn claim_subtree(
+        &mut self,
+        ptr: *const u8,
+        layout: &Layout,
+    ) -> Result<(), E> {
+        self.inner_mut().claim_subtree(ptr, layout)
+    }
+}
+
+/// Claims the bytes described by `layout` at `value`, then checks the value.
+///
+/// # Safety
+///
+/// If the bytes are successfully claimed, `value` must satisfy the
+/// requirements of [`CheckBytes::check_bytes`] except for alignment, and
+/// `layout` must be the layout of the value.
+pub unsafe fn check_subtree<T, C>(
+    value: *const T,
+    layout: Layout,
+    context: &mut C,
+) -> Result<(), C::Error>
+where
+    T: CheckBytes<C> + ?Sized,
+    C: Fallible + BoundsContext + ?Sized,
+{
+    context.claim_subtree(value.cast(), &layout)?;
+    // SAFETY: The bytes of the value were successfully claimed, so they are in
+    // bounds and aligned. The caller has guaranteed that `value` meets all of
+    // the other requirements of `check_bytes`.
+    unsafe { T::check_bytes(value, context) }
+}
+
+/// A [`BoundsContext`] for a single contiguous buffer.
+///
+/// Because claimed bytes are checked as values, the buffer must outlive the
+/// context.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Bounds<'a> {
+    buffer: Range<usize>,
+    unclaimed: Range<usize>,
+    _phantom: PhantomData<&'a [u8]>,
+}
+
+impl<'a> Bounds<'a> {
+    /// Returns a new `Bounds` for the given buffer, with all of its bytes
+    /// unclaimed.
+    #[inline]
+    pub fn new(buffer: &'a [u8]) -> Self {
+        let start = buffer.as_ptr() as usize;
+        let range = start..start + buffer.len();
+        Self {
+            buffer: range.clone(),
+            unclaimed: range,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Returns the number of bytes from the start of the buffer to the end of
+    /// the last claimed range.
+    #[inline]
+    pub fn claimed(&self) -> usize {
+        self.unclaimed.start - self.buffer.start
+    }
+
+    /// Returns the number of bytes after the end of the last claimed range.
+    #[inline]
+    pub fn remaining(&self) -> usize {
+        self.unclaimed.end - self.unclaimed.start
+    }
+
+    fn range_of<E: Source>(
+        &self,
+        ptr: *const u8,
+        layout: &Layout,
+    ) -> Result<Range<usize>, E> {
+        let address = ptr as usize;
+        let in_bounds = address.checked_add(layout.size()).filter(|&end| {
+            address >= self.buffer.start && end <= self.buffer.end
+        });
+        let Some(end) = in_bounds else {
+            fail!(OutOfBoundsError {
+                address,
+                len: layout.size(),
+                buffer_start: self.buffer.start,
+                buffer_end: self.buffer.end,
+            });
+        };
+        if address & (layout.align() - 1) != 0 {
+            return Err(E::new(UnalignedPointerError {
+                address,
+                align: layout.align(),
+            })
+            .trace(LocationContext {
+                address,
+                len: layout.size(),
+            }));
+        }
+        Ok(address..end)
+    }
+}
+
+impl<E: Source> BoundsContext<E> for Bounds<'_> {
+    fn check_subtree_ptr(
+        &mut self,
+        ptr: *const u8,
+        layout: &Layout,
+    ) -> Result<(), E> {
+        self.range_of(ptr, layout).map(|_| ())
+    }
+
+    fn claim_subtree(
+        &mut self,
+        ptr: *const u8,
+        layout: &Layout,
+    ) -> Result<(), E> {
+        let range = self.range_of::<E>(ptr, layout)?;
+        if range.start < self.unclaimed.start {
+            return Err(E::new(OverlappingClaimError {
+                address: range.start,
+                len: range.end - range.start,
+                claimed_end: self.unclaimed.start,
+            })
+            .trace(LocationContext {
+                address: range.start,
+                len: range.end - range.start,
+            }));
+        }
+        self.unclaimed.start = range.end;
+        Ok(())
+    }
+}
+
+impl<E> BudgetContext<E> for Bounds<'_> {}
+
+/// An error resulting from a range of bytes that is not entirely within the
+/// buffer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct OutOfBoundsError {
+    /// The address of the start of the range.
+    pub address: usize,
+    /// The length of the range in bytes.
+    pub len: usize,
+    /// The address of the start of the buffer.
+    pub buffer_start: usize,
+    /// The address of the end of the buffer.
+    pub buffer_end: usize,
+}
+
+impl fmt::Display for OutOfBoundsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} bytes at address {:#x} are outside of the buffer at {:#x}..{:#x}",
+            self.len, self.address, self.buffer_start, self.buffer_end,
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for OutOfBoundsError {}
+
+/// An error resulting from a pointer that is not properly aligned.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct UnalignedPointerError {
+    /// The address of the pointer.
+    pub address: usize,
+    /// The required alignment of the pointer.
+    pub align: usize,
+}
+
+impl fmt::Display for UnalignedPointerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "pointer to address {:#x} is not aligned to {} bytes",
+            self.address, self.align,
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for UnalignedPointerError {}
+
+/// An error resulting from claiming a range of bytes which overlaps or comes
+/// before a previously-claimed range.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct OverlappingClaimError {
+    /// The address of the start of the range.
+    pub address: usize,
+    /// The length of the range in bytes.
+    pub len: usize,
+    /// The address of the end of the last claimed range.
+    pub claimed_end: usize,
+}
+
+impl fmt::Display for OverlappingClaimError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} bytes at address {:#x} overlap or come before bytes claimed up \
+             to {:#x}",
+            self.len, self.address, self.claimed_end,
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for OverlappingClaimError {}
@@ -0,0 +1,243 @@
+//! Limits on how much work validation may do.
+//!
+//! The amount of work done by validation depends on the input. A small buffer
+//! can describe a huge slice, and pointer-following implementations can visit
+//! the same data many times. When validating untrusted input, a [`Budget`]
+//! bounds the total work so that hostile input fails quickly instead.
+//!
+//! Work is measured in units charged through [`BudgetContext`]:
+//!
+//! - Slices charge one unit per element.
+//! - `str` and `CStr` charge one unit per byte.
+//!
+//! Arrays and derived types have a size fixed by their type, so they don't
+//! charge anything themselves. Pointer-following implementations can charge for
+//! each target they visit with [`charge`].
+//!
+//! Contexts used to check slices, `str`, or `CStr` must implement
+//! `BudgetContext`. `()`, [`DepthLimit`], and the contexts in this crate
+//! already do, and a `Strategy` forwards to the context it wraps. Because
+//! [`charge`](BudgetContext::charge) doesn't limit work by default, other
+//! contexts can implement it with an empty impl block. To limit work, wrap a
+//! context in a [`Budgeted`]:
+//!
+//! ```
+//! use bytecheck::{
+//!     budget::{Budget, Budgeted},
+//!     check_bytes_with_context,
+//!     depth::DepthLimit,
+//!     rancor::Failure,
+//! };
+//!
+//! let values = [1u32; 100];
+//!
+//! let check = |limit| unsafe {
+//!     check_bytes_with_context::<[u32], _, Failure>(
+//!         &values[..],
+//!         &mut Budgeted::new(DepthLimit::default(), Budget::new(limit)),
+//!     )
+//! };
+//! assert!(check(100).is_ok());
+//! assert!(check(99).is_err());
+//! ```
+
+use core::fmt;
+
+use rancor::{fail, Fallible, Source, Strategy};
+
+use crate::depth::{DepthContext, DepthLimit};
+
+/// A context which limits the amount of work done by validation.
+pub trait BudgetContext<E = <Self as Fallible>::Error> {
+    /// Charges `units` of work against the budget, returning an error if the
+    /// budget does not have enough units remaining.
+    ///
+    /// By default, this doesn't limit work and always succeeds.
+    #[inline]
+    fn charge(&mut self, units: usize) -> Result<(), E> {
+        let _ = units;
+        Ok(())
+    }
+}
+
+impl<T, E> BudgetContext<E> for Strategy<T, E>
+where
+    T: BudgetContext<E> + ?Sized,
+{
+    #[inline]
+    fn charge(&mut self, units: usize) -> Result<(), E> {
+        T::charge(self, units)
+    }
+}
+
+impl<E> BudgetContext<E> for () {}
+
+impl<E> BudgetContext<E> for DepthLimit {}
+
+/// Charges `units` of work against the budget of the given context.
+#[inline]
+pub fn charge<C>(context: &mut C, units: usize) -> Result<(), C::Error>
+where
+    C: Fallible + BudgetContext + ?Sized,
+{
+    context.charge(units)
+}
+
+/// A [`BudgetContext`] which fails after a fixed number of units of work.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Budget {
+    remaining: usize,
+    limit: usize,
+}
+
+impl Budget {
+    /// Returns a new `Budget` which allows `limit` units of work.
+    #[inline]
+    pub fn new(limit: usize) -> Self {
+        Self {
+            remaining: limit,
+            limit,
+        }
+    }
+
+    /// Returns the total number of units allowed.
+    #[inline]
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// Returns the number of units which have not been charged yet.
+    #[inline]
+    pub fn remaining(&self) -> usize {
+        self.remaining
+    }
+
+    /// Returns the number of units which have been charged.
+    #[inline]
+    pub fn used(&self) -> usize {
+        self.limit - self.remaining
+    }
+
+    /// Splits the remaining units evenly between `parts` new budgets.
+    #[cfg(feature = "rayon")]
+    pub(crate) fn split_evenly(&mut self, parts: usize) -> Vec<Self> {
+        let Some(share) = self.remaining.checked_div(parts) else {
+            return Vec::new();
+        };
+        let extra = self.remaining % parts;
+        self.remaining = 0;
+        (0..parts)
+            .map(|i| Self {
+                remaining: share + usize::from(i < extra),
+                limit: self.limit,
+            })
+            .collect()
+    }
+
+    /// Returns the remaining units of a budget created by `split_evenly`.
+    #[cfg(feature = "rayon")]
+    pub(crate) fn merge_remaining(&mut self, other: Self) {
+        self.remaining += other.remaining;
+    }
+}
+
+impl<E: Source> BudgetContext<E> for Budget {
+    fn charge(&mut self, units: usize) -> Result<(), E> {
+        match self.remaining.checked_sub(units) {
+            Some(remaining) => {
+                self.remaining = remaining;
+                Ok(())
+            }
+            None => fail!(BudgetExceededError { limit: self.limit }),
+        }
+    }
+}
+
+/// A context which adds a [`Budget`] to another context.
+///
+/// Units of work are charged against the budget, and the depth, bounds, and
+/// shared capabilities are forwarded to the inner context. Your own capability
+/// traits can be implemented for `Budgeted<YourContext>` by forwarding to
+/// [`inner_mut`](Budgeted::inner_mut). Alternatively, a context can hold a
+/// `Budget` itself and implement `BudgetContext` by charging it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Budgeted<C> {
+    inner: C,
+    budget: Budget,
+}
+
+impl<C> Budgeted<C> {
+    /// Returns a new `Budgeted` context which wraps `inner` with `budget`.
+    #[inline]
+    pub fn new(inner: C, budget: Budget) -> Self {
+        Self { inner, budget }
+    }
+
+    /// Returns a reference to the inner context.
+    #[inline]
+    pub fn inner(&self) -> &C {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the inner context.
+    #[inline]
+    pub fn inner_mut(&mut self) -> &mut C {
+        &mut self.inner
+    }
+
+    /// Returns the budget.
+    #[inline]
+    pub fn budget(&self) -> &Budget {
+        &self.budget
+    }
+
+    /// Returns the inner context and the budget.
+    #[inline]
+    pub fn into_parts(self) -> (C, Budget) {
+        (self.inner, self.budget)
+    }
+
+    #[cfg(feature = "rayon")]
+    pub(crate) fn parts_mut(&mut self) -> (&mut C, &mut Budget) {
+        (&mut self.inner, &mut self.budget)
+    }
+}
+
+impl<C, E: Source> BudgetContext<E> for Budgeted<C> {
+    #[inline]
+    fn charge(&mut self, units: usize) -> Result<(), E> {
+        self.budget.charge(units)
+    }
+}
+
+impl<C: DepthContext<E>, E> DepthContext<E> for Budgeted<C> {
+    #[inline]
+    fn enter(&mut self) -> Result<(), E> {
+        self.inner.enter()
+    }
+
+    #[inline]
+    fn exit(&mut self) {
+        self.inner.exit()
+    }
+}
+
+/// An error resulting from validation exceeding its budget.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct BudgetExceededError {
+    /// The total number of units allowed.
+    pub limit: usize,
+}
+
+impl fmt::Display for BudgetExceededError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "validation exceeded its budget of {} units of work",
+            self.limit,
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for BudgetExceededError {}
@@ -0,0 +1,449 @@
+//! Checking containers of sections with different types.
+//!
+//! A container is a [`ContainerHeader`], followed immediately by a directory
+//! of [`SectionEntry`]s, followed by the sections. Each entry describes the
+//! offset and length of a section and the tag of its type, which is looked up
+//! in a registry of [`RecordType`]s.
+//!
+//! Opening a container with [`ContainerConfig::open`] checks the header's
+//! magic bytes and version, and uses [`Bounds`] to check that every section
+//! is in bounds, aligned for its type, and doesn't overlap the header, the
+//! directory, or any other section. Sections must be stored in the same
+//! order as their entries. The values in the sections can then be checked
+//! lazily with [`Container::section`], or eagerly with
+//! [`Container::check_all`].
+//!
+//! ```
+//! use bytecheck::{
+//!     container::{ContainerConfig, ContainerHeader, SectionEntry},
+//!     rancor::Failure,
+//!     tlv::RecordType,
+//! };
+//!
+//! #[repr(C, align(8))]
+//! struct Buffer([u8; 48]);
+//!
+//! let header = ContainerHeader {
+//!     magic: *b"EXAMPLE\0",
+//!     version: 1,
+//!     section_count: 1,
+//! };
+//! let entry = SectionEntry {
+//!     offset: 40,
+//!     len: 4,
+//!     tag: 7,
+//! };
+//! let mut buffer = Buffer([0; 48]);
+//! unsafe {
+//!     buffer.0.as_mut_ptr().cast::<ContainerHeader>().write(header);
+//!     buffer.0.as_mut_ptr().add(16).cast::<SectionEntry>().write(entry);
+//!     buffer.0.as_mut_ptr().add(40).cast::<u32>().write(42);
+//! }
+//!
+//! let registry = [RecordType::<Failure>::new::<u32>(7)];
+//! let config = ContainerConfig::new(*b"EXAMPLE\0", 1);
+//! let mut container = config.open(&buffer.0[..44], &registry).unwrap();
+//! let section = container.section(0).unwrap();
+//! assert_eq!(section.get::<u32>(), Some(&42));
+//! ```
+
+use core::{alloc::Layout, fmt, mem};
+
+use rancor::{fail, Fallible, ResultExt as _, Source};
+
+use crate::{
+    bounds::{Bounds, BoundsContext},
+    fingerprint::{Fingerprint, FingerprintHasher},
+    from_bytes,
+    schema::{Schema, SchemaKind, TypeSchema},
+    slice_from_bytes,
+    tlv::{Record, RecordType},
+    BufferContext, CheckBytes,
+};
+
+/// The header at the start of a container.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[repr(C)]
+pub struct ContainerHeader {
+    /// Bytes which identify the format of the container.
+    pub magic: [u8; 8],
+    /// The version of the format of the container.
+    pub version: u32,
+    /// The number of entries in the directory.
+    pub section_count: u32,
+}
+
+// SAFETY: All bit patterns are valid for `ContainerHeader`.
+unsafe impl<C: Fallible + ?Sized> CheckBytes<C> for ContainerHeader {
+    #[inline]
+    unsafe fn check_bytes(_: *const Self, _: &mut C) -> Result<(), C::Error> {
+        Ok(())
+    }
+}
+
+impl Fingerprint for ContainerHeader {
+    const FINGERPRINT: u64 = FingerprintHasher::new()
+        .write_str("ContainerHeader")
+        .finish();
+}
+
+impl Schema for ContainerHeader {
+    const SCHEMA: &'static TypeSchema<'static> =
+        &TypeSchema::sized::<ContainerHeader>(
+            "ContainerHeader",
+            SchemaKind::Primitive,
+        );
+}
+
+/// An entry in the directory of a container, which describes one section.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[repr(C)]
+pub struct SectionEntry {
+    /// The offset of the section from the start of the container.
+    pub offset: u64,
+    /// The length of the section in bytes.
+    pub len: u64,
+    /// The tag of the type of the section.
+    pub tag: u64,
+}
+
+// SAFETY: All bit patterns are valid for `SectionEntry`.
+unsafe impl<C: Fallible + ?Sized> CheckBytes<C> for SectionEntry {
+    #[inline]
+    unsafe fn check_bytes(_: *const Self, _: &mut C) -> Result<(), C::Error> {
+        Ok(())
+    }
+}
+
+impl Fingerprint for SectionEntry {
+    const FINGERPRINT: u64 =
+        FingerprintHasher::new().write_str("SectionEntry").finish();
+}
+
+impl Schema for SectionEntry {
+    const SCHEMA: &'static TypeSchema<'static> =
+        &TypeSchema::sized::<SectionEntry>(
+            "SectionEntry",
+            SchemaKind::Primitive,
+        );
+}
+
+/// The expected magic bytes and version of a container.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ContainerConfig {
+    magic: [u8; 8],
+    version: u32,
+}
+
+impl ContainerConfig {
+    /// Returns a new `ContainerConfig` which accepts containers with the
+    /// given magic bytes and version.
+    #[inline]
+    pub const fn new(magic: [u8; 8], version: u32) -> Self {
+        Self { magic, version }
+    }
+
+    /// Returns the expected magic bytes.
+    #[inline]
+    pub const fn magic(&self) -> [u8; 8] {
+        self.magic
+    }
+
+    /// Returns the expected version.
+    #[inline]
+    pub const fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// Checks the header and directory of the container in `bytes`, with the
+    /// types of the sections registered in `registry`.
+    ///
+    /// `bytes` must be aligned for the header, the directory, and each
+    /// registered type. The values in the sections are not checked.
+    #[inline]
+    pub fn open<'a, 'r, E>(
+        self,
+        bytes: &'a [u8],
+        registry: &'r [RecordType<E>],
+    ) -> Result<Container<'a, 'r, E>, E>
+    where
+        E: Source,
+    {
+        self.open_with_context(bytes, registry, ())
+    }
+
+    /// Checks the header and directory of the container in `bytes`, with the
+    /// types of the sections registered in `registry`. The values in the
+    /// sections will be checked within the given context.
+    ///
+    /// See [`open`](ContainerConfig::open) for more details.
+    pub fn open_with_context<'a, 'r, E, C>(
+        self,
+        bytes: &'a [u8],
+        registry: &'r [RecordType<E, C>],
+        context: C,
+    ) -> Result<Container<'a, 'r, E, C>, E>
+    where
+        E: Source,
+    {
+        self.check_directory(bytes, registry)
+            .map(|(header, directory)| Container {
+                bytes,
+                header,
+                directory,
+                registry,
+                context,
+            })
+            .trace(BufferContext::new(bytes))
+    }
+
+    fn check_directory<'a, E, C>(
+        &self,
+        bytes: &'a [u8],
+        registry: &[RecordType<E, C>],
+    ) -> Result<(&'a ContainerHeader, &'a [SectionEntry]), E>
+    where
+        E: Source,
+    {
+        let mut bounds = Bounds::new(bytes);
+
+        let header_layout = Layout::new::<ContainerHeader>();
+        claim::<E>(&mut bounds, bytes.as_ptr(), &header_layout)?;
+        let header =
+            from_bytes::<ContainerHeader, E>(&bytes[..header_layout.size()])?;
+        if header.magic != self.magic {
+            fail!(MagicMismatchError {
+                expected: self.magic,
+                found: header.magic,
+            });
+        }
+        if header.version != self.version {
+            fail!(VersionMismatchError {
+                expected: self.version,
+                found: header.version,
+            });
+        }
+
+        let start = header_layout.size();
+        let directory_ptr = bytes.as_ptr().wrapping_add(start);
+        let directory_layout = saturating_layout(
+            (header.section_count as usize)
+                .saturating_mul(mem::size_of::<SectionEntry>()),
+            mem::align_of::<SectionEntry>(),
+        );
+        claim::<E>(&mut bounds, directory_ptr, &directory_layout)?;
+        let directory = slice_from_bytes::<SectionEntry, E>(
+            &bytes[start..start + directory_layout.size()],
+        )?;
+
+        for (index, entry) in directory.iter().enumerate() {
+            let Some(section_type) =
+                registry.iter().find(|t| t.tag() == entry.tag)
+            else {
+                return Err(E::new(UnknownSectionTypeError { tag: entry.tag })
+                    .trace(SectionContext {
+                        index,
+                        tag: entry.tag,
+                    }));
+            };
+            // Offsets and lengths which don't fit in a `usize` can't be in
+            // bounds, so they're saturated to fail the bounds check.
+            let offset = usize::try_from(entry.offset).unwrap_or(usize::MAX);
+            let len = usize::try_from(entry.len).unwrap_or(usize::MAX);
+            let layout = saturating_layout(len, section_type.layout().align());
+            let ptr = bytes.as_ptr().wrapping_add(offset);
+            claim::<E>(&mut bounds, ptr, &layout).with_trace(|| {
+                SectionContext {
+                    index,
+                    tag: entry.tag,
+                }
+            })?;
+        }
+
+        Ok((header, directory))
+    }
+}
+
+// Returns the layout of `len` bytes with the given alignment. Lengths which
+// are too large for a layout are clamped, since they can't be in bounds.
+fn saturating_layout(len: usize, align: usize) -> Layout {
+    let len = len.min(isize::MAX as usize - (align - 1));
+    Layout::from_size_align(len, align).expect("layout is clamped to be valid")
+}
+
+fn claim<E: Source>(
+    bounds: &mut Bounds<'_>,
+    ptr: *const u8,
+    layout: &Layout,
+) -> Result<(), E> {
+    BoundsContext::<E>::claim_subtree(bounds, ptr, layout)
+}
+
+/// A container whose header and directory have been checked.
+///
+/// Returned by [`ContainerConfig::open`].
+pub struct Container<'a, 'r, E, C = ()> {
+    bytes: &'a [u8],
+    header: &'a ContainerHeader,
+    directory: &'a [SectionEntry],
+    registry: &'r [RecordType<E, C>],
+    context: C,
+}
+
+impl<'a, E, C> Container<'a, '_, E, C> {
+    /// Returns the header of the container.
+    #[inline]
+    pub fn header(&self) -> &'a ContainerHeader {
+        self.header
+    }
+
+    /// Returns the entries of the directory.
+    #[inline]
+    pub fn directory(&self) -> &'a [SectionEntry] {
+        self.directory
+    }
+
+    /// Returns the number of sections.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.directory.len()
+    }
+
+    /// Returns whether the container has no sections.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.directory.is_empty()
+    }
+
+    /// Returns the context.
+    #[inline]
+    pub fn into_context(self) -> C {
+        self.context
+    }
+}
+
+impl<'a, E: Source, C> Container<'a, '_, E, C> {
+    /// Checks the value in the section at `index` and returns a view of it.
+    ///
+    /// The value is checked every time this is called.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn section(&mut self, index: usize) -> Result<Record<'a>, E> {
+        let entry = &self.directory[index];
+        let section_type =
+            self.registry.iter().find(|t| t.tag() == entry.tag).expect(
+                "section types were checked when the container was opened",
+            );
+        // The section was claimed when the container was opened, so it is in
+        // bounds.
+        let offset = entry.offset as usize;
+        let value = &self.bytes[offset..offset + entry.len as usize];
+        section_type
+            .check(offset, value, &mut self.context)
+            .trace(SectionContext {
+                index,
+                tag: entry.tag,
+            })
+            .trace(BufferContext::new(self.bytes))
+    }
+
+    /// Checks the values in all of the sections.
+    pub fn check_all(&mut self) -> Result<(), E> {
+        for index in 0..self.len() {
+            self.section(index)?;
+        }
+        Ok(())
+    }
+}
+
+impl<E, C> fmt::Debug for Container<'_, '_, E, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Container")
+            .field("header", self.header)
+            .field("directory", &self.directory)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Context for errors resulting from invalid sections.
+#[derive(Debug)]
+pub struct SectionContext {
+    /// The index of the section in the directory.
+    pub index: usize,
+    /// The tag of the type of the section.
+    pub tag: u64,
+}
+
+impl fmt::Display for SectionContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "while checking section {} with tag {}",
+            self.index, self.tag,
+        )
+    }
+}
+
+/// An error resulting from a container or
+/// [envelope](crate::envelope::Envelope) with the wrong magic bytes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct MagicMismatchError {
+    /// The expected magic bytes.
+    pub expected: [u8; 8],
+    /// The magic bytes which were found.
+    pub found: [u8; 8],
+}
+
+impl fmt::Display for MagicMismatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "found magic bytes {:02x?}, expected {:02x?}",
+            self.found, self.expected,
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for MagicMismatchError {}
+
+/// An error resulting from a container or
+/// [envelope](crate::envelope::Envelope) with an unsupported version.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct VersionMismatchError {
+    /// The expected version.
+    pub expected: u32,
+    /// The version which was found.
+    pub found: u32,
+}
+
+impl fmt::Display for VersionMismatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "found version {}, expected {}",
+            self.found, self.expected,
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for VersionMismatchError {}
+
+/// An error resulting from a section with a type that isn't registered.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct UnknownSectionTypeError {
+    /// The tag of the type of the section.
+    pub tag: u64,
+}
+
+impl fmt::Display for UnknownSectionTypeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "section has unknown type tag {}", self.tag)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for UnknownSectionTypeError {}
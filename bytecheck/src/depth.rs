@@ -0,0 +1,224 @@
+//! Limits on how deeply validation may recurse.
+//!
+//! Derived implementations of [`CheckBytes`] recurse into their fields, which
+//! is bounded by the size of the type. But implementations that follow
+//! pointers, like those for boxes in recursive types using `#[omit_bounds]`,
+//! can recurse as deep as the input describes. On hostile input this can
+//! overflow the stack.
+//!
+//! Those implementations should call [`check_nested`] (or [`enter`] and
+//! [`exit`]) around checking the pointee. When validating with a
+//! [`DepthLimit`] context, validation then fails with a [`DepthLimitError`]
+//! instead of recursing past the configured maximum depth:
+//!
+//! ```
+//! use bytecheck::{
+//!     check_bytes_with_context,
+//!     depth::{check_nested, DepthContext, DepthLimit},
+//!     rancor::{Failure, Fallible},
+//!     CheckBytes,
+//! };
+//!
+//! struct MyBox<T> {
+//!     inner: *const T,
+//! }
+//!
+//! unsafe impl<T, C> CheckBytes<C> for MyBox<T>
+//! where
+//!     T: CheckBytes<C>,
+//!     C: Fallible + DepthContext + ?Sized,
+//! {
+//!     unsafe fn check_bytes(
+//!         value: *const Self,
+//!         context: &mut C,
+//!     ) -> Result<(), C::Error> {
+//!         // SAFETY: The caller has guaranteed that `value` points to a valid
+//!         // `MyBox<T>`, whose pointer always points to a valid `T`.
+//!         unsafe { check_nested((*value).inner, context) }
+//!     }
+//! }
+//!
+//! #[derive(CheckBytes)]
+//! #[check_bytes(bounds(__C: DepthContext))]
+//! #[repr(u8)]
+//! enum Node {
+//!     Nil,
+//!     Cons(#[omit_bounds] MyBox<Node>),
+//! }
+//!
+//! let nil = Node::Nil;
+//! let one = Node::Cons(MyBox { inner: &nil });
+//! let two = Node::Cons(MyBox { inner: &one });
+//!
+//! let check = |max_depth| unsafe {
+//!     check_bytes_with_context::<_, _, Failure>(
+//!         &two,
+//!         &mut DepthLimit::new(max_depth),
+//!     )
+//! };
+//! assert!(check(2).is_ok());
+//! assert!(check(1).is_err());
+//! ```
+
+use core::fmt;
+
+use rancor::{fail, Fallible, Source, Strategy};
+
+use crate::CheckBytes;
+
+/// A context which tracks how deeply nested the value being checked is.
+pub trait DepthContext<E = <Self as Fallible>::Error> {
+    /// Enters a nested value, returning an error if doing so would exceed the
+    /// maximum depth.
+    fn enter(&mut self) -> Result<(), E>;
+
+    /// Exits a nested value previously entered with
+    /// [`enter`](DepthContext::enter).
+    ///
+    /// Every call must be paired with an earlier successful call to `enter`.
+    fn exit(&mut self);
+}
+
+impl<T, E> DepthContext<E> for Strategy<T, E>
+where
+    T: DepthContext<E> + ?Sized,
+{
+    #[inline]
+    fn enter(&mut self) -> Result<(), E> {
+        T::enter(self)
+    }
+
+    #[inline]
+    fn exit(&mut self) {
+        T::exit(self)
+    }
+}
+
+/// Enters a nested value in the given context.
+///
+/// Every successful call must be paired with a later call to [`exit`].
+#[inline]
+pub fn enter<C>(context: &mut C) -> Result<(), C::Error>
+where
+    C: Fallible + DepthContext + ?Sized,
+{
+    context.enter()
+}
+
+/// Exits a nested value previously entered with [`enter`].
+#[inline]
+pub fn exit<C>(context: &mut C)
+where
+    C: Fallible + DepthContext + ?Sized,
+{
+    context.exit()
+}
+
+/// Checks a nested value one level deeper than the current value.
+///
+/// # Safety
+///
+/// `value` must satisfy the same requirements as for
+/// [`CheckBytes::check_bytes`].
+#[inline]
+pub unsafe fn check_nested<T, C>(
+    value: *const T,
+    context: &mut C,
+) -> Result<(), C::Error>
+where
+    T: CheckBytes<C> + ?Sized,
+    C: Fallible + DepthContext + ?Sized,
+{
+    context.enter()?;
+    // SAFETY: The caller has guaranteed that `value` meets the requirements of
+    // `check_bytes`.
+    let result = unsafe { T::check_bytes(value, context) };
+    context.exit();
+    result
+}
+
+/// A [`DepthContext`] which fails when values are nested more than a maximum
+/// depth.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DepthLimit {
+    depth: usize,
+    max_depth: usize,
+}
+
+impl DepthLimit {
+    /// The maximum depth used by [`DepthLimit::default`].
+    pub const DEFAULT_MAX_DEPTH: usize = 128;
+
+    /// Returns a new `DepthLimit` which allows values to be nested up to
+    /// `max_depth` levels deep.
+    #[inline]
+    pub fn new(max_depth: usize) -> Self {
+        Self {
+            depth: 0,
+            max_depth,
+        }
+    }
+
+    /// Returns the current depth.
+    #[inline]
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Returns the maximum depth.
+    #[inline]
+    pub fn max_depth(&self) -> usize {
+        self.max_depth
+    }
+}
+
+impl Default for DepthLimit {
+    #[inline]
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_MAX_DEPTH)
+    }
+}
+
+impl<E: Source> DepthContext<E> for DepthLimit {
+    fn enter(&mut self) -> Result<(), E> {
+        if self.depth >= self.max_depth {
+            fail!(DepthLimitError {
+                depth: self.depth + 1,
+                max_depth: self.max_depth,
+            });
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    #[inline]
+    fn exit(&mut self) {
+        debug_assert!(
+            self.depth > 0,
+            "`DepthLimit::exit` called without a matching `enter`",
+        );
+        self.depth = self.depth.saturating_sub(1);
+    }
+}
+
+/// An error resulting from values being nested more deeply than allowed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct DepthLimitError {
+    /// The depth of the value that would have been checked.
+    pub depth: usize,
+    /// The maximum allowed depth.
+    pub max_depth: usize,
+}
+
+impl fmt::Display for DepthLimitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "value nested at depth {} exceeds the maximum depth of {}",
+            self.depth, self.max_depth,
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for DepthLimitError {}
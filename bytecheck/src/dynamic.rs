@@ -0,0 +1,431 @@
+//! Validation against layout schemas which are only known at runtime.
+//!
+//! Tools like format inspectors have to check values of types they weren't
+//! compiled with. [`check_schema`] checks a buffer against a [`TypeSchema`]
+//! instead of a `CheckBytes` type, with the same rules as the built-in and
+//! derived implementations. Failures have the same kinds, paths, and locations
+//! as their static counterparts, except that invalid enum discriminants fail
+//! with an [`InvalidSchemaDiscriminantError`].
+//!
+//! Schemas can come from [`Schema`](crate::schema::Schema) implementations or
+//! be built at runtime. They must be `'static` because their names are used in
+//! errors, so schemas built at runtime usually have to be leaked. Checking
+//! never reads outside of the buffer, even if the schema doesn't describe any
+//! real type. Schemas which can't be checked fail with an
+//! [`InvalidSchemaError`]. These include opaque schemas, like those of types
+//! with a [`Verify`](crate::Verify) hook, since the checks they need aren't
+//! known at runtime.
+//!
+//! ```
+//! use bytecheck::{
+//!     dynamic::check_schema,
+//!     rancor::Failure,
+//!     schema::{FieldSchema, Schema, SchemaKind, TypeSchema},
+//! };
+//!
+//! const PAIR: TypeSchema<'static> = TypeSchema {
+//!     name: "Pair",
+//!     size: Some(2),
+//!     align: 1,
+//!     kind: SchemaKind::Struct {
+//!         repr: "C",
+//!         fields: &[
+//!             FieldSchema {
+//!                 name: Some("flag"),
+//!                 offset: 0,
+//!                 schema: bool::SCHEMA,
+//!             },
+//!             FieldSchema {
+//!                 name: Some("byte"),
+//!                 offset: 1,
+//!                 schema: u8::SCHEMA,
+//!             },
+//!         ],
+//!     },
+//! };
+//!
+//! check_schema::<Failure>(&[1, 255], &PAIR).unwrap();
+//! assert!(check_schema::<Failure>(&[2, 255], &PAIR).is_err());
+//! ```
+
+use core::fmt;
+
+use rancor::{fail, ResultExt as _, Source};
+
+use crate::{
+    budget::BudgetContext,
+    check_buffer,
+    error::ErrorKind,
+    fmt_invalid_discriminant,
+    schema::{FieldSchema, SchemaKind, TypeSchema, VariantSchema},
+    ArrayCheckContext, BufferContext, InvalidEnumDiscriminantError,
+    LocationContext, NamedEnumVariantCheckContext, SliceCheckContext,
+    StructCheckContext, TupleStructCheckContext,
+    UnnamedEnumVariantCheckContext,
+};
+
+/// Checks whether the given bytes are a valid value of the type described by
+/// `schema`.
+///
+/// The buffer must be aligned to the alignment of the schema. If the schema is
+/// sized, the buffer must be exactly as long as it. A `str` schema takes up
+/// the whole buffer, and a slice schema takes up as many whole elements as
+/// fit in the buffer, like [`slice_from_bytes`](crate::slice_from_bytes).
+#[inline]
+pub fn check_schema<E: Source>(
+    bytes: &[u8],
+    schema: &'static TypeSchema<'static>,
+) -> Result<(), E> {
+    check_schema_with_context(bytes, schema, &mut ())
+}
+
+/// Checks whether the given bytes are a valid value of the type described by
+/// `schema` within the given context.
+///
+/// The context is charged for work the same way as the static
+/// implementations. See [`check_schema`] for more details.
+pub fn check_schema_with_context<C, E>(
+    bytes: &[u8],
+    schema: &'static TypeSchema<'static>,
+    context: &mut C,
+) -> Result<(), E>
+where
+    C: BudgetContext<E> + ?Sized,
+    E: Source,
+{
+    if !schema.align.is_power_of_two() {
+        fail!(InvalidSchemaError {
+            type_name: schema.name,
+            reason: "has an alignment which is not a power of two",
+        });
+    }
+    let expected = match (schema.size, schema.kind) {
+        (Some(size), _) => size,
+        (None, SchemaKind::Slice { element }) => match element.size {
+            Some(0) => 0,
+            Some(size) => bytes.len() / size * size,
+            None => fail!(InvalidSchemaError {
+                type_name: element.name,
+                reason: "is unsized",
+            }),
+        },
+        (None, _) => bytes.len(),
+    };
+    check_buffer::<E>(bytes, schema.align, expected)?;
+    check_value(bytes, schema, context).trace(BufferContext::new(bytes))
+}
+
+/// Returns the location of `bytes` for error traces.
+#[inline]
+fn location(bytes: &[u8]) -> LocationContext {
+    LocationContext {
+        address: bytes.as_ptr() as usize,
+        len: bytes.len(),
+    }
+}
+
+/// Returns an error for a schema which can't be checked.
+#[inline]
+fn invalid<E: Source>(
+    bytes: &[u8],
+    schema: &'static TypeSchema<'static>,
+    reason: &'static str,
+) -> E {
+    E::new(InvalidSchemaError {
+        type_name: schema.name,
+        reason,
+    })
+    .trace(location(bytes))
+}
+
+/// Returns the bytes of the value described by `schema` at `offset` from the
+/// start of `bytes`.
+fn value_bytes<'a, E: Source>(
+    bytes: &'a [u8],
+    offset: usize,
+    schema: &'static TypeSchema<'static>,
+) -> Result<&'a [u8], E> {
+    let Some(size) = schema.size else {
+        return Err(invalid(bytes, schema, "is unsized"));
+    };
+    offset
+        .checked_add(size)
+        .and_then(|end| bytes.get(offset..end))
+        .ok_or_else(|| invalid(bytes, schema, "extends past its parent"))
+}
+
+/// Checks that `bytes` are a valid value of the type described by `schema`.
+///
+/// `bytes` must contain exactly the bytes of the value.
+fn check_value<C, E>(
+    bytes: &[u8],
+    schema: &'static TypeSchema<'static>,
+    context: &mut C,
+) -> Result<(), E>
+where
+    C: BudgetContext<E> + ?Sized,
+    E: Source,
+{
+    match schema.kind {
+        SchemaKind::Primitive => Ok(()),
+        SchemaKind::Bool => {
+            match *bytes {
+                [0 | 1] => Ok(()),
+                [byte] => Err(E::new(ErrorKind::InvalidBool { byte })
+                    .trace(location(bytes))),
+                _ => Err(invalid(bytes, schema, "is not one byte long")),
+            }
+        }
+        SchemaKind::Char => {
+            let Ok(value) = bytes.try_into().map(u32::from_ne_bytes) else {
+                return Err(invalid(bytes, schema, "is not four bytes long"));
+            };
+            if char::from_u32(value).is_none() {
+                return Err(E::new(ErrorKind::InvalidChar { value })
+                    .trace(location(bytes)));
+            }
+            Ok(())
+        }
+        SchemaKind::NonZero => {
+            if bytes.iter().all(|&byte| byte == 0) {
+                return Err(
+                    E::new(ErrorKind::ZeroNonZero).trace(location(bytes))
+                );
+            }
+            Ok(())
+        }
+        SchemaKind::Array { element, len } => {
+            let stride = element.size.unwrap_or(0);
+            for index in 0..len {
+                let Some(offset) = index.checked_mul(stride) else {
+                    return Err(invalid(bytes, schema, "is too large"));
+                };
+                let element_bytes = value_bytes(bytes, offset, element)?;
+                check_value(element_bytes, element, context)
+                    .with_trace(|| ArrayCheckContext { index })?;
+            }
+            Ok(())
+        }
+        SchemaKind::Slice { element } => {
+            let Some(stride) = element.size else {
+                return Err(invalid(bytes, element, "is unsized"));
+            };
+            let len = bytes.len().checked_div(stride).unwrap_or(0);
+            context.charge(len)?;
+            for index in 0..len {
+                let element_bytes =
+                    value_bytes(bytes, index * stride, element)?;
+                check_value(element_bytes, element, context)
+                    .with_trace(|| SliceCheckContext { index })?;
+            }
+            Ok(())
+        }
+        SchemaKind::Str => {
+            context.charge(bytes.len())?;
+            if let Err(e) = core::str::from_utf8(bytes) {
+                return Err(E::new(ErrorKind::InvalidUtf8 {
+                    valid_up_to: e.valid_up_to(),
+                })
+                .trace(location(bytes)));
+            }
+            Ok(())
+        }
+        SchemaKind::Struct { fields, .. } => {
+            for (i, field) in fields.iter().enumerate() {
+                let result = check_field(bytes, field, context);
+                match field.name {
+                    Some(field_name) => {
+                        result.with_trace(|| StructCheckContext {
+                            struct_name: schema.name,
+                            field_name,
+                        })?
+                    }
+                    None => result.with_trace(|| TupleStructCheckContext {
+                        tuple_struct_name: schema.name,
+                        field_index: i,
+                    })?,
+                }
+            }
+            Ok(())
+        }
+        SchemaKind::Enum { tag, variants, .. } => {
+            let tag_bytes = value_bytes(bytes, 0, tag)?;
+            let Some((discriminant, signed)) =
+                read_discriminant(tag.name, tag_bytes)
+            else {
+                return Err(invalid(bytes, tag, "is not an integer"));
+            };
+            let Some(variant) =
+                variants.iter().find(|v| v.discriminant == discriminant)
+            else {
+                return Err(E::new(InvalidSchemaDiscriminantError {
+                    enum_name: schema.name,
+                    invalid_discriminant: discriminant,
+                    signed,
+                    variants,
+                })
+                .trace(location(tag_bytes)));
+            };
+            for (i, field) in variant.fields.iter().enumerate() {
+                let result = check_field(bytes, field, context);
+                match field.name {
+                    Some(field_name) => {
+                        result.with_trace(|| NamedEnumVariantCheckContext {
+                            enum_name: schema.name,
+                            variant_name: variant.name,
+                            field_name,
+                        })?
+                    }
+                    // Fields of tuple variants come after the tag.
+                    None => result.with_trace(|| {
+                        UnnamedEnumVariantCheckContext {
+                            enum_name: schema.name,
+                            variant_name: variant.name,
+                            field_index: i + 1,
+                        }
+                    })?,
+                }
+            }
+            Ok(())
+        }
+        SchemaKind::Opaque => Err(invalid(bytes, schema, "is opaque")),
+    }
+}
+
+/// Checks the field described by `field` of the value in `bytes`.
+#[inline]
+fn check_field<C, E>(
+    bytes: &[u8],
+    field: &'static FieldSchema<'static>,
+    context: &mut C,
+) -> Result<(), E>
+where
+    C: BudgetContext<E> + ?Sized,
+    E: Source,
+{
+    let field_bytes = value_bytes(bytes, field.offset, field.schema)?;
+    check_value(field_bytes, field.schema, context)
+}
+
+/// Reads a discriminant of the integer type named `name` from `bytes`.
+///
+/// Returns the discriminant converted to a `u128` with `as`, and whether the
+/// integer type is signed.
+fn read_discriminant(name: &str, bytes: &[u8]) -> Option<(u128, bool)> {
+    macro_rules! read {
+        ($unsigned:ty, $signed:ty) => {{
+            let value = <$unsigned>::from_ne_bytes(bytes.try_into().ok()?);
+            if name.starts_with('i') {
+                (i128::from(value as $signed) as u128, true)
+            } else {
+                (u128::from(value), false)
+            }
+        }};
+    }
+
+    Some(match name {
+        "u8" | "i8" => read!(u8, i8),
+        "u16" | "i16" => read!(u16, i16),
+        "u32" | "i32" => read!(u32, i32),
+        "u64" | "i64" => read!(u64, i64),
+        "u128" | "i128" => read!(u128, i128),
+        _ => return None,
+    })
+}
+
+/// An error resulting from a schema which can't be used to check values.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct InvalidSchemaError {
+    /// The name of the type with the invalid schema.
+    pub type_name: &'static str,
+    /// Why the schema can't be used, like `"is opaque"`.
+    pub reason: &'static str,
+}
+
+impl fmt::Display for InvalidSchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "schema for type '{}' {}", self.type_name, self.reason)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for InvalidSchemaError {}
+
+/// An error resulting from an invalid discriminant of an enum described by a
+/// schema.
+///
+/// This displays the same message as [`InvalidEnumDiscriminantError`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct InvalidSchemaDiscriminantError {
+    /// The name of the enum with an invalid discriminant.
+    pub enum_name: &'static str,
+    /// The invalid discriminant, converted to a `u128` with `as`.
+    pub invalid_discriminant: u128,
+    /// Whether the discriminant is a signed integer.
+    pub signed: bool,
+    /// The variants of the enum, which have the valid discriminants.
+    pub variants: &'static [VariantSchema<'static>],
+}
+
+impl InvalidSchemaDiscriminantError {
+    /// Returns the variant with the discriminant closest to the invalid
+    /// discriminant, if it differs by at most two.
+    pub fn closest_variant(&self) -> Option<&'static VariantSchema<'static>> {
+        let distance = |v: &VariantSchema<'_>| {
+            if self.signed {
+                (v.discriminant as i128)
+                    .abs_diff(self.invalid_discriminant as i128)
+            } else {
+                v.discriminant.abs_diff(self.invalid_discriminant)
+            }
+        };
+        self.variants
+            .iter()
+            .min_by_key(|v| distance(v))
+            .filter(|v| {
+                distance(v)
+                    <= InvalidEnumDiscriminantError::<u128>::CLOSE_DISTANCE
+            })
+    }
+
+    #[inline]
+    fn display(&self, discriminant: u128) -> DisplayDiscriminant {
+        DisplayDiscriminant {
+            discriminant,
+            signed: self.signed,
+        }
+    }
+}
+
+impl fmt::Display for InvalidSchemaDiscriminantError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt_invalid_discriminant(
+            f,
+            self.enum_name,
+            self.display(self.invalid_discriminant),
+            self.variants
+                .iter()
+                .map(|v| (v.name, self.display(v.discriminant))),
+            self.closest_variant()
+                .map(|v| (v.name, self.display(v.discriminant))),
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for InvalidSchemaDiscriminantError {}
+
+struct DisplayDiscriminant {
+    discriminant: u128,
+    signed: bool,
+}
+
+impl fmt::Display for DisplayDiscriminant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.signed {
+            write!(f, "{}", self.discriminant as i128)
+        } else {
+            write!(f, "{}", self.discriminant)
+        }
+    }
+}
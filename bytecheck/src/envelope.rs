@@ -0,0 +1,288 @@
+//! Self-describing envelopes around values.
+//!
+//! An [`Envelope`] stores an [`EnvelopeHeader`] before a value. The header
+//! records the magic bytes and version of the format, the
+//! [fingerprint](crate::fingerprint) of the value's type, and the length and
+//! alignment of the value. Checking an envelope checks the header against the
+//! expected format and type first, so bytes written for a different format,
+//! version, or type are rejected before the value is checked.
+//!
+//! The format is described by a marker type implementing [`EnvelopeFormat`]:
+//!
+//! ```
+//! use bytecheck::{
+//!     check_bytes,
+//!     envelope::{Envelope, EnvelopeFormat},
+//!     rancor::Failure,
+//! };
+//!
+//! struct MyFormat;
+//!
+//! impl EnvelopeFormat for MyFormat {
+//!     const MAGIC: [u8; 8] = *b"MYFORMAT";
+//!     const VERSION: u32 = 1;
+//! }
+//!
+//! let envelope = Envelope::<u32, MyFormat>::new(42);
+//! unsafe {
+//!     check_bytes::<_, Failure>(&envelope).unwrap();
+//! }
+//! assert_eq!(*envelope.value(), 42);
+//!
+//! // The same bytes aren't a valid envelope for an `i32`.
+//! let ptr = (&envelope as *const Envelope<u32, MyFormat>)
+//!     .cast::<Envelope<i32, MyFormat>>();
+//! unsafe {
+//!     assert!(check_bytes::<_, Failure>(ptr).is_err());
+//! }
+//! ```
+
+use core::{fmt, marker::PhantomData, mem, ptr::addr_of};
+
+use rancor::{Fallible, ResultExt as _, Source, Trace};
+
+use crate::{
+    container::{MagicMismatchError, VersionMismatchError},
+    fingerprint::{Fingerprint, FingerprintHasher},
+    schema::{Schema, SchemaKind, TypeSchema},
+    CheckBytes, LocationContext, StructCheckContext,
+};
+
+/// The magic bytes and version of a format which uses envelopes.
+pub trait EnvelopeFormat {
+    /// Bytes which identify the format.
+    const MAGIC: [u8; 8];
+    /// The version of the format.
+    const VERSION: u32;
+}
+
+/// The header of an [`Envelope`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[repr(C)]
+pub struct EnvelopeHeader {
+    /// Bytes which identify the format of the envelope.
+    pub magic: [u8; 8],
+    /// The version of the format of the envelope.
+    pub version: u32,
+    /// The alignment of the value in bytes.
+    pub align: u32,
+    /// The fingerprint of the type of the value.
+    pub fingerprint: u64,
+    /// The length of the value in bytes.
+    pub len: u64,
+}
+
+impl EnvelopeHeader {
+    /// Returns the header for an envelope containing a `T` in the format `F`.
+    #[inline]
+    pub const fn new<T: Fingerprint, F: EnvelopeFormat>() -> Self {
+        Self {
+            magic: F::MAGIC,
+            version: F::VERSION,
+            align: mem::align_of::<T>() as u32,
+            fingerprint: T::FINGERPRINT,
+            len: mem::size_of::<T>() as u64,
+        }
+    }
+}
+
+// SAFETY: All bit patterns are valid for `EnvelopeHeader`.
+unsafe impl<C: Fallible + ?Sized> CheckBytes<C> for EnvelopeHeader {
+    #[inline]
+    unsafe fn check_bytes(_: *const Self, _: &mut C) -> Result<(), C::Error> {
+        Ok(())
+    }
+}
+
+impl Fingerprint for EnvelopeHeader {
+    const FINGERPRINT: u64 = FingerprintHasher::new()
+        .write_str("EnvelopeHeader")
+        .finish();
+}
+
+impl Schema for EnvelopeHeader {
+    const SCHEMA: &'static TypeSchema<'static> =
+        &TypeSchema::sized::<EnvelopeHeader>(
+            "EnvelopeHeader",
+            SchemaKind::Primitive,
+        );
+}
+
+/// A value preceded by a header describing its format and type.
+#[repr(C)]
+pub struct Envelope<T, F> {
+    header: EnvelopeHeader,
+    value: T,
+    _format: PhantomData<fn() -> F>,
+}
+
+impl<T: Fingerprint, F: EnvelopeFormat> Envelope<T, F> {
+    /// Returns a new envelope containing `value`.
+    #[inline]
+    pub const fn new(value: T) -> Self {
+        Self {
+            header: EnvelopeHeader::new::<T, F>(),
+            value,
+            _format: PhantomData,
+        }
+    }
+}
+
+impl<T, F> Envelope<T, F> {
+    /// Returns the header of the envelope.
+    #[inline]
+    pub fn header(&self) -> &EnvelopeHeader {
+        &self.header
+    }
+
+    /// Returns the value in the envelope.
+    #[inline]
+    pub fn value(&self) -> &T {
+        &self.value
+    }
+
+    /// Returns the value in the envelope, consuming it.
+    #[inline]
+    pub fn into_value(self) -> T {
+        self.value
+    }
+}
+
+impl<T: fmt::Debug, F> fmt::Debug for Envelope<T, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Envelope")
+            .field("header", &self.header)
+            .field("value", &self.value)
+            .finish()
+    }
+}
+
+// SAFETY: `check_bytes` only returns `Ok` if the value is a valid `T`. The
+// header has no invalid bit patterns.
+unsafe impl<T, F, C> CheckBytes<C> for Envelope<T, F>
+where
+    T: CheckBytes<C> + Fingerprint,
+    F: EnvelopeFormat,
+    C: Fallible + ?Sized,
+    C::Error: Source,
+{
+    unsafe fn check_bytes(
+        value: *const Self,
+        context: &mut C,
+    ) -> Result<(), C::Error> {
+        // SAFETY: The caller has guaranteed that `value` is aligned and points
+        // to enough bytes for an `Envelope`, and all bit patterns are valid
+        // for the header.
+        let header = unsafe { &*addr_of!((*value).header) };
+        if header.magic != F::MAGIC {
+            return Err(C::Error::new(MagicMismatchError {
+                expected: F::MAGIC,
+                found: header.magic,
+            })
+            .trace(LocationContext::new(addr_of!(header.magic))));
+        }
+        if header.version != F::VERSION {
+            return Err(C::Error::new(VersionMismatchError {
+                expected: F::VERSION,
+                found: header.version,
+            })
+            .trace(LocationContext::new(addr_of!(header.version))));
+        }
+        if header.fingerprint != T::FINGERPRINT {
+            return Err(C::Error::new(EnvelopeFingerprintError {
+                expected: T::FINGERPRINT,
+                found: header.fingerprint,
+            })
+            .trace(LocationContext::new(addr_of!(header.fingerprint))));
+        }
+        let expected = EnvelopeHeader::new::<T, F>();
+        if header.len != expected.len || header.align != expected.align {
+            return Err(C::Error::new(EnvelopeLayoutError {
+                expected_len: expected.len,
+                expected_align: expected.align,
+                found_len: header.len,
+                found_align: header.align,
+            })
+            .trace(LocationContext::new(header as *const EnvelopeHeader)));
+        }
+
+        // SAFETY: The caller has guaranteed that `value` points to enough
+        // bytes for an `Envelope`, so the field is in bounds and aligned.
+        unsafe {
+            T::check_bytes(addr_of!((*value).value), context).with_trace(|| {
+                StructCheckContext {
+                    struct_name: "Envelope",
+                    field_name: "value",
+                }
+            })
+        }
+    }
+}
+
+impl<T: Fingerprint, F: EnvelopeFormat> Fingerprint for Envelope<T, F> {
+    const FINGERPRINT: u64 = FingerprintHasher::new()
+        .write_str("Envelope")
+        .write_u64(u64::from_le_bytes(F::MAGIC))
+        .write_u64(F::VERSION as u64)
+        .write_u64(T::FINGERPRINT)
+        .finish();
+}
+
+// Schemas can't describe the checks on the header, so an `Envelope` is opaque.
+impl<T, F> Schema for Envelope<T, F> {
+    const SCHEMA: &'static TypeSchema<'static> =
+        &TypeSchema::sized::<Envelope<T, F>>("Envelope", SchemaKind::Opaque);
+}
+
+/// An error resulting from an envelope containing a value of a different type.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct EnvelopeFingerprintError {
+    /// The fingerprint of the expected type.
+    pub expected: u64,
+    /// The fingerprint stored in the envelope.
+    pub found: u64,
+}
+
+impl fmt::Display for EnvelopeFingerprintError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "envelope has type fingerprint {:#018x}, expected {:#018x}",
+            self.found, self.expected,
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for EnvelopeFingerprintError {}
+
+/// An error resulting from an envelope whose value has the wrong length or
+/// alignment.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct EnvelopeLayoutError {
+    /// The expected length of the value in bytes.
+    pub expected_len: u64,
+    /// The expected alignment of the value in bytes.
+    pub expected_align: u32,
+    /// The length of the value stored in the envelope.
+    pub found_len: u64,
+    /// The alignment of the value stored in the envelope.
+    pub found_align: u32,
+}
+
+impl fmt::Display for EnvelopeLayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "envelope has a value of {} bytes aligned to {}, expected {} \
+             bytes aligned to {}",
+            self.found_len,
+            self.found_align,
+            self.expected_len,
+            self.expected_align,
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for EnvelopeLayoutError {}
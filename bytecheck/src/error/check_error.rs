@@ -0,0 +1,180 @@
+use core::{any::Any, fmt};
+use std::error::Error;
+
+use rancor::{Source, Trace};
+
+use crate::{
+    error::{Diagnostic, ErrorKind, Location},
+    path::{write_json_str, Path},
+    BufferContext, LocationContext,
+};
+
+/// An error which records the structured [`Path`] to the value that failed
+/// validation.
+///
+/// Trace contexts added by bytecheck are recorded as path segments. All other
+/// traces are kept as messages and displayed after the path.
+///
+/// When checked through a safe entry point like
+/// [`from_bytes`](crate::from_bytes), the error also records the
+/// [`Location`] of the invalid value within the checked buffer.
+pub struct CheckError {
+    source: Box<dyn Error + Send + Sync + 'static>,
+    kind: Option<ErrorKind>,
+    path: Path,
+    traces: Vec<String>,
+    // The address and length of the innermost invalid value.
+    value: Option<(usize, usize)>,
+    // The address of the start of the checked buffer.
+    buffer: Option<usize>,
+}
+
+impl CheckError {
+    /// Returns the kind of the error, if it was produced by a built-in
+    /// `CheckBytes` implementation or a failed [`Verify`](crate::Verify).
+    #[inline]
+    pub fn kind(&self) -> Option<ErrorKind> {
+        self.kind
+    }
+
+    /// Returns the location of the invalid value within the checked buffer.
+    ///
+    /// Returns `None` if the value was not checked through a safe entry point,
+    /// or if the check that failed did not record the location of the value.
+    pub fn location(&self) -> Option<Location> {
+        let (address, len) = self.value?;
+        Some(Location {
+            offset: address.checked_sub(self.buffer?)?,
+            len,
+        })
+    }
+
+    /// Returns the path to the value that failed validation.
+    #[inline]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the trace messages which were not recognized as path segments,
+    /// from the innermost to the outermost.
+    #[inline]
+    pub fn traces(&self) -> impl ExactSizeIterator<Item = &str> {
+        self.traces.iter().map(String::as_str)
+    }
+
+    /// Returns a value which displays the error as JSON.
+    ///
+    /// The output has the form
+    /// `{"message":"...","offset":...,"len":...,"path":"...","segments":[...]}`
+    /// where `message` is the display output of the underlying error, `offset`
+    /// and `len` are the [`Location`] of the invalid value (or `null`), and
+    /// the remaining fields are the same as those of [`Path::json`].
+    #[inline]
+    pub fn json(&self) -> CheckErrorJson<'_> {
+        CheckErrorJson { error: self }
+    }
+}
+
+impl fmt::Debug for CheckError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CheckError")
+            .field("source", &self.source)
+            .field("kind", &self.kind)
+            .field("path", &self.path)
+            .field("traces", &self.traces)
+            .field("location", &self.location())
+            .finish()
+    }
+}
+
+impl fmt::Display for CheckError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.source)?;
+        if !self.path.is_empty() {
+            write!(f, "\nat {}", self.path)?;
+        }
+        if let Some(Location { offset, len }) = self.location() {
+            write!(f, "\nat bytes {}..{}", offset, offset + len)?;
+        }
+        for trace in self.traces.iter() {
+            write!(f, "\n{trace}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for CheckError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&*self.source)
+    }
+}
+
+impl Trace for CheckError {
+    fn trace<R>(mut self, trace: R) -> Self
+    where
+        R: fmt::Debug + fmt::Display + Send + Sync + 'static,
+    {
+        let any = &trace as &dyn Any;
+        if let Some(location) = any.downcast_ref::<LocationContext>() {
+            if self.value.is_none() {
+                self.value = Some((location.address, location.len));
+            }
+        } else if let Some(buffer) = any.downcast_ref::<BufferContext>() {
+            self.buffer = Some(buffer.address);
+        } else if let Some(kind) = ErrorKind::from_trace(any) {
+            self.kind.get_or_insert(kind);
+        } else if !self.path.push_trace(&trace) {
+            self.traces.push(trace.to_string());
+        }
+        self
+    }
+}
+
+impl Source for CheckError {
+    fn new<T: Error + Send + Sync + 'static>(source: T) -> Self {
+        Self {
+            kind: ErrorKind::from_source(&source),
+            source: Box::new(source),
+            path: Path::new(),
+            traces: Vec::new(),
+            value: None,
+            buffer: None,
+        }
+    }
+}
+
+impl Diagnostic for CheckError {
+    fn location(&self) -> Option<Location> {
+        CheckError::location(self)
+    }
+
+    fn fmt_path(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.path, f)
+    }
+
+    fn fmt_reason(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.source, f)
+    }
+}
+
+/// Displays a [`CheckError`] as JSON.
+///
+/// Returned by [`CheckError::json`].
+pub struct CheckErrorJson<'a> {
+    error: &'a CheckError,
+}
+
+impl fmt::Display for CheckErrorJson<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("{\"message\":")?;
+        write_json_str(&self.error.source.to_string(), f)?;
+        match self.error.location() {
+            Some(Location { offset, len }) => {
+                write!(f, ",\"offset\":{offset},\"len\":{len},")?
+            }
+            None => f.write_str(",\"offset\":null,\"len\":null,")?,
+        }
+        self.error.path.write_json_fields(f)?;
+        f.write_str("}")
+    }
+}
@@ -0,0 +1,166 @@
+use core::{any::Any, fmt};
+
+use rancor::{Source, Trace};
+
+use crate::{
+    error::{Diagnostic, ErrorKind, Location},
+    path::Segment,
+    BufferContext, LocationContext,
+};
+
+/// An error which records the [`ErrorKind`] and the innermost `N` segments of
+/// the path to the value that failed validation, without allocating.
+///
+/// Segments beyond the first `N` are counted but not stored, and the path is
+/// displayed with a leading `...` to show that it was truncated. The message
+/// of the source error is not kept, so errors which don't have a kind are only
+/// reported as having failed.
+///
+/// Like `CheckError`, this also records the [`Location`] of the invalid value
+/// when checked through a safe entry point.
+#[derive(Clone, Copy, Debug)]
+pub struct InlineCheckError<const N: usize = 8> {
+    kind: Option<ErrorKind>,
+    // Stored innermost first, since that's the order they're added in.
+    segments: [Option<Segment>; N],
+    len: usize,
+    truncated: usize,
+    value: Option<(usize, usize)>,
+    buffer: Option<usize>,
+}
+
+impl<const N: usize> InlineCheckError<N> {
+    fn with_kind(kind: Option<ErrorKind>) -> Self {
+        Self {
+            kind,
+            segments: [None; N],
+            len: 0,
+            truncated: 0,
+            value: None,
+            buffer: None,
+        }
+    }
+
+    /// Returns the kind of the error, if it was produced by a built-in
+    /// `CheckBytes` implementation or a failed [`Verify`](crate::Verify).
+    #[inline]
+    pub fn kind(&self) -> Option<ErrorKind> {
+        self.kind
+    }
+
+    /// Returns an iterator over the recorded segments of the path, from the
+    /// outermost to the innermost.
+    #[inline]
+    pub fn segments(&self) -> impl DoubleEndedIterator<Item = &Segment> {
+        self.segments[..self.len].iter().rev().flatten()
+    }
+
+    /// Returns the number of outer segments of the path which were not
+    /// recorded because the path was longer than `N` segments.
+    #[inline]
+    pub fn truncated(&self) -> usize {
+        self.truncated
+    }
+
+    /// Returns the location of the invalid value within the checked buffer.
+    ///
+    /// Returns `None` if the value was not checked through a safe entry point,
+    /// or if the check that failed did not record the location of the value.
+    pub fn location(&self) -> Option<Location> {
+        let (address, len) = self.value?;
+        Some(Location {
+            offset: address.checked_sub(self.buffer?)?,
+            len,
+        })
+    }
+
+    fn push_outer(&mut self, segment: Segment) {
+        if self.len < N {
+            self.segments[self.len] = Some(segment);
+            self.len += 1;
+        } else {
+            self.truncated += 1;
+        }
+    }
+}
+
+impl<const N: usize> fmt::Display for InlineCheckError<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            Some(kind) => write!(f, "{kind}")?,
+            None => write!(f, "validation failed")?,
+        }
+        if self.len != 0 || self.truncated != 0 {
+            f.write_str("\nat ")?;
+            Diagnostic::fmt_path(self, f)?;
+        }
+        if let Some(Location { offset, len }) = self.location() {
+            write!(f, "\nat bytes {}..{}", offset, offset + len)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+impl<const N: usize> std::error::Error for InlineCheckError<N> {}
+
+impl<const N: usize> Diagnostic for InlineCheckError<N> {
+    fn location(&self) -> Option<Location> {
+        InlineCheckError::location(self)
+    }
+
+    fn fmt_path(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.truncated != 0 {
+            f.write_str("...")?;
+        }
+        for (i, segment) in self.segments().enumerate() {
+            segment.render(i == 0 && self.truncated == 0, f)?;
+        }
+        Ok(())
+    }
+
+    fn fmt_reason(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            Some(kind) => write!(f, "{kind}"),
+            None => write!(f, "validation failed"),
+        }
+    }
+}
+
+impl<const N: usize> Trace for InlineCheckError<N> {
+    fn trace<R>(mut self, trace: R) -> Self
+    where
+        R: fmt::Debug + fmt::Display + Send + Sync + 'static,
+    {
+        let any = &trace as &dyn Any;
+        if let Some(location) = any.downcast_ref::<LocationContext>() {
+            if self.value.is_none() {
+                self.value = Some((location.address, location.len));
+            }
+        } else if let Some(buffer) = any.downcast_ref::<BufferContext>() {
+            self.buffer = Some(buffer.address);
+        } else if let Some(kind) = ErrorKind::from_trace(any) {
+            self.kind.get_or_insert(kind);
+        } else if let Some((inner, outer)) = Segment::from_trace(any) {
+            self.push_outer(inner);
+            if let Some(outer) = outer {
+                self.push_outer(outer);
+            }
+        }
+        self
+    }
+}
+
+impl<const N: usize> Source for InlineCheckError<N> {
+    #[cfg(feature = "std")]
+    fn new<T: std::error::Error + Send + Sync + 'static>(source: T) -> Self {
+        Self::with_kind(ErrorKind::from_source(&source))
+    }
+
+    #[cfg(not(feature = "std"))]
+    fn new<T: fmt::Debug + fmt::Display + Send + Sync + 'static>(
+        source: T,
+    ) -> Self {
+        Self::with_kind(ErrorKind::from_source(&source))
+    }
+}
@@ -0,0 +1,333 @@
+//! Error types which record where validation failed.
+
+use core::{any::Any, fmt};
+
+use crate::{
+    bounds::{OutOfBoundsError, OverlappingClaimError, UnalignedPointerError},
+    budget::BudgetExceededError,
+    depth::DepthLimitError,
+    dynamic::InvalidSchemaDiscriminantError,
+    shared::{CyclicSharedError, SharedCapacityError, SharedTypeMismatchError},
+    EnumRepr, InvalidEnumDiscriminantError, VerifyContext,
+};
+
+#[cfg(feature = "std")]
+mod check_error;
+mod inline;
+
+#[cfg(feature = "std")]
+pub use self::check_error::{CheckError, CheckErrorJson};
+pub use self::inline::InlineCheckError;
+
+/// The location of an invalid value within a checked buffer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Location {
+    /// The offset of the invalid value from the start of the buffer, in bytes.
+    pub offset: usize,
+    /// The size of the invalid value in bytes.
+    pub len: usize,
+}
+
+/// An error which can describe the invalid value that caused it.
+pub trait Diagnostic {
+    /// Returns the location of the invalid value within the checked buffer, if
+    /// it is known.
+    fn location(&self) -> Option<Location>;
+
+    /// Writes the path to the invalid value, or nothing if the path is not
+    /// known.
+    fn fmt_path(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result;
+
+    /// Writes a description of why the value is invalid.
+    fn fmt_reason(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result;
+}
+
+/// The kind of a failure produced by a built-in `CheckBytes` implementation.
+///
+/// Built-in implementations fail with an `ErrorKind` as their source error,
+/// so error types can recover it with [`ErrorKind::from_source`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// A `bool` was not 0 or 1.
+    InvalidBool {
+        /// The invalid byte.
+        byte: u8,
+    },
+    /// A `char` was not a valid Unicode scalar value.
+    InvalidChar {
+        /// The invalid value.
+        value: u32,
+    },
+    /// A `str` was not valid UTF-8.
+    InvalidUtf8 {
+        /// The number of bytes of valid UTF-8 before the invalid sequence.
+        valid_up_to: usize,
+    },
+    /// A non-zero integer was zero.
+    ZeroNonZero,
+    /// A C string did not end with a nul byte.
+    MissingNul,
+    /// A C string contained a nul byte before its end.
+    InteriorNul {
+        /// The position of the first nul byte.
+        position: usize,
+    },
+    /// An enum had a discriminant that did not match any of its variants.
+    InvalidDiscriminant {
+        /// The name of the enum.
+        enum_name: &'static str,
+        /// The invalid discriminant. `u128` discriminants greater than
+        /// `i128::MAX` wrap around to negative values.
+        discriminant: i128,
+    },
+    /// A type's [`Verify`](crate::Verify) implementation returned an error.
+    VerifyFailed {
+        /// The name of the type that failed to verify.
+        type_name: &'static str,
+    },
+    /// Values were nested more deeply than a
+    /// [`DepthLimit`](crate::depth::DepthLimit) allowed.
+    DepthLimitExceeded {
+        /// The depth of the value that would have been checked.
+        depth: usize,
+        /// The maximum allowed depth.
+        max_depth: usize,
+    },
+    /// Validation did more work than a [`Budget`](crate::budget::Budget)
+    /// allowed.
+    BudgetExceeded {
+        /// The total number of units of work allowed.
+        limit: usize,
+    },
+    /// A range of bytes was not entirely within the buffer.
+    OutOfBounds {
+        /// The length of the range in bytes.
+        len: usize,
+    },
+    /// A pointer within the buffer was not properly aligned.
+    UnalignedPointer {
+        /// The required alignment of the pointer.
+        align: usize,
+    },
+    /// A range of bytes overlapped or came before a previously-claimed range.
+    OverlappingClaim {
+        /// The length of the range in bytes.
+        len: usize,
+    },
+    /// A shared value contained a pointer to itself.
+    CyclicShared,
+    /// A shared value was checked as two different types.
+    SharedTypeMismatch,
+    /// More shared values were checked than could be recorded.
+    SharedCapacityExceeded {
+        /// The maximum number of shared values which could be recorded.
+        capacity: usize,
+    },
+}
+
+// Both `dyn Any` and `dyn Error` can be downcast, so finding the kind of an
+// error is shared between them. `dyn Error` can only be downcast to other error
+// types, so the possible sources of a kind are bounded by `KindSource`.
+#[cfg(feature = "std")]
+trait KindSource: std::error::Error + 'static {}
+#[cfg(not(feature = "std"))]
+trait KindSource: 'static {}
+
+impl KindSource for ErrorKind {}
+impl KindSource for DepthLimitError {}
+impl KindSource for BudgetExceededError {}
+impl KindSource for OutOfBoundsError {}
+impl KindSource for UnalignedPointerError {}
+impl KindSource for OverlappingClaimError {}
+impl KindSource for CyclicSharedError {}
+impl KindSource for SharedTypeMismatchError {}
+impl KindSource for SharedCapacityError {}
+impl<T: EnumRepr> KindSource for InvalidEnumDiscriminantError<T> {}
+impl KindSource for InvalidSchemaDiscriminantError {}
+
+trait Downcast {
+    fn get<T: KindSource>(&self) -> Option<&T>;
+}
+
+impl Downcast for dyn Any {
+    fn get<T: KindSource>(&self) -> Option<&T> {
+        self.downcast_ref()
+    }
+}
+
+#[cfg(feature = "std")]
+impl Downcast for dyn std::error::Error + 'static {
+    fn get<T: KindSource>(&self) -> Option<&T> {
+        self.downcast_ref()
+    }
+}
+
+fn kind_of<S: Downcast + ?Sized>(source: &S) -> Option<ErrorKind> {
+    macro_rules! discriminant {
+        ($($ty:ty),*) => {
+            $(
+                if let Some(e) =
+                    source.get::<InvalidEnumDiscriminantError<$ty>>()
+                {
+                    return Some(ErrorKind::InvalidDiscriminant {
+                        enum_name: e.enum_name,
+                        discriminant: e.invalid_discriminant as i128,
+                    });
+                }
+            )*
+        };
+    }
+
+    if let Some(kind) = source.get::<ErrorKind>() {
+        return Some(*kind);
+    }
+    if let Some(e) = source.get::<DepthLimitError>() {
+        return Some(ErrorKind::DepthLimitExceeded {
+            depth: e.depth,
+            max_depth: e.max_depth,
+        });
+    }
+    if let Some(e) = source.get::<BudgetExceededError>() {
+        return Some(ErrorKind::BudgetExceeded { limit: e.limit });
+    }
+    if let Some(e) = source.get::<OutOfBoundsError>() {
+        return Some(ErrorKind::OutOfBounds { len: e.len });
+    }
+    if let Some(e) = source.get::<UnalignedPointerError>() {
+        return Some(ErrorKind::UnalignedPointer { align: e.align });
+    }
+    if let Some(e) = source.get::<OverlappingClaimError>() {
+        return Some(ErrorKind::OverlappingClaim { len: e.len });
+    }
+    if source.get::<CyclicSharedError>().is_some() {
+        return Some(ErrorKind::CyclicShared);
+    }
+    if source.get::<SharedTypeMismatchError>().is_some() {
+        return Some(ErrorKind::SharedTypeMismatch);
+    }
+    if let Some(e) = source.get::<SharedCapacityError>() {
+        return Some(ErrorKind::SharedCapacityExceeded {
+            capacity: e.capacity,
+        });
+    }
+    discriminant!(i8, i16, i32, i64, i128, u8, u16, u32, u64, u128);
+    if let Some(e) = source.get::<InvalidSchemaDiscriminantError>() {
+        return Some(ErrorKind::InvalidDiscriminant {
+            enum_name: e.enum_name,
+            discriminant: e.invalid_discriminant as i128,
+        });
+    }
+    None
+}
+
+impl ErrorKind {
+    /// Returns the kind of the given source error, if it was produced by a
+    /// built-in `CheckBytes` implementation.
+    ///
+    /// This is intended to be called from implementations of
+    /// [`Source::new`](rancor::Source::new).
+    pub fn from_source(source: &dyn Any) -> Option<Self> {
+        kind_of(source)
+    }
+
+    /// Returns the kind recorded by the given trace context, if it is one of
+    /// the contexts added by bytecheck.
+    ///
+    /// Only [`VerifyContext`] records a kind. Because a `Verify`
+    /// implementation may itself fail with a more specific kind, this should
+    /// only be used when the source error did not have a kind.
+    pub fn from_trace(trace: &dyn Any) -> Option<Self> {
+        trace
+            .downcast_ref::<VerifyContext>()
+            .map(|c| ErrorKind::VerifyFailed {
+                type_name: c.type_name,
+            })
+    }
+
+    /// Returns the kind of the first error in the given error's chain of
+    /// sources which has one.
+    #[cfg(feature = "std")]
+    pub fn find(error: &(dyn std::error::Error + 'static)) -> Option<Self> {
+        let mut current = Some(error);
+        while let Some(error) = current {
+            let kind = match error.downcast_ref::<CheckError>() {
+                Some(error) => error.kind(),
+                None => kind_of(error),
+            };
+            if kind.is_some() {
+                return kind;
+            }
+            current = error.source();
+        }
+        None
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ErrorKind::InvalidBool { byte } => write!(
+                f,
+                "bool set to invalid byte {byte}, expected either 0 or 1",
+            ),
+            ErrorKind::InvalidChar { value } => {
+                write!(f, "invalid char scalar value {value:#x}")
+            }
+            ErrorKind::InvalidUtf8 { valid_up_to } => {
+                write!(f, "invalid UTF-8 after {valid_up_to} valid bytes")
+            }
+            ErrorKind::ZeroNonZero => write!(f, "nonzero integer is zero"),
+            ErrorKind::MissingNul => {
+                write!(f, "C string is missing a nul terminator")
+            }
+            ErrorKind::InteriorNul { position } => write!(
+                f,
+                "C string contains an interior nul byte at position \
+                 {position}",
+            ),
+            ErrorKind::InvalidDiscriminant {
+                enum_name,
+                discriminant,
+            } => write!(
+                f,
+                "invalid discriminant '{discriminant}' for enum '{enum_name}'",
+            ),
+            ErrorKind::VerifyFailed { type_name } => {
+                write!(f, "failed to verify '{type_name}'")
+            }
+            ErrorKind::DepthLimitExceeded { depth, max_depth } => write!(
+                f,
+                "value nested at depth {depth} exceeds the maximum depth of \
+                 {max_depth}",
+            ),
+            ErrorKind::BudgetExceeded { limit } => write!(
+                f,
+                "validation exceeded its budget of {limit} units of work",
+            ),
+            ErrorKind::OutOfBounds { len } => {
+                write!(f, "{len} bytes are outside of the buffer")
+            }
+            ErrorKind::UnalignedPointer { align } => {
+                write!(f, "pointer is not aligned to {align} bytes")
+            }
+            ErrorKind::OverlappingClaim { len } => write!(
+                f,
+                "{len} bytes overlap or come before previously claimed bytes",
+            ),
+            ErrorKind::CyclicShared => {
+                write!(f, "shared value contains a pointer to itself")
+            }
+            ErrorKind::SharedTypeMismatch => {
+                write!(f, "shared value was checked as two different types")
+            }
+            ErrorKind::SharedCapacityExceeded { capacity } => write!(
+                f,
+                "checked more than the maximum of {capacity} shared values",
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ErrorKind {}
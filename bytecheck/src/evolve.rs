@@ -0,0 +1,300 @@
+//! Records which add fields to their end over time.
+//!
+//! An evolving record is a `repr(C)` struct which stores its own size in
+//! bytes. New versions of the record only add fields to its end, so older
+//! readers can skip fields they don't know about and newer readers can tell
+//! which fields older writers didn't write.
+//!
+//! `#[derive(CheckBytes)]` supports evolving records with two attributes:
+//!
+//! - `#[check_bytes(size_field = ...)]` on the struct names the unsigned
+//!   integer field which stores the size of the record.
+//! - `#[check_bytes(added = N)]` on a field marks it as added in version `N`.
+//!   These fields must come after all of the other fields, in the order they
+//!   were added, and must have the type [`Trailing<T>`](Trailing).
+//!
+//! The derived implementation checks the required fields, then checks that
+//! the stored size doesn't cut off any field, and then only checks the
+//! trailing fields which fit in the stored size. Stored sizes larger than the
+//! record are allowed, since they come from newer writers.
+//!
+//! The buffer must still hold enough bytes for the whole record, so records
+//! written by older writers must be padded before they're checked. Copy them
+//! into a zeroed buffer which is `size_of::<T>()` bytes long and aligned for
+//! `T`. The padding is past the stored size, so it is never checked.
+//!
+//! The derive also implements [`Evolving`], which readers can use to find out
+//! which trailing fields were present. Since the stored size can be changed
+//! after the record is checked, accessing a trailing field with
+//! [`get`](Evolving::get) is unsafe:
+//!
+//! ```
+//! use bytecheck::{
+//!     check_bytes,
+//!     evolve::{Evolving, Trailing},
+//!     rancor::Failure,
+//!     CheckBytes,
+//! };
+//!
+//! #[derive(CheckBytes)]
+//! #[check_bytes(size_field = size)]
+//! #[repr(C)]
+//! struct Record {
+//!     size: u32,
+//!     id: u32,
+//!     #[check_bytes(added = 2)]
+//!     flag: Trailing<bool>,
+//! }
+//!
+//! let mut record = Record {
+//!     size: 8,
+//!     id: 1,
+//!     flag: Trailing::new(true),
+//! };
+//! unsafe {
+//!     check_bytes::<_, Failure>(&record).unwrap();
+//!     assert_eq!(record.get(&record.flag), None);
+//! }
+//!
+//! // The trailing field was created with `Trailing::new`, so it's valid to
+//! // access it with a larger stored size.
+//! record.size = 9;
+//! unsafe {
+//!     assert_eq!(record.get(&record.flag), Some(&true));
+//! }
+//! assert_eq!(record.present_fields().len(), 3);
+//!
+//! // Sizes which end in the middle of a field are rejected.
+//! record.size = 6;
+//! unsafe {
+//!     assert!(check_bytes::<_, Failure>(&record).is_err());
+//! }
+//! ```
+
+use core::{
+    fmt,
+    mem::{self, MaybeUninit},
+};
+
+use rancor::{fail, Fallible, Source};
+
+use crate::{
+    fingerprint::{Fingerprint, FingerprintHasher},
+    schema::{Schema, SchemaKind, TypeSchema},
+    CheckBytes,
+};
+
+/// A field of an evolving record which may not have been written.
+///
+/// Trailing fields are only initialized when the stored size of their record
+/// includes them. Use [`Evolving::get`] to access them.
+#[repr(transparent)]
+pub struct Trailing<T> {
+    value: MaybeUninit<T>,
+}
+
+impl<T> Trailing<T> {
+    /// Returns a new trailing field containing `value`.
+    #[inline]
+    pub const fn new(value: T) -> Self {
+        Self {
+            value: MaybeUninit::new(value),
+        }
+    }
+
+    /// Returns a reference to the value of the field.
+    ///
+    /// # Safety
+    ///
+    /// The field must be present in its record.
+    #[inline]
+    pub unsafe fn assume_present(&self) -> &T {
+        // SAFETY: The caller has guaranteed that the field is present, so it
+        // was either created with `new` or checked.
+        unsafe { self.value.assume_init_ref() }
+    }
+}
+
+impl<T> fmt::Debug for Trailing<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Trailing").finish_non_exhaustive()
+    }
+}
+
+// SAFETY: `Trailing<T>` is `repr(transparent)` over a `T`, so it is valid if
+// its value is a valid `T`.
+unsafe impl<T, C> CheckBytes<C> for Trailing<T>
+where
+    T: CheckBytes<C>,
+    C: Fallible + ?Sized,
+{
+    #[inline]
+    unsafe fn check_bytes(
+        value: *const Self,
+        context: &mut C,
+    ) -> Result<(), C::Error> {
+        // SAFETY: The caller has guaranteed that `value` is aligned and points
+        // to enough bytes for a `T`.
+        unsafe { T::check_bytes(value.cast::<T>(), context) }
+    }
+}
+
+impl<T: Fingerprint> Fingerprint for Trailing<T> {
+    const FINGERPRINT: u64 = FingerprintHasher::new()
+        .write_str("Trailing")
+        .write_u64(T::FINGERPRINT)
+        .finish();
+}
+
+// Whether a trailing field is checked depends on the stored size of its
+// record, so it's opaque.
+impl<T> Schema for Trailing<T> {
+    const SCHEMA: &'static TypeSchema<'static> =
+        &TypeSchema::sized::<Trailing<T>>("Trailing", SchemaKind::Opaque);
+}
+
+/// A field of an evolving record.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct EvolvingField {
+    /// The name of the field.
+    pub name: &'static str,
+    /// The offset of the field in bytes.
+    pub offset: usize,
+    /// The size of the field in bytes.
+    pub size: usize,
+    /// The version the field was added in, or `None` if the field is required.
+    pub added: Option<u32>,
+}
+
+impl EvolvingField {
+    /// Returns the offset of the end of the field in bytes.
+    #[inline]
+    pub const fn end(&self) -> usize {
+        self.offset + self.size
+    }
+
+    /// Returns whether the field is present in a record with the given stored
+    /// size.
+    #[inline]
+    pub const fn is_present(&self, stored_size: u64) -> bool {
+        self.end() as u64 <= stored_size
+    }
+}
+
+/// A record which stores its own size, and only contains the trailing fields
+/// which fit in that size.
+///
+/// This is implemented by `#[derive(CheckBytes)]` for structs with a size
+/// field. See the [module docs](self) for details.
+///
+/// # Safety
+///
+/// `FIELDS` must describe the fields of the record in order, and `stored_size`
+/// must return the value of the record's size field.
+pub unsafe trait Evolving {
+    /// The fields of the record, in order.
+    const FIELDS: &'static [EvolvingField];
+
+    /// Returns the stored size of the record in bytes.
+    fn stored_size(&self) -> u64;
+
+    /// Returns the fields which are present in the record according to its
+    /// current stored size.
+    ///
+    /// These are always a prefix of [`FIELDS`](Evolving::FIELDS).
+    #[inline]
+    fn present_fields(&self) -> &'static [EvolvingField] {
+        let stored_size = self.stored_size();
+        let len = Self::FIELDS
+            .iter()
+            .take_while(|field| field.is_present(stored_size))
+            .count();
+        &Self::FIELDS[..len]
+    }
+
+    /// Returns whether the field with the given name is present in the record
+    /// according to its current stored size.
+    #[inline]
+    fn is_present(&self, name: &str) -> bool {
+        self.present_fields().iter().any(|field| field.name == name)
+    }
+
+    /// Returns the value of a trailing field of the record, or `None` if it
+    /// isn't present.
+    ///
+    /// Returns `None` if `field` is not a trailing field of `self`.
+    ///
+    /// # Safety
+    ///
+    /// Every trailing field which is present according to the current stored
+    /// size must be a valid value. This holds if the record was checked and its
+    /// stored size hasn't been increased since, or if its trailing fields were
+    /// created with [`Trailing::new`].
+    #[inline]
+    unsafe fn get<'a, T>(&'a self, field: &'a Trailing<T>) -> Option<&'a T> {
+        let offset = (field as *const Trailing<T> as usize)
+            .wrapping_sub((self as *const Self).cast::<u8>() as usize);
+        let stored_size = self.stored_size();
+        Self::FIELDS
+            .iter()
+            .find(|f| {
+                f.added.is_some()
+                    && f.offset == offset
+                    && f.size == mem::size_of::<T>()
+            })
+            .filter(|f| f.is_present(stored_size))
+            // SAFETY: `field` is a trailing field of `self` which is present,
+            // and the caller has guaranteed that present trailing fields are
+            // valid.
+            .map(|_| unsafe { field.assume_present() })
+    }
+}
+
+/// Checks that `stored_size` doesn't cut off any field of `T`.
+///
+/// This is called by derived implementations of `CheckBytes` before they
+/// check trailing fields.
+pub fn check_stored_size<T, E>(stored_size: u64) -> Result<(), E>
+where
+    T: Evolving + ?Sized,
+    E: Source,
+{
+    for field in T::FIELDS {
+        let cut_off = if field.added.is_some() {
+            (field.offset as u64) < stored_size
+                && stored_size < field.end() as u64
+        } else {
+            !field.is_present(stored_size)
+        };
+        if cut_off {
+            fail!(StoredSizeError {
+                stored_size,
+                field_name: field.name,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// An error resulting from an evolving record with a stored size that cuts off
+/// one of its fields.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct StoredSizeError {
+    /// The stored size of the record in bytes.
+    pub stored_size: u64,
+    /// The name of the field that was cut off.
+    pub field_name: &'static str,
+}
+
+impl fmt::Display for StoredSizeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "stored size {} cuts off field '{}'",
+            self.stored_size, self.field_name,
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for StoredSizeError {}
@@ -0,0 +1,260 @@
+//! Stable fingerprints of type layouts.
+//!
+//! A [`Fingerprint`] is a hash of everything that determines how a type is laid
+//! out and checked: its name, its repr, the names and fingerprints of its
+//! fields, and the discriminants of its variants. `#[derive(CheckBytes)]`
+//! implements it for every type it derives for, so reordering, renaming, or
+//! retyping a field changes the fingerprint.
+//!
+//! Because of this, the type of every field of a derived type must implement
+//! `Fingerprint`, and deriving fails with an error naming the field type if
+//! one doesn't. Types with hand-written `CheckBytes` implementations should
+//! implement `Fingerprint` by hashing their name and the fingerprints of their
+//! contents with a [`FingerprintHasher`].
+//!
+//! Writers can store the fingerprint of the root type next to the bytes, and
+//! readers can compare it with their own before trusting them:
+//!
+//! ```
+//! use bytecheck::{fingerprint::Fingerprint, CheckBytes};
+//!
+//! #[derive(CheckBytes)]
+//! #[repr(C)]
+//! struct Old {
+//!     a: u32,
+//!     b: u16,
+//! }
+//!
+//! #[derive(CheckBytes)]
+//! #[repr(C)]
+//! struct New {
+//!     b: u16,
+//!     a: u32,
+//! }
+//!
+//! assert_ne!(Old::FINGERPRINT, New::FINGERPRINT);
+//! ```
+//!
+//! Fingerprints are computed with [`FingerprintHasher`], which is stable across
+//! compiler versions and platforms. They describe the structure of a type and
+//! not the byte order of its fields.
+
+#[cfg(target_has_atomic = "8")]
+use core::sync::atomic::{AtomicBool, AtomicI8, AtomicU8};
+#[cfg(target_has_atomic = "16")]
+use core::sync::atomic::{AtomicI16, AtomicU16};
+#[cfg(target_has_atomic = "32")]
+use core::sync::atomic::{AtomicI32, AtomicU32};
+#[cfg(target_has_atomic = "64")]
+use core::sync::atomic::{AtomicI64, AtomicU64};
+use core::{
+    marker::{PhantomData, PhantomPinned},
+    mem::{self, ManuallyDrop},
+    num::{
+        NonZeroI128, NonZeroI16, NonZeroI32, NonZeroI64, NonZeroI8,
+        NonZeroU128, NonZeroU16, NonZeroU32, NonZeroU64, NonZeroU8,
+    },
+    ops,
+};
+
+/// A type with a stable fingerprint of its layout.
+///
+/// This is implemented by `#[derive(CheckBytes)]`. Manual implementations
+/// should hash a unique name for the type along with the fingerprints of any
+/// types it contains using [`FingerprintHasher`].
+pub trait Fingerprint {
+    /// The fingerprint of the type's layout.
+    const FINGERPRINT: u64;
+}
+
+/// Returns the fingerprint of `T`.
+#[inline]
+pub const fn fingerprint_of<T: Fingerprint + ?Sized>() -> u64 {
+    T::FINGERPRINT
+}
+
+/// A `const` hasher for computing fingerprints.
+///
+/// This is 64-bit FNV-1a. Strings and byte slices are prefixed with their
+/// length, so consecutive writes can't run together.
+#[derive(Clone, Copy, Debug)]
+pub struct FingerprintHasher {
+    state: u64,
+}
+
+impl FingerprintHasher {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    /// Returns a new hasher.
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            state: Self::OFFSET_BASIS,
+        }
+    }
+
+    const fn write_raw(mut self, bytes: &[u8]) -> Self {
+        let mut i = 0;
+        while i < bytes.len() {
+            self.state ^= bytes[i] as u64;
+            self.state = self.state.wrapping_mul(Self::PRIME);
+            i += 1;
+        }
+        self
+    }
+
+    /// Writes a length-prefixed byte slice into the hasher.
+    #[inline]
+    pub const fn write_bytes(self, bytes: &[u8]) -> Self {
+        self.write_u64(bytes.len() as u64).write_raw(bytes)
+    }
+
+    /// Writes a length-prefixed string into the hasher.
+    #[inline]
+    pub const fn write_str(self, s: &str) -> Self {
+        self.write_bytes(s.as_bytes())
+    }
+
+    /// Writes a `u64` into the hasher.
+    #[inline]
+    pub const fn write_u64(self, value: u64) -> Self {
+        self.write_raw(&value.to_le_bytes())
+    }
+
+    /// Writes a `u128` into the hasher.
+    #[inline]
+    pub const fn write_u128(self, value: u128) -> Self {
+        self.write_raw(&value.to_le_bytes())
+    }
+
+    /// Returns the hash of everything written so far.
+    #[inline]
+    pub const fn finish(self) -> u64 {
+        self.state
+    }
+}
+
+impl Default for FingerprintHasher {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Alignment varies between targets and compiler versions (for example, `u64`
+// on i686 and `u128` before Rust 1.77), so only the size is hashed.
+const fn primitive<T>(name: &str) -> u64 {
+    FingerprintHasher::new()
+        .write_str(name)
+        .write_u64(mem::size_of::<T>() as u64)
+        .finish()
+}
+
+const fn wrapper<T: Fingerprint + ?Sized>(name: &str) -> u64 {
+    FingerprintHasher::new()
+        .write_str(name)
+        .write_u64(T::FINGERPRINT)
+        .finish()
+}
+
+macro_rules! impl_primitive {
+    ($($type:ident),* $(,)?) => {
+        $(
+            impl Fingerprint for $type {
+                const FINGERPRINT: u64 = primitive::<$type>(stringify!($type));
+            }
+        )*
+    };
+}
+
+impl_primitive! {
+    bool, char,
+    i8, i16, i32, i64, i128,
+    u8, u16, u32, u64, u128,
+    f32, f64,
+    NonZeroI8, NonZeroI16, NonZeroI32, NonZeroI64, NonZeroI128,
+    NonZeroU8, NonZeroU16, NonZeroU32, NonZeroU64, NonZeroU128,
+}
+#[cfg(target_has_atomic = "8")]
+impl_primitive!(AtomicBool, AtomicI8, AtomicU8);
+#[cfg(target_has_atomic = "16")]
+impl_primitive!(AtomicI16, AtomicU16);
+#[cfg(target_has_atomic = "32")]
+impl_primitive!(AtomicI32, AtomicU32);
+#[cfg(target_has_atomic = "64")]
+impl_primitive!(AtomicI64, AtomicU64);
+
+impl Fingerprint for () {
+    const FINGERPRINT: u64 = primitive::<()>("()");
+}
+
+impl Fingerprint for PhantomPinned {
+    const FINGERPRINT: u64 = primitive::<PhantomPinned>("PhantomPinned");
+}
+
+impl Fingerprint for ops::RangeFull {
+    const FINGERPRINT: u64 = primitive::<ops::RangeFull>("RangeFull");
+}
+
+// `PhantomData` doesn't contain a `T`, so `T` doesn't affect its layout.
+impl<T: ?Sized> Fingerprint for PhantomData<T> {
+    const FINGERPRINT: u64 = primitive::<PhantomData<T>>("PhantomData");
+}
+
+impl<T: Fingerprint> Fingerprint for ManuallyDrop<T> {
+    const FINGERPRINT: u64 = wrapper::<T>("ManuallyDrop");
+}
+
+macro_rules! impl_range {
+    ($($type:ident),* $(,)?) => {
+        $(
+            impl<T: Fingerprint> Fingerprint for ops::$type<T> {
+                const FINGERPRINT: u64 = wrapper::<T>(stringify!($type));
+            }
+        )*
+    };
+}
+
+impl_range!(Range, RangeFrom, RangeTo, RangeToInclusive);
+
+impl<T: Fingerprint, const N: usize> Fingerprint for [T; N] {
+    const FINGERPRINT: u64 = FingerprintHasher::new()
+        .write_str("array")
+        .write_u64(T::FINGERPRINT)
+        .write_u64(N as u64)
+        .finish();
+}
+
+impl<T: Fingerprint> Fingerprint for [T] {
+    const FINGERPRINT: u64 = wrapper::<T>("slice");
+}
+
+impl Fingerprint for str {
+    const FINGERPRINT: u64 = FingerprintHasher::new().write_str("str").finish();
+}
+
+macro_rules! impl_tuple {
+    ($($type:ident),*) => {
+        impl<$($type: Fingerprint),*> Fingerprint for ($($type,)*) {
+            const FINGERPRINT: u64 = FingerprintHasher::new()
+                .write_str("tuple")
+                $(.write_u64($type::FINGERPRINT))*
+                .finish();
+        }
+    };
+}
+
+impl_tuple!(T0);
+impl_tuple!(T0, T1);
+impl_tuple!(T0, T1, T2);
+impl_tuple!(T0, T1, T2, T3);
+impl_tuple!(T0, T1, T2, T3, T4);
+impl_tuple!(T0, T1, T2, T3, T4, T5);
+impl_tuple!(T0, T1, T2, T3, T4, T5, T6);
+impl_tuple!(T0, T1, T2, T3, T4, T5, T6, T7);
+impl_tuple!(T0, T1, T2, T3, T4, T5, T6, T7, T8);
+impl_tuple!(T0, T1, T2, T3, T4, T5, T6, T7, T8, T9);
+impl_tuple!(T0, T1, T2, T3, T4, T5, T6, T7, T8, T9, T10);
+impl_tuple!(T0, T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11);
+impl_tuple!(T0, T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11, T12);
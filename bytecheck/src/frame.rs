@@ -0,0 +1,581 @@
+//! Checking streams of length-prefixed frames.
+//!
+//! A frame stream is a sequence of frames, each of which is a length prefix
+//! followed by a payload of that many bytes. A [`FrameConfig`] describes the
+//! width and byte order of the prefix, the maximum payload length, and the
+//! alignment of the prefix and payload. Padding is inserted before each
+//! prefix and payload so that they start at a multiple of the alignment from
+//! the start of the stream.
+//!
+//! [`FrameConfig::frames`] checks the payloads of the frames in a byte slice
+//! as a `T` and yields references to them. With the `std` feature,
+//! `FrameConfig::read_frames` reads frames from an `io::Read` and yields owned
+//! values.
+//!
+//! If a payload is invalid, its error is yielded and checking continues with
+//! the next frame. If the frames themselves are malformed, the error is
+//! yielded and the iterator ends. The stream may end with padding after the
+//! last frame.
+//!
+//! ```
+//! use bytecheck::{frame::FrameConfig, rancor::Failure};
+//!
+//! // Frames with a one-byte prefix containing one `bool` each.
+//! let bytes = [1, 1, 1, 2, 1, 0];
+//! let config = FrameConfig::new().with_prefix(1);
+//! let mut frames = config.frames::<bool, Failure>(&bytes);
+//!
+//! assert_eq!(frames.next().unwrap().ok(), Some(&true));
+//! assert!(frames.next().unwrap().is_err());
+//! assert_eq!(frames.next().unwrap().ok(), Some(&false));
+//! assert!(frames.next().is_none());
+//! ```
+
+use core::{fmt, marker::PhantomData};
+
+use rancor::{fail, ResultExt as _, Source, Strategy};
+
+use crate::{from_bytes_with_context, BufferContext, CheckBytes};
+
+/// The byte order of a length prefix.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Endianness {
+    /// The least significant byte comes first.
+    Little,
+    /// The most significant byte comes first.
+    Big,
+}
+
+/// The layout of a frame stream.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FrameConfig {
+    prefix: usize,
+    endianness: Endianness,
+    max_len: usize,
+    align: usize,
+}
+
+impl FrameConfig {
+    /// The default maximum payload length in bytes.
+    pub const DEFAULT_MAX_LEN: usize = 1 << 20;
+
+    /// Returns a new `FrameConfig` with a four-byte little-endian prefix, a
+    /// maximum payload length of [`DEFAULT_MAX_LEN`](Self::DEFAULT_MAX_LEN),
+    /// and no padding.
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            prefix: 4,
+            endianness: Endianness::Little,
+            max_len: Self::DEFAULT_MAX_LEN,
+            align: 1,
+        }
+    }
+
+    /// Sets the width of the length prefix in bytes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `width` is not 1, 2, 4, or 8.
+    #[inline]
+    pub const fn with_prefix(mut self, width: usize) -> Self {
+        assert!(
+            matches!(width, 1 | 2 | 4 | 8),
+            "prefix width must be 1, 2, 4, or 8 bytes",
+        );
+        self.prefix = width;
+        self
+    }
+
+    /// Sets the byte order of the length prefix.
+    #[inline]
+    pub const fn with_endianness(mut self, endianness: Endianness) -> Self {
+        self.endianness = endianness;
+        self
+    }
+
+    /// Sets the maximum payload length in bytes.
+    #[inline]
+    pub const fn with_max_len(mut self, max_len: usize) -> Self {
+        self.max_len = max_len;
+        self
+    }
+
+    /// Sets the alignment of the length prefix and payload.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `align` is not a power of two.
+    #[inline]
+    pub const fn with_align(mut self, align: usize) -> Self {
+        assert!(align.is_power_of_two(), "align must be a power of two");
+        self.align = align;
+        self
+    }
+
+    /// Returns the width of the length prefix in bytes.
+    #[inline]
+    pub const fn prefix(&self) -> usize {
+        self.prefix
+    }
+
+    /// Returns the byte order of the length prefix.
+    #[inline]
+    pub const fn endianness(&self) -> Endianness {
+        self.endianness
+    }
+
+    /// Returns the maximum payload length in bytes.
+    #[inline]
+    pub const fn max_len(&self) -> usize {
+        self.max_len
+    }
+
+    /// Returns the alignment of the length prefix and payload.
+    #[inline]
+    pub const fn align(&self) -> usize {
+        self.align
+    }
+
+    /// Returns an iterator which checks the payloads of the frames in `bytes`
+    /// as `T`.
+    ///
+    /// Payloads are checked in place, so `bytes` must be aligned for `T`.
+    #[inline]
+    pub fn frames<T, E>(self, bytes: &[u8]) -> Frames<'_, T, E> {
+        self.frames_with_context(bytes, ())
+    }
+
+    /// Returns an iterator which checks the payloads of the frames in `bytes`
+    /// as `T` within the given context.
+    ///
+    /// Payloads are checked in place, so `bytes` must be aligned for `T`.
+    #[inline]
+    pub fn frames_with_context<T, E, C>(
+        self,
+        bytes: &[u8],
+        context: C,
+    ) -> Frames<'_, T, E, C> {
+        Frames {
+            config: self,
+            bytes,
+            offset: 0,
+            index: 0,
+            done: false,
+            context,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Returns an iterator which reads frames from `reader` and checks their
+    /// payloads as `T`.
+    #[cfg(feature = "std")]
+    #[inline]
+    pub fn read_frames<T, E, R>(self, reader: R) -> ReadFrames<R, T, E> {
+        self.read_frames_with_context(reader, ())
+    }
+
+    /// Returns an iterator which reads frames from `reader` and checks their
+    /// payloads as `T` within the given context.
+    #[cfg(feature = "std")]
+    #[inline]
+    pub fn read_frames_with_context<T, E, R, C>(
+        self,
+        reader: R,
+        context: C,
+    ) -> ReadFrames<R, T, E, C> {
+        ReadFrames {
+            config: self,
+            reader,
+            offset: 0,
+            index: 0,
+            done: false,
+            context,
+            _phantom: PhantomData,
+        }
+    }
+
+    pub(crate) fn padding(&self, offset: usize) -> usize {
+        offset.wrapping_neg() & (self.align - 1)
+    }
+
+    pub(crate) fn decode_len<E: Source>(
+        &self,
+        prefix: &[u8],
+    ) -> Result<usize, E> {
+        let len = self.decode_uint(prefix);
+        match usize::try_from(len) {
+            Ok(len) if len <= self.max_len => Ok(len),
+            _ => fail!(FrameTooLargeError {
+                len,
+                max_len: self.max_len,
+            }),
+        }
+    }
+
+    // Decodes an unsigned integer of up to eight bytes with the configured
+    // byte order.
+    pub(crate) fn decode_uint(&self, bytes: &[u8]) -> u64 {
+        let mut buf = [0; 8];
+        match self.endianness {
+            Endianness::Little => {
+                buf[..bytes.len()].copy_from_slice(bytes);
+                u64::from_le_bytes(buf)
+            }
+            Endianness::Big => {
+                buf[8 - bytes.len()..].copy_from_slice(bytes);
+                u64::from_be_bytes(buf)
+            }
+        }
+    }
+}
+
+impl Default for FrameConfig {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// An iterator over the checked payloads of the frames in a byte slice.
+///
+/// Returned by [`FrameConfig::frames`].
+pub struct Frames<'a, T, E, C = ()> {
+    config: FrameConfig,
+    bytes: &'a [u8],
+    offset: usize,
+    index: usize,
+    done: bool,
+    context: C,
+    _phantom: PhantomData<fn() -> (&'a T, E)>,
+}
+
+impl<T, E, C> Frames<'_, T, E, C> {
+    /// Returns the number of bytes which have been consumed.
+    #[inline]
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the context.
+    #[inline]
+    pub fn into_context(self) -> C {
+        self.context
+    }
+}
+
+impl<'a, T, E, C> Frames<'a, T, E, C>
+where
+    T: CheckBytes<Strategy<C, E>>,
+    E: Source,
+{
+    fn next_frame(&mut self) -> Result<&'a T, E> {
+        let config = &self.config;
+        // Offsets saturate so that frames which would end past `usize::MAX`
+        // fail as truncated instead of overflowing.
+        let start = self.offset.saturating_add(config.padding(self.offset));
+        let prefix_end = start.saturating_add(config.prefix);
+        let Some(prefix) = self.bytes.get(start..prefix_end) else {
+            fail!(TruncatedFrameError {
+                expected: prefix_end,
+                found: self.bytes.len(),
+            });
+        };
+        let len = config.decode_len(prefix)?;
+        let payload_start =
+            prefix_end.saturating_add(config.padding(prefix_end));
+        let payload_end = payload_start.saturating_add(len);
+        let Some(payload) = self.bytes.get(payload_start..payload_end) else {
+            fail!(TruncatedFrameError {
+                expected: payload_end,
+                found: self.bytes.len(),
+            });
+        };
+        // The frame is well-formed, so checking can continue with the next
+        // frame even if the payload is invalid.
+        self.offset = payload_end;
+        from_bytes_with_context(payload, &mut self.context)
+            .trace(BufferContext::new(self.bytes))
+    }
+}
+
+impl<'a, T, E, C> Iterator for Frames<'a, T, E, C>
+where
+    T: CheckBytes<Strategy<C, E>>,
+    E: Source,
+{
+    type Item = Result<&'a T, E>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let padding = self.config.padding(self.offset);
+        if self.done || self.offset + padding >= self.bytes.len() {
+            return None;
+        }
+        let offset = self.offset;
+        let result = self.next_frame().map_err(|error| {
+            if self.offset == offset {
+                self.done = true;
+            }
+            error.trace(FrameContext {
+                index: self.index,
+                offset,
+            })
+        });
+        self.index += 1;
+        Some(result)
+    }
+}
+
+#[cfg(feature = "std")]
+pub use self::read::ReadFrames;
+
+#[cfg(feature = "std")]
+mod read {
+    use core::{
+        marker::PhantomData,
+        mem::{self, MaybeUninit},
+        slice,
+    };
+    use std::io::{self, Read};
+
+    use rancor::{ResultExt as _, Source, Strategy};
+
+    use super::{FrameConfig, FrameContext, TruncatedFrameError};
+    use crate::{io::ReadError, BufferContext, BufferLengthError, CheckBytes};
+
+    /// An iterator over the checked payloads of the frames read from a
+    /// reader.
+    ///
+    /// Returned by [`FrameConfig::read_frames`].
+    pub struct ReadFrames<R, T, E, C = ()> {
+        pub(super) config: FrameConfig,
+        pub(super) reader: R,
+        pub(super) offset: usize,
+        pub(super) index: usize,
+        pub(super) done: bool,
+        pub(super) context: C,
+        pub(super) _phantom: PhantomData<fn() -> (T, E)>,
+    }
+
+    impl<R, T, E, C> ReadFrames<R, T, E, C> {
+        /// Returns the number of bytes which have been read.
+        #[inline]
+        pub fn offset(&self) -> usize {
+            self.offset
+        }
+
+        /// Returns the reader and the context.
+        #[inline]
+        pub fn into_parts(self) -> (R, C) {
+            (self.reader, self.context)
+        }
+    }
+
+    impl<R, T, E, C> ReadFrames<R, T, E, C>
+    where
+        R: Read,
+        T: CheckBytes<Strategy<C, E>>,
+        E: Source,
+    {
+        // Reads until `buf` is full or the reader ends, and returns the
+        // number of bytes read.
+        fn fill(&mut self, buf: &mut [u8]) -> Result<usize, ReadError<E>> {
+            let mut filled = 0;
+            while filled < buf.len() {
+                match self.reader.read(&mut buf[filled..]) {
+                    Ok(0) => break,
+                    Ok(n) => {
+                        filled += n;
+                        self.offset += n;
+                    }
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => (),
+                    Err(e) => return Err(ReadError::Io(e)),
+                }
+            }
+            Ok(filled)
+        }
+
+        // Skips up to `len` bytes, and returns the number of bytes skipped.
+        fn skip(&mut self, len: usize) -> Result<usize, ReadError<E>> {
+            let mut reader = (&mut self.reader).take(len as u64);
+            let skipped = io::copy(&mut reader, &mut io::sink())
+                .map_err(ReadError::Io)? as usize;
+            self.offset += skipped;
+            Ok(skipped)
+        }
+
+        fn truncated(&mut self, expected: usize) -> ReadError<E> {
+            self.done = true;
+            ReadError::Invalid(E::new(TruncatedFrameError {
+                expected,
+                found: self.offset,
+            }))
+        }
+
+        // Returns `None` if the reader ended between two frames.
+        fn next_frame(&mut self) -> Result<Option<T>, ReadError<E>> {
+            let config = self.config;
+            let padding = config.padding(self.offset);
+            if self.skip(padding)? < padding {
+                return Ok(None);
+            }
+
+            let mut prefix = [0; 8];
+            let prefix = &mut prefix[..config.prefix];
+            let expected = self.offset + prefix.len();
+            match self.fill(prefix)? {
+                0 => return Ok(None),
+                n if n < prefix.len() => return Err(self.truncated(expected)),
+                _ => (),
+            }
+            let len = config.decode_len(prefix).map_err(|error| {
+                self.done = true;
+                ReadError::Invalid(error)
+            })?;
+
+            let padding = config.padding(self.offset);
+            let expected =
+                self.offset.saturating_add(padding).saturating_add(len);
+            if self.skip(padding)? < padding {
+                return Err(self.truncated(expected));
+            }
+            let size = mem::size_of::<T>();
+            if len != size {
+                // The frame is well-formed, so skip the payload and continue
+                // with the next frame.
+                if self.skip(len)? < len {
+                    return Err(self.truncated(expected));
+                }
+                return Err(ReadError::Invalid(E::new(BufferLengthError {
+                    expected: size,
+                    found: len,
+                })));
+            }
+
+            let offset = self.offset;
+            let mut value = MaybeUninit::<T>::zeroed();
+            // SAFETY: `value` is `size_of::<T>()` bytes long and its bytes
+            // are initialized.
+            let bytes = unsafe {
+                slice::from_raw_parts_mut(value.as_mut_ptr().cast::<u8>(), size)
+            };
+            if self.fill(bytes)? < size {
+                return Err(self.truncated(expected));
+            }
+            let ptr = value.as_ptr();
+            // SAFETY: All of the bytes of `value` are initialized, and `value`
+            // is aligned for `T`.
+            unsafe {
+                T::check_bytes(ptr, Strategy::wrap(&mut self.context))
+                    // The payload is checked in `value`, so the buffer is
+                    // placed such that locations are relative to the start of
+                    // the stream.
+                    .trace(BufferContext {
+                        address: (ptr as usize).wrapping_sub(offset),
+                        len: offset + size,
+                    })
+                    .map_err(ReadError::Invalid)?;
+            }
+            // SAFETY: `check_bytes` returned `Ok`, so `value` is a valid `T`.
+            Ok(Some(unsafe { value.assume_init() }))
+        }
+    }
+
+    impl<R, T, E, C> Iterator for ReadFrames<R, T, E, C>
+    where
+        R: Read,
+        T: CheckBytes<Strategy<C, E>>,
+        E: Source,
+    {
+        type Item = Result<T, ReadError<E>>;
+
+        fn next(&mut self) -> Option<Self::Item> {
+            if self.done {
+                return None;
+            }
+            let offset = self.offset;
+            let result = match self.next_frame() {
+                Ok(None) => {
+                    self.done = true;
+                    return None;
+                }
+                Ok(Some(value)) => Ok(value),
+                Err(ReadError::Io(error)) => {
+                    self.done = true;
+                    Err(ReadError::Io(error))
+                }
+                Err(ReadError::Invalid(error)) => {
+                    Err(ReadError::Invalid(error.trace(FrameContext {
+                        index: self.index,
+                        offset,
+                    })))
+                }
+            };
+            self.index += 1;
+            Some(result)
+        }
+    }
+}
+
+/// Context for errors resulting from invalid frames.
+#[derive(Debug)]
+pub struct FrameContext {
+    /// The index of the frame.
+    pub index: usize,
+    /// The offset of the start of the frame from the start of the stream.
+    pub offset: usize,
+}
+
+impl fmt::Display for FrameContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "while checking frame {} at offset {}",
+            self.index, self.offset,
+        )
+    }
+}
+
+/// An error resulting from a frame with a length greater than the maximum.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FrameTooLargeError {
+    /// The length of the frame's payload in bytes.
+    pub len: u64,
+    /// The maximum length of a payload in bytes.
+    pub max_len: usize,
+}
+
+impl fmt::Display for FrameTooLargeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "frame has length {}, which is greater than the maximum of {}",
+            self.len, self.max_len,
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for FrameTooLargeError {}
+
+/// An error resulting from a stream which ends in the middle of a frame.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TruncatedFrameError {
+    /// The number of bytes needed to read the frame.
+    pub expected: usize,
+    /// The number of bytes in the stream.
+    pub found: usize,
+}
+
+impl fmt::Display for TruncatedFrameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "stream ended after {} bytes in the middle of a frame ending at {}",
+            self.found, self.expected,
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for TruncatedFrameError {}
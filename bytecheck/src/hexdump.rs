@@ -0,0 +1,202 @@
+//! Annotated hexdumps of validation failures.
+//!
+//! [`Hexdump`] renders the bytes surrounding an invalid value, highlights the
+//! bytes of the value, and labels them with the path to the value and the
+//! reason it is invalid:
+//!
+//! ```text
+//! 00000000  00 00 00 00 01 00 00 00  78 00 00 00 00 00 00 00  |........x.......|
+//! 00000010  00 00 00 00 07 00 00 00  78 00 00 00 00 00 00 00  |........x.......|
+//!                       ^^
+//!                       Test.b: bool set to invalid byte 7, expected either 0 or 1
+//! 00000020  00 00 00 00 01 00 00 00  78 00 00 00              |........x...|
+//! ```
+//!
+//! Because `Hexdump` implements `Display`, it can be written to any
+//! [`fmt::Write`] sink and does not require `std`.
+
+use core::fmt::{self, Write as _};
+
+use crate::error::{Diagnostic, Location};
+
+const BYTES_PER_ROW: usize = 16;
+const MAX_HIGHLIGHTED_ROWS: usize = 8;
+// The width of the offset column, including the two spaces after it.
+const OFFSET_WIDTH: usize = 10;
+
+/// Displays a hexdump of the bytes around the invalid value of an error.
+///
+/// If the error does not know the location of the invalid value, only the
+/// label is displayed.
+pub struct Hexdump<'a, E: ?Sized> {
+    error: &'a E,
+    buffer: &'a [u8],
+    context_rows: usize,
+}
+
+impl<'a, E: Diagnostic + ?Sized> Hexdump<'a, E> {
+    /// Returns a new `Hexdump` of the given error with the buffer that was
+    /// checked.
+    ///
+    /// By default, two rows of context are shown before and after the invalid
+    /// value.
+    #[inline]
+    pub fn new(error: &'a E, buffer: &'a [u8]) -> Self {
+        Self {
+            error,
+            buffer,
+            context_rows: 2,
+        }
+    }
+
+    /// Sets the number of rows of context to show before and after the invalid
+    /// value.
+    #[inline]
+    pub fn context_rows(mut self, context_rows: usize) -> Self {
+        self.context_rows = context_rows;
+        self
+    }
+
+    fn fmt_row(&self, f: &mut fmt::Formatter<'_>, row: usize) -> fmt::Result {
+        let start = row * BYTES_PER_ROW;
+        let end = usize::min(start + BYTES_PER_ROW, self.buffer.len());
+        let bytes = &self.buffer[start..end];
+
+        write!(f, "{start:08x}  ")?;
+        for i in 0..BYTES_PER_ROW {
+            if i == BYTES_PER_ROW / 2 {
+                f.write_char(' ')?;
+            }
+            match bytes.get(i) {
+                Some(byte) => write!(f, "{byte:02x} ")?,
+                None => f.write_str("   ")?,
+            }
+        }
+        f.write_str(" |")?;
+        for &byte in bytes {
+            if byte.is_ascii_graphic() || byte == b' ' {
+                f.write_char(byte as char)?;
+            } else {
+                f.write_char('.')?;
+            }
+        }
+        f.write_str("|\n")
+    }
+
+    /// Writes carets under the highlighted bytes of `row` and returns the
+    /// column of the first caret.
+    fn fmt_carets(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+        row: usize,
+        start: usize,
+        end: usize,
+    ) -> Result<usize, fmt::Error> {
+        let row_start = row * BYTES_PER_ROW;
+        let first = start.max(row_start) - row_start;
+        let last = end.min(row_start + BYTES_PER_ROW) - row_start;
+        let indent = column(first);
+
+        write!(f, "{:indent$}", "")?;
+        if first == last {
+            f.write_char('^')?;
+        }
+        for i in first..last {
+            if i != first {
+                f.write_char(' ')?;
+                if i == BYTES_PER_ROW / 2 {
+                    f.write_char(' ')?;
+                }
+            }
+            f.write_str("^^")?;
+        }
+        f.write_char('\n')?;
+
+        Ok(indent)
+    }
+
+    fn fmt_label(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut empty = IsEmpty(true);
+        write!(empty, "{}", Path(self.error))?;
+        if !empty.0 {
+            write!(f, "{}: ", Path(self.error))?;
+        }
+        self.error.fmt_reason(f)?;
+        f.write_char('\n')
+    }
+}
+
+impl<E: Diagnostic + ?Sized> fmt::Display for Hexdump<'_, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let location = self.error.location().filter(|l| {
+            !self.buffer.is_empty() && l.offset <= self.buffer.len()
+        });
+        let Some(Location { offset, len }) = location else {
+            return self.fmt_label(f);
+        };
+        let start = offset;
+        let end = offset.saturating_add(len).min(self.buffer.len());
+
+        let last_buffer_row = (self.buffer.len() - 1) / BYTES_PER_ROW;
+        let first_row = usize::min(start / BYTES_PER_ROW, last_buffer_row);
+        let last_row = usize::min(
+            end.saturating_sub(1).max(start) / BYTES_PER_ROW,
+            last_buffer_row,
+        );
+
+        for row in first_row.saturating_sub(self.context_rows)..first_row {
+            self.fmt_row(f, row)?;
+        }
+
+        let highlighted = last_row - first_row + 1;
+        for row in first_row..=last_row {
+            if highlighted > MAX_HIGHLIGHTED_ROWS {
+                let skip_start = first_row + MAX_HIGHLIGHTED_ROWS / 2;
+                let skip_end = last_row + 1 - MAX_HIGHLIGHTED_ROWS / 2;
+                if row == skip_start {
+                    writeln!(f, "{:OFFSET_WIDTH$}...", "")?;
+                }
+                if (skip_start..skip_end).contains(&row) {
+                    continue;
+                }
+            }
+
+            self.fmt_row(f, row)?;
+            let indent = self.fmt_carets(f, row, start, end)?;
+            if row == last_row {
+                write!(f, "{:indent$}", "")?;
+                self.fmt_label(f)?;
+            }
+        }
+
+        let after = usize::min(last_row + self.context_rows, last_buffer_row);
+        for row in last_row + 1..=after {
+            self.fmt_row(f, row)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the column of the byte at `index` in a row.
+fn column(index: usize) -> usize {
+    let gap = if index >= BYTES_PER_ROW / 2 { 1 } else { 0 };
+    OFFSET_WIDTH + 3 * index + gap
+}
+
+struct Path<'a, E: ?Sized>(&'a E);
+
+impl<E: Diagnostic + ?Sized> fmt::Display for Path<'_, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt_path(f)
+    }
+}
+
+struct IsEmpty(bool);
+
+impl fmt::Write for IsEmpty {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0 &= s.is_empty();
+        Ok(())
+    }
+}
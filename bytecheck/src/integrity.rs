@@ -0,0 +1,359 @@
+//! Checksums which protect values from corruption.
+//!
+//! Checking a value catches invalid bit patterns, but not corruption which
+//! leaves it valid, like a flipped bit in an integer. An [`Integrity`] stores a
+//! checksum of the bytes of its value, and checking it verifies the checksum
+//! before checking the value. Corrupted values fail with a
+//! [`ChecksumMismatchError`] instead of an error from one of their fields.
+//!
+//! Two checksum algorithms are provided: [`Crc32c`] and [`XxHash64`].
+//!
+//! ```
+//! use bytecheck::{
+//!     check_bytes,
+//!     integrity::{Integrity, XxHash64},
+//!     rancor::Failure,
+//! };
+//!
+//! // SAFETY: `u32` doesn't contain any padding bytes.
+//! let mut value = unsafe { Integrity::<u32, XxHash64>::new(42) };
+//! unsafe {
+//!     check_bytes::<_, Failure>(&value).unwrap();
+//! }
+//!
+//! // Flip a bit in the value.
+//! unsafe {
+//!     let ptr = (&mut value as *mut Integrity<u32, XxHash64>).cast::<u8>();
+//!     *ptr.add(8) ^= 1;
+//! }
+//! unsafe {
+//!     assert!(check_bytes::<_, Failure>(&value).is_err());
+//! }
+//! ```
+
+use core::{
+    fmt,
+    marker::PhantomData,
+    mem,
+    ptr::{self, addr_of},
+    slice,
+};
+
+use rancor::{Fallible, ResultExt as _, Source, Trace};
+
+use crate::{
+    fingerprint::{Fingerprint, FingerprintHasher},
+    schema::{Schema, SchemaKind, TypeSchema},
+    CheckBytes, LocationContext, StructCheckContext,
+};
+
+/// An algorithm for computing checksums of bytes.
+pub trait ChecksumAlgorithm {
+    /// The name of the algorithm.
+    const NAME: &'static str;
+
+    /// Returns the checksum of `bytes`.
+    fn checksum(bytes: &[u8]) -> u64;
+}
+
+/// The CRC-32C (Castagnoli) checksum algorithm.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Crc32c;
+
+impl ChecksumAlgorithm for Crc32c {
+    const NAME: &'static str = "CRC-32C";
+
+    #[inline]
+    fn checksum(bytes: &[u8]) -> u64 {
+        crc32c(bytes) as u64
+    }
+}
+
+/// The xxHash64 checksum algorithm, with a seed of 0.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct XxHash64;
+
+impl ChecksumAlgorithm for XxHash64 {
+    const NAME: &'static str = "xxHash64";
+
+    #[inline]
+    fn checksum(bytes: &[u8]) -> u64 {
+        xxhash64(bytes, 0)
+    }
+}
+
+const CRC32C_TABLE: [u32; 256] = {
+    // The reversed Castagnoli polynomial.
+    const POLY: u32 = 0x82f6_3b78;
+
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ POLY
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// Returns the CRC-32C checksum of `bytes`.
+pub fn crc32c(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc = (crc >> 8) ^ CRC32C_TABLE[((crc ^ byte as u32) & 0xff) as usize];
+    }
+    !crc
+}
+
+const PRIME64_1: u64 = 0x9e37_79b1_85eb_ca87;
+const PRIME64_2: u64 = 0xc2b2_ae3d_27d4_eb4f;
+const PRIME64_3: u64 = 0x1656_67b1_9e37_79f9;
+const PRIME64_4: u64 = 0x85eb_ca77_c2b2_ae63;
+const PRIME64_5: u64 = 0x27d4_eb2f_1656_67c5;
+
+#[inline]
+fn read_u64(bytes: &[u8]) -> u64 {
+    let mut buf = [0; 8];
+    buf.copy_from_slice(&bytes[..8]);
+    u64::from_le_bytes(buf)
+}
+
+#[inline]
+fn read_u32(bytes: &[u8]) -> u32 {
+    let mut buf = [0; 4];
+    buf.copy_from_slice(&bytes[..4]);
+    u32::from_le_bytes(buf)
+}
+
+#[inline]
+fn xxh64_round(acc: u64, input: u64) -> u64 {
+    acc.wrapping_add(input.wrapping_mul(PRIME64_2))
+        .rotate_left(31)
+        .wrapping_mul(PRIME64_1)
+}
+
+#[inline]
+fn xxh64_merge(acc: u64, value: u64) -> u64 {
+    (acc ^ xxh64_round(0, value))
+        .wrapping_mul(PRIME64_1)
+        .wrapping_add(PRIME64_4)
+}
+
+/// Returns the xxHash64 hash of `bytes` with the given seed.
+pub fn xxhash64(bytes: &[u8], seed: u64) -> u64 {
+    let mut rest = bytes;
+    let mut hash = if bytes.len() >= 32 {
+        let mut v1 = seed.wrapping_add(PRIME64_1).wrapping_add(PRIME64_2);
+        let mut v2 = seed.wrapping_add(PRIME64_2);
+        let mut v3 = seed;
+        let mut v4 = seed.wrapping_sub(PRIME64_1);
+        while rest.len() >= 32 {
+            v1 = xxh64_round(v1, read_u64(rest));
+            v2 = xxh64_round(v2, read_u64(&rest[8..]));
+            v3 = xxh64_round(v3, read_u64(&rest[16..]));
+            v4 = xxh64_round(v4, read_u64(&rest[24..]));
+            rest = &rest[32..];
+        }
+        let hash = v1
+            .rotate_left(1)
+            .wrapping_add(v2.rotate_left(7))
+            .wrapping_add(v3.rotate_left(12))
+            .wrapping_add(v4.rotate_left(18));
+        let hash = xxh64_merge(hash, v1);
+        let hash = xxh64_merge(hash, v2);
+        let hash = xxh64_merge(hash, v3);
+        xxh64_merge(hash, v4)
+    } else {
+        seed.wrapping_add(PRIME64_5)
+    };
+
+    hash = hash.wrapping_add(bytes.len() as u64);
+    while rest.len() >= 8 {
+        hash ^= xxh64_round(0, read_u64(rest));
+        hash = hash
+            .rotate_left(27)
+            .wrapping_mul(PRIME64_1)
+            .wrapping_add(PRIME64_4);
+        rest = &rest[8..];
+    }
+    if rest.len() >= 4 {
+        hash ^= (read_u32(rest) as u64).wrapping_mul(PRIME64_1);
+        hash = hash
+            .rotate_left(23)
+            .wrapping_mul(PRIME64_2)
+            .wrapping_add(PRIME64_3);
+        rest = &rest[4..];
+    }
+    for &byte in rest {
+        hash ^= (byte as u64).wrapping_mul(PRIME64_5);
+        hash = hash.rotate_left(11).wrapping_mul(PRIME64_1);
+    }
+
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(PRIME64_2);
+    hash ^= hash >> 29;
+    hash = hash.wrapping_mul(PRIME64_3);
+    hash ^ (hash >> 32)
+}
+
+/// A value preceded by a checksum of its bytes.
+///
+/// The checksum is stored as a `u64`, even for algorithms which produce
+/// smaller checksums.
+#[repr(C)]
+pub struct Integrity<T, A = Crc32c> {
+    checksum: u64,
+    value: T,
+    _algorithm: PhantomData<fn() -> A>,
+}
+
+impl<T, A: ChecksumAlgorithm> Integrity<T, A> {
+    /// Returns a new `Integrity` containing `value` and its checksum.
+    ///
+    /// # Safety
+    ///
+    /// `T` must not contain any padding or other uninitialized bytes.
+    #[inline]
+    pub unsafe fn new(value: T) -> Self {
+        // SAFETY: The caller has guaranteed that all of the bytes of `value`
+        // are initialized.
+        let bytes = unsafe { value_bytes(&value) };
+        Self {
+            checksum: A::checksum(bytes),
+            value,
+            _algorithm: PhantomData,
+        }
+    }
+}
+
+impl<T, A> Integrity<T, A> {
+    /// Returns the stored checksum.
+    #[inline]
+    pub fn checksum(&self) -> u64 {
+        self.checksum
+    }
+
+    /// Returns the value.
+    #[inline]
+    pub fn value(&self) -> &T {
+        &self.value
+    }
+
+    /// Returns the value, consuming the `Integrity`.
+    #[inline]
+    pub fn into_value(self) -> T {
+        self.value
+    }
+}
+
+impl<T: fmt::Debug, A> fmt::Debug for Integrity<T, A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Integrity")
+            .field("checksum", &self.checksum)
+            .field("value", &self.value)
+            .finish()
+    }
+}
+
+impl<T: Fingerprint, A: ChecksumAlgorithm> Fingerprint for Integrity<T, A> {
+    const FINGERPRINT: u64 = FingerprintHasher::new()
+        .write_str("Integrity")
+        .write_str(A::NAME)
+        .write_u64(T::FINGERPRINT)
+        .finish();
+}
+
+// Schemas can't describe checksums, so an `Integrity` is opaque.
+impl<T, A> Schema for Integrity<T, A> {
+    const SCHEMA: &'static TypeSchema<'static> =
+        &TypeSchema::sized::<Integrity<T, A>>("Integrity", SchemaKind::Opaque);
+}
+
+/// Returns the bytes of the value pointed to by `value`.
+///
+/// # Safety
+///
+/// `value` must point to `size_of::<T>()` initialized bytes which stay valid
+/// for `'a`.
+unsafe fn value_bytes<'a, T>(value: *const T) -> &'a [u8] {
+    // SAFETY: The caller has guaranteed that `value` points to enough
+    // initialized bytes for a `T`.
+    unsafe { slice::from_raw_parts(value.cast::<u8>(), mem::size_of::<T>()) }
+}
+
+// SAFETY: `check_bytes` only returns `Ok` if the value is a valid `T`. All bit
+// patterns are valid for the checksum.
+unsafe impl<T, A, C> CheckBytes<C> for Integrity<T, A>
+where
+    T: CheckBytes<C>,
+    A: ChecksumAlgorithm,
+    C: Fallible + ?Sized,
+    C::Error: Source,
+{
+    unsafe fn check_bytes(
+        value: *const Self,
+        context: &mut C,
+    ) -> Result<(), C::Error> {
+        // SAFETY: The caller has guaranteed that `value` is aligned and points
+        // to enough initialized bytes for an `Integrity`.
+        let (stored, inner) = unsafe {
+            (
+                ptr::read(addr_of!((*value).checksum)),
+                addr_of!((*value).value),
+            )
+        };
+        // SAFETY: `inner` points to the initialized bytes of the value.
+        let computed = A::checksum(unsafe { value_bytes(inner) });
+        if computed != stored {
+            return Err(C::Error::new(ChecksumMismatchError {
+                algorithm: A::NAME,
+                expected: stored,
+                found: computed,
+            })
+            .trace(LocationContext::new(inner)));
+        }
+
+        // SAFETY: The caller has guaranteed that `value` points to enough
+        // bytes for an `Integrity`, so the field is in bounds and aligned.
+        unsafe {
+            T::check_bytes(inner, context).with_trace(|| StructCheckContext {
+                struct_name: "Integrity",
+                field_name: "value",
+            })
+        }
+    }
+}
+
+/// An error resulting from a value whose checksum doesn't match the stored
+/// checksum.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ChecksumMismatchError {
+    /// The name of the checksum algorithm.
+    pub algorithm: &'static str,
+    /// The stored checksum.
+    pub expected: u64,
+    /// The checksum of the value.
+    pub found: u64,
+}
+
+impl fmt::Display for ChecksumMismatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} checksum {:#x} does not match stored checksum {:#x}",
+            self.algorithm, self.found, self.expected,
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ChecksumMismatchError {}
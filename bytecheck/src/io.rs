@@ -0,0 +1,187 @@
+//! Reading and checking values from readers.
+//!
+//! [`read`] and [`read_slice`] read exactly enough bytes for a `T` or `[T]`
+//! from an [`io::Read`] into aligned storage, check them, and return the
+//! owned value. Failures are reported as a [`ReadError`], which keeps I/O
+//! errors separate from validation errors.
+//!
+//! ```
+//! use bytecheck::{io, rancor::Failure};
+//!
+//! let mut reader = &[1u8, 0, 1, 2][..];
+//! let values = io::read_slice::<bool, Failure, _>(&mut reader, 3).unwrap();
+//! assert_eq!(values, [true, false, true]);
+//!
+//! let error = io::read::<bool, Failure, _>(&mut reader).unwrap_err();
+//! assert!(matches!(error, io::ReadError::Invalid(_)));
+//! let error = io::read::<bool, Failure, _>(&mut reader).unwrap_err();
+//! assert!(matches!(error, io::ReadError::Io(_)));
+//! ```
+
+use core::{
+    fmt,
+    mem::{self, MaybeUninit},
+    ptr, slice,
+};
+use std::io::{self, Read};
+
+use rancor::Strategy;
+
+use crate::CheckBytes;
+
+/// An error resulting from reading and checking a value.
+#[derive(Debug)]
+pub enum ReadError<E> {
+    /// Reading the bytes of the value failed.
+    Io(io::Error),
+    /// The bytes were read, but were not a valid value.
+    Invalid(E),
+}
+
+impl<E> ReadError<E> {
+    /// Returns the I/O error, if reading failed.
+    #[inline]
+    pub fn io_error(&self) -> Option<&io::Error> {
+        match self {
+            Self::Io(error) => Some(error),
+            Self::Invalid(_) => None,
+        }
+    }
+
+    /// Returns the validation error, if the value was invalid.
+    #[inline]
+    pub fn validation_error(&self) -> Option<&E> {
+        match self {
+            Self::Io(_) => None,
+            Self::Invalid(error) => Some(error),
+        }
+    }
+}
+
+impl<E: fmt::Display> fmt::Display for ReadError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "failed to read value: {error}"),
+            Self::Invalid(error) => error.fmt(f),
+        }
+    }
+}
+
+impl<E: std::error::Error + 'static> std::error::Error for ReadError<E> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(error) => Some(error),
+            Self::Invalid(error) => Some(error),
+        }
+    }
+}
+
+/// Reads the bytes of a `T` from `reader` and checks them.
+#[inline]
+pub fn read<T, E, R>(reader: &mut R) -> Result<T, ReadError<E>>
+where
+    T: CheckBytes<Strategy<(), E>>,
+    R: Read + ?Sized,
+{
+    read_with_context(reader, &mut ())
+}
+
+/// Reads the bytes of a `T` from `reader` and checks them within the given
+/// context.
+pub fn read_with_context<T, C, E, R>(
+    reader: &mut R,
+    context: &mut C,
+) -> Result<T, ReadError<E>>
+where
+    T: CheckBytes<Strategy<C, E>>,
+    C: ?Sized,
+    R: Read + ?Sized,
+{
+    // The bytes are zeroed so that `read_exact` never sees uninitialized
+    // memory.
+    let mut value = MaybeUninit::<T>::zeroed();
+    // SAFETY: `value` is `size_of::<T>()` bytes long and its bytes are
+    // initialized.
+    let bytes = unsafe {
+        slice::from_raw_parts_mut(
+            value.as_mut_ptr().cast::<u8>(),
+            mem::size_of::<T>(),
+        )
+    };
+    reader.read_exact(bytes).map_err(ReadError::Io)?;
+    // SAFETY: All of the bytes of `value` are initialized, and `value` is
+    // aligned for `T`.
+    unsafe {
+        CheckBytes::check_bytes(value.as_ptr(), Strategy::wrap(context))
+            .map_err(ReadError::Invalid)?;
+    }
+    // SAFETY: `check_bytes` returned `Ok`, so `value` is a valid `T`.
+    Ok(unsafe { value.assume_init() })
+}
+
+/// Reads the bytes of `len` elements of `T` from `reader` and checks them.
+#[inline]
+pub fn read_slice<T, E, R>(
+    reader: &mut R,
+    len: usize,
+) -> Result<Vec<T>, ReadError<E>>
+where
+    [T]: CheckBytes<Strategy<(), E>>,
+    R: Read + ?Sized,
+{
+    read_slice_with_context(reader, len, &mut ())
+}
+
+/// Reads the bytes of `len` elements of `T` from `reader` and checks them
+/// within the given context.
+///
+/// Storage for all of the elements is allocated before reading, so `len`
+/// should be limited when it comes from untrusted input.
+pub fn read_slice_with_context<T, C, E, R>(
+    reader: &mut R,
+    len: usize,
+    context: &mut C,
+) -> Result<Vec<T>, ReadError<E>>
+where
+    [T]: CheckBytes<Strategy<C, E>>,
+    C: ?Sized,
+    R: Read + ?Sized,
+{
+    let Some(size) = mem::size_of::<T>().checked_mul(len) else {
+        return Err(ReadError::Io(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "slice is too large to read",
+        )));
+    };
+    let mut values = Vec::<T>::new();
+    if values.try_reserve_exact(len).is_err() {
+        return Err(ReadError::Io(io::Error::new(
+            io::ErrorKind::OutOfMemory,
+            "failed to allocate storage for slice",
+        )));
+    }
+    let data = values.as_mut_ptr();
+    // SAFETY: `values` has capacity for `len` elements, which is `size`
+    // bytes. The bytes are zeroed so that `read_exact` never sees
+    // uninitialized memory.
+    let bytes = unsafe {
+        data.cast::<u8>().write_bytes(0, size);
+        slice::from_raw_parts_mut(data.cast::<u8>(), size)
+    };
+    reader.read_exact(bytes).map_err(ReadError::Io)?;
+    // SAFETY: All of the bytes of the first `len` elements are initialized,
+    // and `data` is aligned for `T`.
+    unsafe {
+        CheckBytes::check_bytes(
+            ptr::slice_from_raw_parts(data.cast_const(), len),
+            Strategy::wrap(context),
+        )
+        .map_err(ReadError::Invalid)?;
+    }
+    // SAFETY: `check_bytes` returned `Ok`, so the first `len` elements are
+    // valid.
+    unsafe {
+        values.set_len(len);
+    }
+    Ok(values)
+}
@@ -101,6 +101,11 @@
 //! ## Features
 //!
 //! - `std`: (Enabled by default) Enables standard library support.
+//! - `hexdump`: Enables rendering annotated hexdumps of validation failures.
+//! - `mmap`: Enables checking values in memory-mapped files with `memmap2`.
+//!   Implies `std`.
+//! - `rayon`: Enables checking large slices in parallel with `rayon`. Implies
+//!   `std`.
 //!
 //! ## Crate support
 //!
@@ -130,6 +135,33 @@
 )]
 #![cfg_attr(not(feature = "std"), no_std)]
 
+pub mod bounds;
+pub mod budget;
+pub mod container;
+pub mod depth;
+pub mod dynamic;
+pub mod envelope;
+pub mod error;
+pub mod evolve;
+pub mod fingerprint;
+pub mod frame;
+#[cfg(feature = "hexdump")]
+pub mod hexdump;
+pub mod integrity;
+#[cfg(feature = "std")]
+pub mod io;
+#[cfg(feature = "mmap")]
+pub mod mmap;
+#[cfg(feature = "rayon")]
+pub mod parallel;
+pub mod path;
+pub mod rel_ptr;
+pub mod schema;
+pub mod shared;
+pub mod snapshot;
+pub mod stream;
+pub mod tlv;
+
 // Support for various common crates. These are primarily to get users off the
 // ground and build some momentum.
 
@@ -154,7 +186,7 @@ use core::sync::atomic::{AtomicI64, AtomicU64};
 use core::{
     fmt,
     marker::{PhantomData, PhantomPinned},
-    mem::ManuallyDrop,
+    mem::{self, ManuallyDrop},
     num::{
         NonZeroI128, NonZeroI16, NonZeroI32, NonZeroI64, NonZeroI8,
         NonZeroU128, NonZeroU16, NonZeroU32, NonZeroU64, NonZeroU8,
@@ -165,6 +197,8 @@ use rancor::{fail, Source, Fallible, ResultExt as _, Strategy, Trace};
 #[cfg(feature = "simdutf8")]
 use simdutf8::basic::from_utf8;
 
+use crate::{budget::BudgetContext, error::ErrorKind};
+
 pub use bytecheck_derive::CheckBytes;
 pub use rancor;
 
@@ -241,6 +275,158 @@ where
     unsafe { CheckBytes::check_bytes(value, Strategy::wrap(context)) }
 }
 
+/// Checks whether the given bytes are a valid `T` and returns a reference to
+/// it.
+///
+/// The buffer must be aligned for `T` and exactly `size_of::<T>()` bytes long.
+/// Errors which record a [`LocationContext`] can report the location of the
+/// invalid value relative to the start of `bytes`.
+#[inline]
+pub fn from_bytes<T, E>(bytes: &[u8]) -> Result<&T, E>
+where
+    T: CheckBytes<Strategy<(), E>>,
+    E: Source,
+{
+    from_bytes_with_context(bytes, &mut ())
+}
+
+/// Checks whether the given bytes are a valid `T` within the given context and
+/// returns a reference to it.
+///
+/// See [`from_bytes`] for more details.
+pub fn from_bytes_with_context<'a, T, C, E>(
+    bytes: &'a [u8],
+    context: &mut C,
+) -> Result<&'a T, E>
+where
+    T: CheckBytes<Strategy<C, E>>,
+    C: ?Sized,
+    E: Source,
+{
+    check_buffer::<E>(bytes, mem::align_of::<T>(), mem::size_of::<T>())?;
+    let ptr = bytes.as_ptr().cast::<T>();
+    // SAFETY: We checked that `bytes` is aligned for `T` and is exactly
+    // `size_of::<T>()` bytes long, and the bytes of a slice are always
+    // initialized.
+    unsafe {
+        CheckBytes::check_bytes(ptr, Strategy::wrap(context))
+            .trace(BufferContext::new(bytes))?;
+    }
+    // SAFETY: `check_bytes` returned `Ok`, so `ptr` points to a valid `T`. The
+    // returned reference borrows from `bytes`, so the value can't be mutated
+    // or freed while it's alive.
+    Ok(unsafe { &*ptr })
+}
+
+/// Checks whether the given bytes are a valid slice of `T` and returns a
+/// reference to it.
+///
+/// The buffer must be aligned for `T` and its length must be a multiple of
+/// `size_of::<T>()`. If `T` is zero-sized, the buffer must be empty and the
+/// returned slice is empty.
+#[inline]
+pub fn slice_from_bytes<T, E>(bytes: &[u8]) -> Result<&[T], E>
+where
+    [T]: CheckBytes<Strategy<(), E>>,
+    E: Source,
+{
+    slice_from_bytes_with_context(bytes, &mut ())
+}
+
+/// Checks whether the given bytes are a valid slice of `T` within the given
+/// context and returns a reference to it.
+///
+/// See [`slice_from_bytes`] for more details.
+pub fn slice_from_bytes_with_context<'a, T, C, E>(
+    bytes: &'a [u8],
+    context: &mut C,
+) -> Result<&'a [T], E>
+where
+    [T]: CheckBytes<Strategy<C, E>>,
+    C: ?Sized,
+    E: Source,
+{
+    let size = mem::size_of::<T>();
+    let len = bytes.len().checked_div(size).unwrap_or(0);
+    check_buffer::<E>(bytes, mem::align_of::<T>(), len * size)?;
+    let ptr = ptr::slice_from_raw_parts(bytes.as_ptr().cast::<T>(), len);
+    // SAFETY: We checked that `bytes` is aligned for `T` and is exactly `len`
+    // elements long, and the bytes of a slice are always initialized.
+    unsafe {
+        CheckBytes::check_bytes(ptr, Strategy::wrap(context))
+            .trace(BufferContext::new(bytes))?;
+    }
+    // SAFETY: `check_bytes` returned `Ok`, so `ptr` points to a valid `[T]`.
+    // The returned reference borrows from `bytes`, so the value can't be
+    // mutated or freed while it's alive.
+    Ok(unsafe { &*ptr })
+}
+
+/// Checks that `bytes` is aligned to `align` and exactly `expected` bytes long.
+fn check_buffer<E: Source>(
+    bytes: &[u8],
+    align: usize,
+    expected: usize,
+) -> Result<(), E> {
+    let address = bytes.as_ptr() as usize;
+    if address & (align - 1) != 0 {
+        fail!(UnalignedBufferError { address, align });
+    }
+    if bytes.len() != expected {
+        fail!(BufferLengthError {
+            expected,
+            found: bytes.len(),
+        });
+    }
+    Ok(())
+}
+
+/// An error resulting from a buffer that is not aligned for the type being
+/// checked.
+#[derive(Debug)]
+pub struct UnalignedBufferError {
+    /// The address of the buffer.
+    pub address: usize,
+    /// The required alignment of the buffer.
+    pub align: usize,
+}
+
+impl fmt::Display for UnalignedBufferError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "buffer at address {:#x} is not aligned to {} bytes",
+            self.address, self.align,
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for UnalignedBufferError {}
+
+/// An error resulting from a buffer that has the wrong length for the type
+/// being checked.
+#[derive(Debug)]
+pub struct BufferLengthError {
+    /// The expected length of the buffer in bytes.
+    pub expected: usize,
+    /// The actual length of the buffer in bytes.
+    pub found: usize,
+}
+
+impl fmt::Display for BufferLengthError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "buffer has length {}, expected {}",
+            self.found, self.expected,
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for BufferLengthError {}
+
 macro_rules! impl_primitive {
     ($type:ty) => {
         // SAFETY: All bit patterns are valid for these primitive types.
@@ -328,24 +514,6 @@ where
     }
 }
 
-#[derive(Debug)]
-struct BoolCheckError {
-    byte: u8,
-}
-
-impl fmt::Display for BoolCheckError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(
-            f,
-            "bool set to invalid byte {}, expected either 0 or 1",
-            self.byte,
-        )
-    }
-}
-
-#[cfg(feature = "std")]
-impl std::error::Error for BoolCheckError {}
-
 // SAFETY: A bool is a one byte value that must either be 0 or 1. `check_bytes`
 // only returns `Ok` if `value` is 0 or 1.
 unsafe impl<C> CheckBytes<C> for bool
@@ -365,7 +533,8 @@ where
         let byte = unsafe { *value.cast::<u8>() };
         match byte {
             0 | 1 => Ok(()),
-            _ => fail!(BoolCheckError { byte }),
+            _ => Err(C::Error::new(ErrorKind::InvalidBool { byte })
+                .trace(LocationContext::new(value))),
         }
     }
 }
@@ -404,7 +573,10 @@ where
         // ensures that we can read a `u32` regardless and try to convert it to
         // a `char`.
         let value = unsafe { ptr.cast::<u32>().read_unaligned() };
-        char::try_from(value).into_error()?;
+        if char::from_u32(value).is_none() {
+            return Err(C::Error::new(ErrorKind::InvalidChar { value })
+                .trace(LocationContext::new(ptr)));
+        }
         Ok(())
     }
 }
@@ -529,7 +701,7 @@ impl fmt::Display for SliceCheckContext {
 unsafe impl<T, C> CheckBytes<C> for [T]
 where
     T: CheckBytes<C>,
-    C: Fallible + ?Sized,
+    C: Fallible + BudgetContext + ?Sized,
     C::Error: Trace,
 {
     #[inline]
@@ -538,6 +710,9 @@ where
         context: &mut C,
     ) -> Result<(), C::Error> {
         let (data_address, len) = ptr_meta::PtrExt::to_raw_parts(value);
+        // Charge for the whole slice up front so that a huge length fails
+        // before any elements are checked.
+        context.charge(len)?;
         let base = data_address.cast::<T>();
         for index in 0..len {
             // SAFETY: The caller has guaranteed that `value` points to enough
@@ -556,13 +731,13 @@ where
 // valid UTF-8. If they are valid UTF-8 then the overall `str` is also valid.
 unsafe impl<C> CheckBytes<C> for str
 where
-    C: Fallible + ?Sized,
+    C: Fallible + BudgetContext + ?Sized,
     C::Error: Source,
 {
     #[inline]
     unsafe fn check_bytes(
         value: *const Self,
-        _: &mut C,
+        context: &mut C,
     ) -> Result<(), C::Error> {
         let slice_ptr = value as *const [u8];
         // SAFETY: The caller has guaranteed that `value` is properly-aligned
@@ -570,7 +745,18 @@ where
         // the same layout as a `str`, we can dereference it for UTF-8
         // validation.
         let slice = unsafe { &*slice_ptr };
-        from_utf8(slice).into_error()?;
+        context.charge(slice.len())?;
+        if from_utf8(slice).is_err() {
+            // `simdutf8`'s basic errors don't report where the invalid bytes
+            // are, so get that from `core` instead.
+            let valid_up_to = core::str::from_utf8(slice)
+                .map_or_else(|e| e.valid_up_to(), |s| s.len());
+            return Err(C::Error::new(ErrorKind::InvalidUtf8 { valid_up_to })
+                .trace(LocationContext {
+                    address: slice.as_ptr() as usize,
+                    len: slice.len(),
+                }));
+        }
         Ok(())
     }
 }
@@ -580,26 +766,102 @@ where
 // `CStr` per `CStr::from_bytes_with_nul`.
 unsafe impl<C> CheckBytes<C> for std::ffi::CStr
 where
-    C: Fallible + ?Sized,
+    C: Fallible + BudgetContext + ?Sized,
     C::Error: Source,
 {
     #[inline]
     unsafe fn check_bytes(
         value: *const Self,
-        _: &mut C,
+        context: &mut C,
     ) -> Result<(), C::Error> {
         let slice_ptr = value as *const [u8];
         // SAFETY: The caller has guaranteed that `value` is properly-aligned
         // and points to enough bytes for its `CStr`. Because a `u8` slice has
         // the same layout as a `CStr`, we can dereference it for validation.
         let slice = unsafe { &*slice_ptr };
-        std::ffi::CStr::from_bytes_with_nul(slice).into_error()?;
-        Ok(())
+        context.charge(slice.len())?;
+        // A `CStr` must end with a nul byte and contain no other nul bytes,
+        // which is the same check `CStr::from_bytes_with_nul` performs.
+        let kind = match slice.iter().position(|&b| b == 0) {
+            Some(position) if position == slice.len() - 1 => return Ok(()),
+            Some(position) => ErrorKind::InteriorNul { position },
+            None => ErrorKind::MissingNul,
+        };
+        Err(C::Error::new(kind).trace(LocationContext {
+            address: slice.as_ptr() as usize,
+            len: slice.len(),
+        }))
     }
 }
 
 // Generic contexts used by the derive.
 
+/// Context for errors that records the location of the value that was invalid.
+///
+/// Built-in implementations add this context when a value fails to validate.
+/// Error types like `error::CheckError` combine it with a [`BufferContext`] to
+/// report where in a buffer the invalid bytes are.
+#[derive(Debug)]
+pub struct LocationContext {
+    /// The address of the invalid value.
+    pub address: usize,
+    /// The size of the invalid value in bytes.
+    pub len: usize,
+}
+
+impl LocationContext {
+    /// Returns the location of the value pointed to by `ptr`.
+    #[inline]
+    pub fn new<T>(ptr: *const T) -> Self {
+        Self {
+            address: ptr as usize,
+            len: mem::size_of::<T>(),
+        }
+    }
+}
+
+impl fmt::Display for LocationContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "while checking {} bytes at address {:#x}",
+            self.len, self.address,
+        )
+    }
+}
+
+/// Context for errors that records the buffer being checked.
+///
+/// The safe entry points like [`from_bytes`] add this context to every error.
+#[derive(Debug)]
+pub struct BufferContext {
+    /// The address of the start of the buffer.
+    pub address: usize,
+    /// The length of the buffer in bytes.
+    pub len: usize,
+}
+
+impl BufferContext {
+    /// Returns the location of the given buffer.
+    #[inline]
+    pub fn new(bytes: &[u8]) -> Self {
+        Self {
+            address: bytes.as_ptr() as usize,
+            len: bytes.len(),
+        }
+    }
+}
+
+impl fmt::Display for BufferContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "while checking buffer of {} bytes at address {:#x}",
+            self.len, self.address,
+        )
+    }
+}
+
 /// Context for errors resulting from invalid structs.
 #[derive(Debug)]
 pub struct StructCheckContext {
@@ -638,29 +900,134 @@ impl fmt::Display for TupleStructCheckContext {
     }
 }
 
+/// An integer type which can be used as the discriminant of an enum.
+pub trait EnumRepr: Copy + fmt::Debug + fmt::Display + 'static {
+    /// Returns the absolute difference between `self` and `other`.
+    fn abs_diff(self, other: Self) -> u128;
+}
+
+macro_rules! impl_enum_repr {
+    ($($ty:ty),* $(,)?) => {
+        $(
+            impl EnumRepr for $ty {
+                #[inline]
+                fn abs_diff(self, other: Self) -> u128 {
+                    <$ty>::abs_diff(self, other) as u128
+                }
+            }
+        )*
+    };
+}
+
+impl_enum_repr!(i8, i16, i32, i64, i128, u8, u16, u32, u64, u128);
+
+/// A variant of an enum and its discriminant.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EnumVariant<T> {
+    /// The name of the variant.
+    pub name: &'static str,
+    /// The discriminant of the variant.
+    pub discriminant: T,
+}
+
+/// An enum whose variants and their discriminants are known statically.
+///
+/// This is implemented for enums by the [`CheckBytes`](macro@CheckBytes)
+/// derive.
+pub trait EnumVariants {
+    /// The integer type of the enum's discriminant.
+    type Repr: EnumRepr;
+
+    /// The variants of the enum, in declaration order.
+    const VARIANTS: &'static [EnumVariant<Self::Repr>];
+}
+
 /// An error resulting from an invalid enum tag.
 #[derive(Debug)]
-pub struct InvalidEnumDiscriminantError<T> {
+pub struct InvalidEnumDiscriminantError<T: 'static> {
     /// The name of the enum with an invalid discriminant.
     pub enum_name: &'static str,
     /// The invalid value of the enum discriminant.
     pub invalid_discriminant: T,
+    /// The variants of the enum, which have the valid discriminants.
+    pub variants: &'static [EnumVariant<T>],
 }
 
-impl<T: fmt::Display> fmt::Display for InvalidEnumDiscriminantError<T> {
+impl<T: EnumRepr> InvalidEnumDiscriminantError<T> {
+    /// The greatest difference between the invalid discriminant and a valid one
+    /// for the valid one to be considered close.
+    const CLOSE_DISTANCE: u128 = 2;
+
+    /// Returns the variant with the discriminant closest to the invalid
+    /// discriminant, if it differs by at most two.
+    ///
+    /// A close variant often indicates that the data was written by a newer or
+    /// older version of the enum.
+    pub fn closest_variant(&self) -> Option<&'static EnumVariant<T>> {
+        self.variants
+            .iter()
+            .min_by_key(|v| v.discriminant.abs_diff(self.invalid_discriminant))
+            .filter(|v| {
+                v.discriminant.abs_diff(self.invalid_discriminant)
+                    <= Self::CLOSE_DISTANCE
+            })
+    }
+}
+
+impl<T: EnumRepr> fmt::Display for InvalidEnumDiscriminantError<T> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(
+        fmt_invalid_discriminant(
             f,
-            "invalid discriminant '{}' for enum '{}'",
-            self.invalid_discriminant, self.enum_name
+            self.enum_name,
+            self.invalid_discriminant,
+            self.variants.iter().map(|v| (v.name, v.discriminant)),
+            self.closest_variant().map(|v| (v.name, v.discriminant)),
         )
     }
 }
 
+/// Writes the message for an invalid discriminant of an enum with the given
+/// variants and closest variant.
+fn fmt_invalid_discriminant<D: fmt::Display>(
+    f: &mut fmt::Formatter<'_>,
+    enum_name: &str,
+    invalid_discriminant: D,
+    variants: impl Iterator<Item = (&'static str, D)>,
+    closest: Option<(&'static str, D)>,
+) -> fmt::Result {
+    write!(
+        f,
+        "invalid discriminant '{}' for enum '{}'",
+        invalid_discriminant, enum_name
+    )?;
+    for (i, (name, discriminant)) in variants.enumerate() {
+        let prefix = if i == 0 { ", expected one of" } else { "," };
+        write!(f, "{} {} ({})", prefix, discriminant, name)?;
+    }
+    if let Some((name, discriminant)) = closest {
+        write!(
+            f,
+            "; closest known variant is '{}' ({})",
+            name, discriminant,
+        )?;
+    }
+    Ok(())
+}
+
 #[cfg(feature = "std")]
-impl<T> std::error::Error for InvalidEnumDiscriminantError<T> where
-    T: fmt::Debug + fmt::Display
-{
+impl<T: EnumRepr> std::error::Error for InvalidEnumDiscriminantError<T> {}
+
+/// Context for errors resulting from a type's [`Verify`] implementation.
+#[derive(Debug)]
+pub struct VerifyContext {
+    /// The name of the type that failed to verify.
+    pub type_name: &'static str,
+}
+
+impl fmt::Display for VerifyContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "while verifying '{}'", self.type_name)
+    }
 }
 
 /// Context for errors resulting from checking enum variants with named fields.
@@ -842,19 +1209,6 @@ where
     }
 }
 
-#[derive(Debug)]
-struct NonZeroCheckError;
-
-impl fmt::Display for NonZeroCheckError {
-    #[inline]
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "nonzero integer is zero")
-    }
-}
-
-#[cfg(feature = "std")]
-impl std::error::Error for NonZeroCheckError {}
-
 macro_rules! impl_nonzero {
     ($nonzero:ident, $underlying:ident) => {
         // SAFETY: `check_bytes` only returns `Ok` when `value` is not zero, the
@@ -874,7 +1228,8 @@ macro_rules! impl_nonzero {
                 // have no validity requirements, so we can cast and dereference
                 // value to check if it is equal to zero.
                 if unsafe { *value.cast::<$underlying>() } == 0 {
-                    fail!(NonZeroCheckError);
+                    Err(C::Error::new(ErrorKind::ZeroNonZero)
+                        .trace(LocationContext::new(value)))
                 } else {
                     Ok(())
                 }
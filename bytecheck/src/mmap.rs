@@ -0,0 +1,233 @@
+//! Checking values in memory-mapped files.
+//!
+//! [`open`] and [`open_slice`] map part of a file read-only and check it as a
+//! `T` or `[T]`. The returned [`Mapped`] handle keeps the mapping alive and
+//! dereferences to the checked value.
+//!
+//! Values are checked in place with [`from_bytes`](crate::from_bytes) and
+//! [`slice_from_bytes`](crate::slice_from_bytes). Files are mapped starting at
+//! a page boundary, so the value is aligned if `offset` is a multiple of its
+//! alignment. Errors report locations relative to the start of the value.
+
+use core::{fmt, marker::PhantomData, mem, ops::Deref, ptr};
+use std::{fs::File, path::Path};
+
+use memmap2::{Mmap, MmapOptions};
+use rancor::{fail, Source, Strategy};
+
+use crate::{
+    from_bytes_with_context, slice_from_bytes_with_context, CheckBytes,
+};
+
+/// A checked value in a memory-mapped file.
+///
+/// The file stays mapped until the handle is dropped.
+pub struct Mapped<T: ?Sized> {
+    // Only used to keep the mapping alive.
+    _mmap: Mmap,
+    ptr: *const T,
+    _phantom: PhantomData<T>,
+}
+
+// SAFETY: `Mapped` only provides shared access to its value, and `Mmap` is
+// `Send` and `Sync`.
+unsafe impl<T: Sync + ?Sized> Send for Mapped<T> {}
+
+// SAFETY: `Mapped` only provides shared access to its value, and `Mmap` is
+// `Send` and `Sync`.
+unsafe impl<T: Sync + ?Sized> Sync for Mapped<T> {}
+
+impl<T: ?Sized> Deref for Mapped<T> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        // SAFETY: `ptr` was checked when the handle was created, and it
+        // points into the mapping, which is kept alive as long as `self`.
+        unsafe { &*self.ptr }
+    }
+}
+
+impl<T: fmt::Debug + ?Sized> fmt::Debug for Mapped<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+/// Maps `len` bytes of `file` starting at `offset`.
+///
+/// # Safety
+///
+/// The file must not be modified while the mapping is alive.
+unsafe fn map_range<E: Source>(
+    file: &File,
+    offset: usize,
+    len: usize,
+) -> Result<Mmap, E> {
+    let file_len = file.metadata().map_err(E::new)?.len();
+    let in_bounds = offset
+        .checked_add(len)
+        .is_some_and(|end| end as u64 <= file_len);
+    if !in_bounds {
+        fail!(MapRangeError {
+            offset,
+            len,
+            file_len,
+        });
+    }
+    // SAFETY: The caller has guaranteed that the file will not be modified
+    // while the mapping is alive.
+    unsafe {
+        MmapOptions::new()
+            .offset(offset as u64)
+            .len(len)
+            .map(file)
+            .map_err(E::new)
+    }
+}
+
+/// Opens the file at `path` and checks the `T` at `offset`.
+///
+/// # Safety
+///
+/// The file must not be modified while the returned handle is alive. If it
+/// is, the value may become invalid or the process may crash.
+#[inline]
+pub unsafe fn open<T, E>(
+    path: impl AsRef<Path>,
+    offset: usize,
+) -> Result<Mapped<T>, E>
+where
+    T: CheckBytes<Strategy<(), E>>,
+    E: Source,
+{
+    let file = File::open(path).map_err(E::new)?;
+    // SAFETY: The safety conditions of `map_with_context` are the same as
+    // the safety conditions of this function.
+    unsafe { map_with_context(&file, offset, &mut ()) }
+}
+
+/// Opens the file at `path` and checks the slice of `len` elements of `T` at
+/// `offset`.
+///
+/// # Safety
+///
+/// The file must not be modified while the returned handle is alive. If it
+/// is, the value may become invalid or the process may crash.
+#[inline]
+pub unsafe fn open_slice<T, E>(
+    path: impl AsRef<Path>,
+    offset: usize,
+    len: usize,
+) -> Result<Mapped<[T]>, E>
+where
+    [T]: CheckBytes<Strategy<(), E>>,
+    E: Source,
+{
+    let file = File::open(path).map_err(E::new)?;
+    // SAFETY: The safety conditions of `map_slice_with_context` are the same
+    // as the safety conditions of this function.
+    unsafe { map_slice_with_context(&file, offset, len, &mut ()) }
+}
+
+/// Maps `file` and checks the `T` at `offset` within the given context.
+///
+/// # Safety
+///
+/// The file must not be modified while the returned handle is alive. If it
+/// is, the value may become invalid or the process may crash.
+pub unsafe fn map_with_context<T, C, E>(
+    file: &File,
+    offset: usize,
+    context: &mut C,
+) -> Result<Mapped<T>, E>
+where
+    T: CheckBytes<Strategy<C, E>>,
+    C: ?Sized,
+    E: Source,
+{
+    // SAFETY: The caller has guaranteed that the file will not be modified
+    // while the handle is alive, and the mapping is moved into the handle.
+    let mmap = unsafe { map_range::<E>(file, offset, mem::size_of::<T>())? };
+    let ptr = from_bytes_with_context::<T, C, E>(&mmap, context)? as *const T;
+    Ok(Mapped {
+        _mmap: mmap,
+        ptr,
+        _phantom: PhantomData,
+    })
+}
+
+/// Maps `file` and checks the slice of `len` elements of `T` at `offset`
+/// within the given context.
+///
+/// # Safety
+///
+/// The file must not be modified while the returned handle is alive. If it
+/// is, the value may become invalid or the process may crash.
+pub unsafe fn map_slice_with_context<T, C, E>(
+    file: &File,
+    offset: usize,
+    len: usize,
+    context: &mut C,
+) -> Result<Mapped<[T]>, E>
+where
+    [T]: CheckBytes<Strategy<C, E>>,
+    C: ?Sized,
+    E: Source,
+{
+    let Some(size) = mem::size_of::<T>().checked_mul(len) else {
+        fail!(MapRangeError {
+            offset,
+            len: usize::MAX,
+            file_len: file.metadata().map_err(E::new)?.len(),
+        });
+    };
+    // SAFETY: The caller has guaranteed that the file will not be modified
+    // while the handle is alive, and the mapping is moved into the handle.
+    let mmap = unsafe { map_range::<E>(file, offset, size)? };
+    let ptr = if size == 0 {
+        // Empty mappings may not be aligned, and `slice_from_bytes` can't
+        // tell how many zero-sized elements there are, so empty slices are
+        // checked directly.
+        let data = ptr::NonNull::<T>::dangling().as_ptr().cast_const();
+        let ptr = ptr::slice_from_raw_parts(data, len);
+        // SAFETY: The slice doesn't contain any bytes, so a dangling pointer
+        // is enough to check it.
+        unsafe {
+            CheckBytes::check_bytes(ptr, Strategy::wrap(context))?;
+        }
+        ptr
+    } else {
+        slice_from_bytes_with_context::<T, C, E>(&mmap, context)?
+            as *const [T]
+    };
+    Ok(Mapped {
+        _mmap: mmap,
+        ptr,
+        _phantom: PhantomData,
+    })
+}
+
+/// An error resulting from mapping a range of bytes which extends past the
+/// end of the file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct MapRangeError {
+    /// The offset of the start of the range in bytes.
+    pub offset: usize,
+    /// The length of the range in bytes.
+    pub len: usize,
+    /// The length of the file in bytes.
+    pub file_len: u64,
+}
+
+impl fmt::Display for MapRangeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} bytes at offset {} extend past the end of the file of {} bytes",
+            self.len, self.offset, self.file_len,
+        )
+    }
+}
+
+impl std::error::Error for MapRangeError {}
@@ -0,0 +1,274 @@
+//! Checking large slices in parallel.
+//!
+//! Slices with at least a threshold number of elements are split into chunks
+//! which are checked in parallel with [`rayon`]. Smaller slices are checked
+//! sequentially, since splitting them costs more than it saves.
+//!
+//! Each chunk is checked with its own context, created with
+//! [`SplitContext::split`] and merged back afterward. Errors are reported the
+//! same way as when checking sequentially: if any elements are invalid, the
+//! error is for the element with the lowest index.
+//!
+//! Like `[T]`, one unit of work is charged for each element before any of them
+//! are checked. With a [`Budgeted`] context, the remaining budget is then split
+//! evenly between the chunks, so a slice whose work is concentrated in a few
+//! chunks may exceed its budget when checked in parallel even though it would
+//! not when checked sequentially.
+
+use core::{
+    ops::Range,
+    ptr,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use rancor::{Fallible, ResultExt as _, Source, Strategy, Trace};
+use rayon::iter::{
+    IndexedParallelIterator as _, IntoParallelRefMutIterator as _,
+    ParallelIterator as _,
+};
+
+use crate::{
+    budget::{Budget, BudgetContext, Budgeted},
+    check_buffer,
+    depth::DepthLimit,
+    BufferContext, CheckBytes, SliceCheckContext,
+};
+
+/// The default minimum length of slices which are checked in parallel.
+pub const DEFAULT_THRESHOLD: usize = 16 * 1024;
+
+// The minimum number of elements to check in each chunk.
+const MIN_CHUNK_LEN: usize = 1024;
+// The number of chunks to create for each thread, so that threads which
+// finish early can pick up more work.
+const CHUNKS_PER_THREAD: usize = 4;
+
+/// A context which can be split to check parts of a value in parallel.
+pub trait SplitContext: Sized {
+    /// Returns `parts` new contexts for checking parts of a value in parallel
+    /// with this one.
+    ///
+    /// The returned `Vec` must contain exactly `parts` contexts. Checking
+    /// panics if it does not, since some parts would go unchecked otherwise.
+    fn split(&mut self, parts: usize) -> Vec<Self>;
+
+    /// Merges a context returned by [`split`](SplitContext::split) back into
+    /// this one.
+    fn merge(&mut self, other: Self);
+}
+
+impl SplitContext for () {
+    #[inline]
+    fn split(&mut self, parts: usize) -> Vec<Self> {
+        vec![(); parts]
+    }
+
+    #[inline]
+    fn merge(&mut self, _: Self) {}
+}
+
+impl SplitContext for DepthLimit {
+    #[inline]
+    fn split(&mut self, parts: usize) -> Vec<Self> {
+        vec![*self; parts]
+    }
+
+    #[inline]
+    fn merge(&mut self, _: Self) {}
+}
+
+impl SplitContext for Budget {
+    fn split(&mut self, parts: usize) -> Vec<Self> {
+        self.split_evenly(parts)
+    }
+
+    #[inline]
+    fn merge(&mut self, other: Self) {
+        self.merge_remaining(other);
+    }
+}
+
+impl<C: SplitContext> SplitContext for Budgeted<C> {
+    fn split(&mut self, parts: usize) -> Vec<Self> {
+        let (inner, budget) = self.parts_mut();
+        inner
+            .split(parts)
+            .into_iter()
+            .zip(budget.split(parts))
+            .map(|(inner, budget)| Budgeted::new(inner, budget))
+            .collect()
+    }
+
+    fn merge(&mut self, other: Self) {
+        let (other_inner, other_budget) = other.into_parts();
+        let (inner, budget) = self.parts_mut();
+        inner.merge(other_inner);
+        budget.merge(other_budget);
+    }
+}
+
+/// Checks a slice, checking chunks of it in parallel if it has at least
+/// `threshold` elements.
+///
+/// # Safety
+///
+/// `value` must satisfy the same requirements as for
+/// [`CheckBytes::check_bytes`].
+pub unsafe fn check_slice<T, C, E>(
+    value: *const [T],
+    context: &mut C,
+    threshold: usize,
+) -> Result<(), E>
+where
+    T: CheckBytes<Strategy<C, E>>,
+    C: BudgetContext<E> + SplitContext + Send,
+    E: Source + Send,
+{
+    context.charge(ptr_meta::metadata(value))?;
+    // SAFETY: The caller has guaranteed that `value` meets the requirements of
+    // `check_bytes`, and each range passed to the closure is within it.
+    unsafe {
+        check_chunks(value, context, threshold, |base, range, context| {
+            check_range(base, range, Strategy::<C, E>::wrap(context))
+        })
+    }
+}
+
+/// Checks a slice with `check`, calling it with ranges of chunks in parallel
+/// if the slice has at least `threshold` elements.
+///
+/// # Safety
+///
+/// `value` must point to a slice of `T` which `check` can check in any ranges
+/// within it.
+unsafe fn check_chunks<T, C, E, F>(
+    value: *const [T],
+    context: &mut C,
+    threshold: usize,
+    check: F,
+) -> Result<(), E>
+where
+    C: SplitContext + Send,
+    E: Send,
+    F: Fn(*const T, Range<usize>, &mut C) -> Result<(), E> + Sync,
+{
+    let (data_address, len) = ptr_meta::PtrExt::to_raw_parts(value);
+    let base = data_address.cast::<T>();
+    if len < threshold.max(1) {
+        return check(base, 0..len, context);
+    }
+
+    let chunk_len = len
+        .div_ceil(rayon::current_num_threads() * CHUNKS_PER_THREAD)
+        .max(MIN_CHUNK_LEN);
+    let chunks = len.div_ceil(chunk_len);
+    let mut contexts = context.split(chunks);
+    // Every chunk must be checked with its own context, or the chunks without
+    // one would be skipped and the slice would pass without being checked.
+    assert_eq!(
+        contexts.len(),
+        chunks,
+        "`SplitContext::split` returned the wrong number of contexts",
+    );
+
+    // Raw pointers aren't `Send`, so the base pointer is shared as an address.
+    let address = base as usize;
+    // Chunks after the lowest failed chunk don't need to be checked, since
+    // their errors would never be reported.
+    let lowest_failed = AtomicUsize::new(usize::MAX);
+    let results = contexts
+        .par_iter_mut()
+        .enumerate()
+        .map(|(chunk, context)| {
+            if chunk > lowest_failed.load(Ordering::Relaxed) {
+                return None;
+            }
+            let start = chunk * chunk_len;
+            let end = usize::min(start + chunk_len, len);
+            let result = check(address as *const T, start..end, context);
+            if result.is_err() {
+                lowest_failed.fetch_min(chunk, Ordering::Relaxed);
+            }
+            Some(result)
+        })
+        .collect::<Vec<_>>();
+
+    for split in contexts {
+        context.merge(split);
+    }
+    // Every chunk before the lowest failed chunk was checked, so the first
+    // error is always the one with the lowest index.
+    results.into_iter().flatten().collect()
+}
+
+/// Checks the elements of the slice starting at `base` with indices in
+/// `range`.
+///
+/// # Safety
+///
+/// `base` must point to a slice of `T` which contains all of the elements in
+/// `range` and meets the requirements of `check_bytes`.
+unsafe fn check_range<T, C>(
+    base: *const T,
+    range: Range<usize>,
+    context: &mut C,
+) -> Result<(), C::Error>
+where
+    T: CheckBytes<C>,
+    C: Fallible + ?Sized,
+    C::Error: Trace,
+{
+    for index in range {
+        // SAFETY: The caller has guaranteed that the element at `index` is in
+        // the slice and meets the requirements of `check_bytes`.
+        unsafe {
+            T::check_bytes(base.add(index), context)
+                .with_trace(|| SliceCheckContext { index })?;
+        }
+    }
+    Ok(())
+}
+
+/// Checks whether the given bytes are a valid slice of `T`, checking large
+/// slices in parallel, and returns a reference to it.
+///
+/// See [`slice_from_bytes`](crate::slice_from_bytes) for the requirements on
+/// the buffer.
+#[inline]
+pub fn slice_from_bytes<T, E>(bytes: &[u8]) -> Result<&[T], E>
+where
+    T: CheckBytes<Strategy<(), E>>,
+    E: Source + Send,
+{
+    slice_from_bytes_with_context(bytes, &mut ())
+}
+
+/// Checks whether the given bytes are a valid slice of `T` within the given
+/// context, checking large slices in parallel, and returns a reference to it.
+///
+/// See [`slice_from_bytes`](crate::slice_from_bytes) for the requirements on
+/// the buffer.
+pub fn slice_from_bytes_with_context<'a, T, C, E>(
+    bytes: &'a [u8],
+    context: &mut C,
+) -> Result<&'a [T], E>
+where
+    T: CheckBytes<Strategy<C, E>>,
+    C: BudgetContext<E> + SplitContext + Send,
+    E: Source + Send,
+{
+    let size = core::mem::size_of::<T>();
+    let len = bytes.len().checked_div(size).unwrap_or(0);
+    check_buffer::<E>(bytes, core::mem::align_of::<T>(), len * size)?;
+    let ptr = ptr::slice_from_raw_parts(bytes.as_ptr().cast::<T>(), len);
+    // SAFETY: We checked that `bytes` is aligned for `T` and is exactly `len`
+    // elements long, and the bytes of a slice are always initialized.
+    unsafe {
+        check_slice(ptr, context, DEFAULT_THRESHOLD)
+            .trace(BufferContext::new(bytes))?;
+    }
+    // SAFETY: `check_slice` returned `Ok`, so `ptr` points to a valid `[T]`.
+    // The returned reference borrows from `bytes`, so the value can't be
+    // mutated or freed while it's alive.
+    Ok(unsafe { &*ptr })
+}
@@ -0,0 +1,321 @@
+//! Structured paths to the values that failed validation.
+//!
+//! The contexts added by derived and built-in [`CheckBytes`](crate::CheckBytes)
+//! implementations are opaque to most error types, which can only display
+//! them. The types in this module recognize those contexts and turn them into
+//! [`Segment`]s that can be inspected programmatically or rendered compactly,
+//! e.g. `Header.entries[3].Kind::Named.label`.
+
+use core::{any::Any, fmt};
+
+use crate::{
+    ArrayCheckContext, NamedEnumVariantCheckContext, SliceCheckContext,
+    StructCheckContext, TupleIndexContext, TupleStructCheckContext,
+    UnnamedEnumVariantCheckContext,
+};
+
+/// A single step in a path to a value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Segment {
+    /// A named field of a struct or enum variant.
+    Field {
+        /// The name of the struct or variant containing the field.
+        parent: &'static str,
+        /// The name of the field.
+        name: &'static str,
+    },
+    /// An unnamed field of a tuple, tuple struct, or tuple enum variant.
+    TupleIndex {
+        /// The name of the tuple struct or variant containing the field, or
+        /// `None` if the field belongs to a tuple.
+        parent: Option<&'static str>,
+        /// The index of the field.
+        index: usize,
+    },
+    /// An element of an array or slice.
+    Index(usize),
+    /// The active variant of an enum.
+    Variant {
+        /// The name of the enum.
+        enum_name: &'static str,
+        /// The name of the variant.
+        variant_name: &'static str,
+    },
+}
+
+impl Segment {
+    /// Returns the segments described by a trace context, innermost first.
+    ///
+    /// Returns `None` if the trace is not one of the contexts added by
+    /// bytecheck.
+    pub(crate) fn from_trace(
+        trace: &dyn Any,
+    ) -> Option<(Segment, Option<Segment>)> {
+        if let Some(c) = trace.downcast_ref::<StructCheckContext>() {
+            Some((
+                Segment::Field {
+                    parent: c.struct_name,
+                    name: c.field_name,
+                },
+                None,
+            ))
+        } else if let Some(c) = trace.downcast_ref::<TupleStructCheckContext>()
+        {
+            Some((
+                Segment::TupleIndex {
+                    parent: Some(c.tuple_struct_name),
+                    index: c.field_index,
+                },
+                None,
+            ))
+        } else if let Some(c) =
+            trace.downcast_ref::<NamedEnumVariantCheckContext>()
+        {
+            Some((
+                Segment::Field {
+                    parent: c.variant_name,
+                    name: c.field_name,
+                },
+                Some(Segment::Variant {
+                    enum_name: c.enum_name,
+                    variant_name: c.variant_name,
+                }),
+            ))
+        } else if let Some(c) =
+            trace.downcast_ref::<UnnamedEnumVariantCheckContext>()
+        {
+            // The derive counts the tag as the first field of the variant
+            // struct, so field indices start at one.
+            Some((
+                Segment::TupleIndex {
+                    parent: Some(c.variant_name),
+                    index: c.field_index.saturating_sub(1),
+                },
+                Some(Segment::Variant {
+                    enum_name: c.enum_name,
+                    variant_name: c.variant_name,
+                }),
+            ))
+        } else if let Some(c) = trace.downcast_ref::<TupleIndexContext>() {
+            Some((
+                Segment::TupleIndex {
+                    parent: None,
+                    index: c.index,
+                },
+                None,
+            ))
+        } else if let Some(c) = trace.downcast_ref::<ArrayCheckContext>() {
+            Some((Segment::Index(c.index), None))
+        } else {
+            trace
+                .downcast_ref::<SliceCheckContext>()
+                .map(|c| (Segment::Index(c.index), None))
+        }
+    }
+
+    /// Writes this segment as it appears in a rendered path.
+    ///
+    /// `first` indicates whether this is the outermost segment of the path.
+    pub(crate) fn render(
+        &self,
+        first: bool,
+        f: &mut impl fmt::Write,
+    ) -> fmt::Result {
+        match *self {
+            Segment::Field { parent, name } => {
+                if first {
+                    f.write_str(parent)?;
+                }
+                write!(f, ".{name}")
+            }
+            Segment::TupleIndex { parent, index } => {
+                if let (true, Some(parent)) = (first, parent) {
+                    f.write_str(parent)?;
+                }
+                write!(f, ".{index}")
+            }
+            Segment::Index(index) => write!(f, "[{index}]"),
+            Segment::Variant {
+                enum_name,
+                variant_name,
+            } => {
+                if !first {
+                    f.write_str(".")?;
+                }
+                write!(f, "{enum_name}::{variant_name}")
+            }
+        }
+    }
+
+    /// Writes this segment as a JSON object.
+    pub(crate) fn write_json(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        match *self {
+            Segment::Field { parent, name } => {
+                f.write_str("{\"field\":")?;
+                write_json_str(name, f)?;
+                f.write_str(",\"parent\":")?;
+                write_json_str(parent, f)?;
+            }
+            Segment::TupleIndex { parent, index } => {
+                write!(f, "{{\"tuple_index\":{index},\"parent\":")?;
+                match parent {
+                    Some(parent) => write_json_str(parent, f)?,
+                    None => f.write_str("null")?,
+                }
+            }
+            Segment::Index(index) => write!(f, "{{\"index\":{index}")?,
+            Segment::Variant {
+                enum_name,
+                variant_name,
+            } => {
+                f.write_str("{\"variant\":")?;
+                write_json_str(variant_name, f)?;
+                f.write_str(",\"enum\":")?;
+                write_json_str(enum_name, f)?;
+            }
+        }
+        f.write_str("}")
+    }
+}
+
+impl fmt::Display for Segment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.render(true, f)
+    }
+}
+
+/// Writes `s` as a quoted and escaped JSON string.
+pub(crate) fn write_json_str(s: &str, f: &mut impl fmt::Write) -> fmt::Result {
+    f.write_char('"')?;
+    for c in s.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => f.write_char(c)?,
+        }
+    }
+    f.write_char('"')
+}
+
+/// A path from the root of a validated value to the value that failed
+/// validation.
+///
+/// Paths are built from the innermost segment outward as an error propagates
+/// up through `check_bytes` calls.
+#[cfg(feature = "std")]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Path {
+    // Stored innermost first, since that's the order they're added in.
+    segments: Vec<Segment>,
+}
+
+#[cfg(feature = "std")]
+impl Path {
+    /// Returns a new empty path.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns whether the path has no segments.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.segments.is_empty()
+    }
+
+    /// Returns the number of segments in the path.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.segments.len()
+    }
+
+    /// Returns an iterator over the segments of the path, from the outermost
+    /// to the innermost.
+    #[inline]
+    pub fn segments(
+        &self,
+    ) -> impl DoubleEndedIterator<Item = &Segment> + ExactSizeIterator {
+        self.segments.iter().rev()
+    }
+
+    /// Adds a segment to the outside of the path.
+    #[inline]
+    pub fn push_outer(&mut self, segment: Segment) {
+        self.segments.push(segment);
+    }
+
+    /// Adds the segments described by a trace context to the outside of the
+    /// path.
+    ///
+    /// Returns `false` and leaves the path unchanged if the trace is not one of
+    /// the contexts added by bytecheck.
+    pub fn push_trace<R: Any>(&mut self, trace: &R) -> bool {
+        match Segment::from_trace(trace) {
+            Some((inner, outer)) => {
+                self.push_outer(inner);
+                if let Some(outer) = outer {
+                    self.push_outer(outer);
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns a value which displays the path as JSON.
+    ///
+    /// The output has the form `{"path":"...","segments":[...]}`, where `path`
+    /// is the rendered path and `segments` lists each segment from the
+    /// outermost to the innermost.
+    #[inline]
+    pub fn json(&self) -> PathJson<'_> {
+        PathJson { path: self }
+    }
+
+    pub(crate) fn write_json_fields(
+        &self,
+        f: &mut impl fmt::Write,
+    ) -> fmt::Result {
+        f.write_str("\"path\":")?;
+        write_json_str(&self.to_string(), f)?;
+        f.write_str(",\"segments\":[")?;
+        for (i, segment) in self.segments().enumerate() {
+            if i != 0 {
+                f.write_str(",")?;
+            }
+            segment.write_json(f)?;
+        }
+        f.write_str("]")
+    }
+}
+
+#[cfg(feature = "std")]
+impl fmt::Display for Path {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, segment) in self.segments().enumerate() {
+            segment.render(i == 0, f)?;
+        }
+        Ok(())
+    }
+}
+
+/// Displays a [`Path`] as JSON.
+///
+/// Returned by [`Path::json`].
+#[cfg(feature = "std")]
+pub struct PathJson<'a> {
+    path: &'a Path,
+}
+
+#[cfg(feature = "std")]
+impl fmt::Display for PathJson<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("{")?;
+        self.path.write_json_fields(f)?;
+        f.write_str("}")
+    }
+}
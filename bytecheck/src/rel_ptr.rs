@@ -0,0 +1,532 @@
+//! Self-relative pointers to data in the same buffer.
+//!
+//! A relative pointer stores a signed offset from its own address to the
+//! value it points to, so a buffer containing relative pointers can be used
+//! from any address without being modified. [`RelPtr`] points to a single
+//! value, [`RelSlice`] points to a slice, and [`RelStr`] points to a string.
+//! [`RelShared`] points to a single value which may be shared with other
+//! pointers.
+//!
+//! Relative pointers check their targets with a [`BoundsContext`]. The target
+//! of each pointer is claimed, so relative pointers must point to data after
+//! all of the previously checked data in the buffer:
+//!
+//! ```
+//! use core::{alloc::Layout, mem::offset_of};
+//!
+//! use bytecheck::{
+//!     bounds::{check_subtree, Bounds},
+//!     rancor::{Failure, Strategy},
+//!     rel_ptr::RelSlice,
+//!     CheckBytes,
+//! };
+//!
+//! #[derive(CheckBytes)]
+//! #[repr(C)]
+//! struct Root {
+//!     flags: RelSlice<bool>,
+//! }
+//!
+//! #[repr(C)]
+//! struct Buffer {
+//!     root: Root,
+//!     flags: [u8; 3],
+//! }
+//!
+//! let buffer = Buffer {
+//!     root: Root {
+//!         flags: RelSlice::from_offset(offset_of!(Buffer, flags) as i32, 3),
+//!     },
+//!     flags: [1, 0, 1],
+//! };
+//!
+//! let bytes = unsafe {
+//!     core::slice::from_raw_parts(
+//!         (&buffer as *const Buffer).cast::<u8>(),
+//!         core::mem::size_of::<Buffer>(),
+//!     )
+//! };
+//! let mut bounds = Bounds::new(bytes);
+//! let root = bytes.as_ptr().cast::<Root>();
+//! unsafe {
+//!     check_subtree(
+//!         root,
+//!         Layout::new::<Root>(),
+//!         Strategy::<_, Failure>::wrap(&mut bounds),
+//!     )
+//!     .unwrap();
+//!     assert_eq!((*root).flags.get(), &[true, false, true]);
+//! }
+//! ```
+
+use core::{alloc::Layout, fmt, marker::PhantomData, mem, ptr};
+
+use rancor::{Fallible, ResultExt as _, Trace};
+
+use crate::{
+    bounds::{check_subtree, BoundsContext},
+    fingerprint::{Fingerprint, FingerprintHasher},
+    schema::{Schema, SchemaKind, TypeSchema},
+    shared::{check_shared, SharedContext},
+    CheckBytes,
+};
+
+/// Returns the address `offset` bytes from `base`.
+///
+/// The offset is applied with wrapping arithmetic so that invalid offsets can
+/// be checked against the bounds of a buffer without causing undefined
+/// behavior.
+#[inline]
+fn target<T>(base: *const T, offset: i32) -> *const u8 {
+    base.cast::<u8>().wrapping_offset(offset as isize)
+}
+
+/// Returns the layout of a slice of `len` `T`s.
+///
+/// If the slice would be too large for any layout, this returns the largest
+/// layout with the alignment of `T` so that the slice fails its bounds check.
+#[inline]
+fn slice_layout<T>(len: usize) -> Layout {
+    Layout::array::<T>(len).unwrap_or_else(|_| {
+        let align = mem::align_of::<T>();
+        let max_size = isize::MAX as usize - (align - 1);
+        // SAFETY: `align` is the alignment of a type, so it is a non-zero
+        // power of two. `max_size` rounded up to `align` is `isize::MAX`
+        // rounded down to `align`, which does not exceed `isize::MAX`.
+        unsafe { Layout::from_size_align_unchecked(max_size, align) }
+    })
+}
+
+/// A relative pointer to a single value.
+#[repr(C)]
+pub struct RelPtr<T> {
+    offset: i32,
+    _phantom: PhantomData<*const T>,
+}
+
+impl<T> RelPtr<T> {
+    /// Returns a new `RelPtr` which points `offset` bytes from its own
+    /// address.
+    #[inline]
+    pub fn from_offset(offset: i32) -> Self {
+        Self {
+            offset,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Returns the offset in bytes from the address of this `RelPtr` to the
+    /// value it points to.
+    #[inline]
+    pub fn offset(&self) -> i32 {
+        self.offset
+    }
+
+    /// Returns a pointer to the value this `RelPtr` points to.
+    #[inline]
+    pub fn as_ptr(&self) -> *const T {
+        target(self, self.offset).cast()
+    }
+
+    /// Returns a reference to the value this `RelPtr` points to.
+    ///
+    /// # Safety
+    ///
+    /// The `RelPtr` must point to a valid `T`, for example because it was
+    /// checked with [`CheckBytes`].
+    #[inline]
+    pub unsafe fn get(&self) -> &T {
+        // SAFETY: The caller has guaranteed that the pointer points to a
+        // valid `T`.
+        unsafe { &*self.as_ptr() }
+    }
+}
+
+impl<T> Clone for RelPtr<T> {
+    #[inline]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for RelPtr<T> {}
+
+impl<T> fmt::Debug for RelPtr<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RelPtr")
+            .field("offset", &self.offset)
+            .finish()
+    }
+}
+
+/// A relative pointer to a single value which may be shared with other
+/// pointers.
+///
+/// Shared values are only checked once, using a [`SharedContext`] to record
+/// which values have been checked.
+#[repr(transparent)]
+pub struct RelShared<T> {
+    ptr: RelPtr<T>,
+}
+
+impl<T> RelShared<T> {
+    /// Returns a new `RelShared` which points `offset` bytes from its own
+    /// address.
+    #[inline]
+    pub fn from_offset(offset: i32) -> Self {
+        Self {
+            ptr: RelPtr::from_offset(offset),
+        }
+    }
+
+    /// Returns the offset in bytes from the address of this `RelShared` to
+    /// the value it points to.
+    #[inline]
+    pub fn offset(&self) -> i32 {
+        self.ptr.offset()
+    }
+
+    /// Returns a pointer to the value this `RelShared` points to.
+    #[inline]
+    pub fn as_ptr(&self) -> *const T {
+        self.ptr.as_ptr()
+    }
+
+    /// Returns a reference to the value this `RelShared` points to.
+    ///
+    /// # Safety
+    ///
+    /// The `RelShared` must point to a valid `T`, for example because it was
+    /// checked with [`CheckBytes`].
+    #[inline]
+    pub unsafe fn get(&self) -> &T {
+        // SAFETY: The caller has guaranteed that the pointer points to a
+        // valid `T`.
+        unsafe { self.ptr.get() }
+    }
+}
+
+impl<T> Clone for RelShared<T> {
+    #[inline]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for RelShared<T> {}
+
+impl<T> fmt::Debug for RelShared<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RelShared")
+            .field("offset", &self.offset())
+            .finish()
+    }
+}
+
+/// A relative pointer to a slice.
+#[repr(C)]
+pub struct RelSlice<T> {
+    offset: i32,
+    len: u32,
+    _phantom: PhantomData<*const [T]>,
+}
+
+impl<T> RelSlice<T> {
+    /// Returns a new `RelSlice` which points to `len` elements starting
+    /// `offset` bytes from its own address.
+    #[inline]
+    pub fn from_offset(offset: i32, len: u32) -> Self {
+        Self {
+            offset,
+            len,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Returns the offset in bytes from the address of this `RelSlice` to the
+    /// first element of the slice.
+    #[inline]
+    pub fn offset(&self) -> i32 {
+        self.offset
+    }
+
+    /// Returns the number of elements in the slice.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len as usize
+    }
+
+    /// Returns whether the slice is empty.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns a pointer to the slice this `RelSlice` points to.
+    #[inline]
+    pub fn as_ptr(&self) -> *const [T] {
+        ptr::slice_from_raw_parts(target(self, self.offset).cast(), self.len())
+    }
+
+    /// Returns a reference to the slice this `RelSlice` points to.
+    ///
+    /// # Safety
+    ///
+    /// The `RelSlice` must point to a valid `[T]`, for example because it was
+    /// checked with [`CheckBytes`].
+    #[inline]
+    pub unsafe fn get(&self) -> &[T] {
+        // SAFETY: The caller has guaranteed that the pointer points to a
+        // valid `[T]`.
+        unsafe { &*self.as_ptr() }
+    }
+}
+
+impl<T> Clone for RelSlice<T> {
+    #[inline]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for RelSlice<T> {}
+
+impl<T> fmt::Debug for RelSlice<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RelSlice")
+            .field("offset", &self.offset)
+            .field("len", &self.len)
+            .finish()
+    }
+}
+
+/// A relative pointer to a string.
+#[derive(Clone, Copy, Debug)]
+#[repr(transparent)]
+pub struct RelStr {
+    bytes: RelSlice<u8>,
+}
+
+impl RelStr {
+    /// Returns a new `RelStr` which points to `len` bytes starting `offset`
+    /// bytes from its own address.
+    #[inline]
+    pub fn from_offset(offset: i32, len: u32) -> Self {
+        Self {
+            bytes: RelSlice::from_offset(offset, len),
+        }
+    }
+
+    /// Returns the offset in bytes from the address of this `RelStr` to the
+    /// start of the string.
+    #[inline]
+    pub fn offset(&self) -> i32 {
+        self.bytes.offset()
+    }
+
+    /// Returns the length of the string in bytes.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    /// Returns whether the string is empty.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    /// Returns a pointer to the string this `RelStr` points to.
+    #[inline]
+    pub fn as_ptr(&self) -> *const str {
+        self.bytes.as_ptr() as *const str
+    }
+
+    /// Returns a reference to the string this `RelStr` points to.
+    ///
+    /// # Safety
+    ///
+    /// The `RelStr` must point to a valid `str`, for example because it was
+    /// checked with [`CheckBytes`].
+    #[inline]
+    pub unsafe fn get(&self) -> &str {
+        // SAFETY: The caller has guaranteed that the pointer points to a
+        // valid `str`.
+        unsafe { &*self.as_ptr() }
+    }
+}
+
+#[derive(Debug)]
+struct RelPtrCheckContext {
+    offset: i32,
+}
+
+impl fmt::Display for RelPtrCheckContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "while checking the target of relative pointer with offset {}",
+            self.offset,
+        )
+    }
+}
+
+// SAFETY: `check_bytes` only returns `Ok` if the target of the pointer was
+// successfully claimed, which means it is in bounds and aligned, and the
+// target is a valid `T`. The offset is an `i32`, for which all bit patterns are
+// valid.
+unsafe impl<T, C> CheckBytes<C> for RelPtr<T>
+where
+    T: CheckBytes<C>,
+    C: Fallible + BoundsContext + ?Sized,
+    C::Error: Trace,
+{
+    unsafe fn check_bytes(
+        value: *const Self,
+        context: &mut C,
+    ) -> Result<(), C::Error> {
+        // SAFETY: The caller has guaranteed that `value` points to a valid
+        // `RelPtr`, so we can read its offset.
+        let offset = unsafe { (*value).offset };
+        let target = target(value, offset).cast::<T>();
+        // SAFETY: Once claimed, the target is within the buffer of the
+        // context and so points to enough bytes for a `T`.
+        unsafe {
+            check_subtree(target, Layout::new::<T>(), context)
+                .with_trace(|| RelPtrCheckContext { offset })
+        }
+    }
+}
+
+// SAFETY: `check_bytes` only returns `Ok` if the target of the pointer was
+// successfully claimed, which means it is in bounds and aligned, and the
+// target is a valid `[T]`. The offset and length are integers, for which all
+// bit patterns are valid.
+unsafe impl<T, C> CheckBytes<C> for RelSlice<T>
+where
+    [T]: CheckBytes<C>,
+    C: Fallible + BoundsContext + ?Sized,
+    C::Error: Trace,
+{
+    unsafe fn check_bytes(
+        value: *const Self,
+        context: &mut C,
+    ) -> Result<(), C::Error> {
+        // SAFETY: The caller has guaranteed that `value` points to a valid
+        // `RelSlice`, so we can read its offset and length.
+        let (offset, len) = unsafe { ((*value).offset, (*value).len()) };
+        let target =
+            ptr::slice_from_raw_parts(target(value, offset).cast::<T>(), len);
+        // SAFETY: Once claimed, the target is within the buffer of the
+        // context and so points to enough bytes for `len` elements.
+        unsafe {
+            check_subtree(target, slice_layout::<T>(len), context)
+                .with_trace(|| RelPtrCheckContext { offset })
+        }
+    }
+}
+
+// SAFETY: `check_bytes` only returns `Ok` if the target of the pointer was
+// successfully claimed, which means it is in bounds, and the target is a valid
+// `str`. The offset and length are integers, for which all bit patterns are
+// valid.
+unsafe impl<C> CheckBytes<C> for RelStr
+where
+    str: CheckBytes<C>,
+    C: Fallible + BoundsContext + ?Sized,
+    C::Error: Trace,
+{
+    unsafe fn check_bytes(
+        value: *const Self,
+        context: &mut C,
+    ) -> Result<(), C::Error> {
+        // SAFETY: `RelStr` is `repr(transparent)` over a `RelSlice<u8>`, and
+        // the caller has guaranteed that `value` points to a valid `RelStr`.
+        let bytes = unsafe { &(*value).bytes };
+        let target = bytes.as_ptr() as *const str;
+        // SAFETY: Once claimed, the target is within the buffer of the
+        // context and so points to enough bytes for the string.
+        unsafe {
+            check_subtree(target, slice_layout::<u8>(bytes.len()), context)
+                .with_trace(|| RelPtrCheckContext {
+                    offset: bytes.offset(),
+                })
+        }
+    }
+}
+
+// SAFETY: `check_bytes` only returns `Ok` if the target of the pointer was
+// claimed and checked by this or an earlier call, which means it is in bounds
+// and aligned and is a valid `T`. The offset is an `i32`, for which all bit
+// patterns are valid.
+unsafe impl<T, C> CheckBytes<C> for RelShared<T>
+where
+    T: CheckBytes<C> + 'static,
+    C: Fallible + BoundsContext + SharedContext + ?Sized,
+    C::Error: Trace,
+{
+    unsafe fn check_bytes(
+        value: *const Self,
+        context: &mut C,
+    ) -> Result<(), C::Error> {
+        // SAFETY: `RelShared` is `repr(transparent)` over a `RelPtr<T>`, and
+        // the caller has guaranteed that `value` points to a valid
+        // `RelShared`.
+        let ptr = unsafe { &(*value).ptr };
+        let offset = ptr.offset();
+        // SAFETY: Once claimed, the target is within the buffer of the
+        // context and so points to enough bytes for a `T`.
+        unsafe {
+            check_shared(ptr.as_ptr(), Layout::new::<T>(), context)
+                .with_trace(|| RelPtrCheckContext { offset })
+        }
+    }
+}
+
+impl<T: Fingerprint> Fingerprint for RelPtr<T> {
+    const FINGERPRINT: u64 = FingerprintHasher::new()
+        .write_str("RelPtr")
+        .write_u64(T::FINGERPRINT)
+        .finish();
+}
+
+impl<T: Fingerprint> Fingerprint for RelSlice<T> {
+    const FINGERPRINT: u64 = FingerprintHasher::new()
+        .write_str("RelSlice")
+        .write_u64(T::FINGERPRINT)
+        .finish();
+}
+
+impl Fingerprint for RelStr {
+    const FINGERPRINT: u64 =
+        FingerprintHasher::new().write_str("RelStr").finish();
+}
+
+impl<T: Fingerprint> Fingerprint for RelShared<T> {
+    const FINGERPRINT: u64 = FingerprintHasher::new()
+        .write_str("RelShared")
+        .write_u64(T::FINGERPRINT)
+        .finish();
+}
+
+// Relative pointers check their targets with capabilities that schemas can't
+// describe, so they're opaque.
+impl<T> Schema for RelPtr<T> {
+    const SCHEMA: &'static TypeSchema<'static> =
+        &TypeSchema::sized::<RelPtr<T>>("RelPtr", SchemaKind::Opaque);
+}
+
+impl<T> Schema for RelSlice<T> {
+    const SCHEMA: &'static TypeSchema<'static> =
+        &TypeSchema::sized::<RelSlice<T>>("RelSlice", SchemaKind::Opaque);
+}
+
+impl Schema for RelStr {
+    const SCHEMA: &'static TypeSchema<'static> =
+        &TypeSchema::sized::<RelStr>("RelStr", SchemaKind::Opaque);
+}
+
+impl<T> Schema for RelShared<T> {
+    const SCHEMA: &'static TypeSchema<'static> =
+        &TypeSchema::sized::<RelShared<T>>("RelShared", SchemaKind::Opaque);
+}
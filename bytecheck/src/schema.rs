@@ -0,0 +1,332 @@
+//! Layout schemas of types.
+//!
+//! A [`TypeSchema`] describes how a type is laid out and what makes it valid:
+//! its size and alignment, the offsets and schemas of its fields, and the
+//! discriminants and fields of its variants. `#[derive(CheckBytes)]`
+//! implements [`Schema`] for every type it derives for, so tools can inspect
+//! the layout of types as the compiler laid them out.
+//!
+//! Like fingerprints, the type of every field of a derived type must implement
+//! `Schema`, and deriving fails with an error naming the field type if one
+//! doesn't. Types which are checked in ways that a schema can't describe, like
+//! pointers, should use [`SchemaKind::Opaque`].
+//!
+//! ```
+//! use bytecheck::{
+//!     schema::{schema_of, SchemaKind},
+//!     CheckBytes,
+//! };
+//!
+//! #[derive(CheckBytes)]
+//! #[repr(C)]
+//! struct Point {
+//!     x: u16,
+//!     y: u32,
+//! }
+//!
+//! let schema = schema_of::<Point>();
+//! assert_eq!(schema.size, Some(8));
+//! let SchemaKind::Struct { fields, .. } = schema.kind else {
+//!     panic!("expected a struct");
+//! };
+//! assert_eq!(fields[1].name, Some("y"));
+//! assert_eq!(fields[1].offset, 4);
+//! assert_eq!(fields[1].schema.name, "u32");
+//! ```
+
+#[cfg(target_has_atomic = "8")]
+use core::sync::atomic::{AtomicBool, AtomicI8, AtomicU8};
+#[cfg(target_has_atomic = "16")]
+use core::sync::atomic::{AtomicI16, AtomicU16};
+#[cfg(target_has_atomic = "32")]
+use core::sync::atomic::{AtomicI32, AtomicU32};
+#[cfg(target_has_atomic = "64")]
+use core::sync::atomic::{AtomicI64, AtomicU64};
+use core::{
+    marker::{PhantomData, PhantomPinned},
+    mem::{self, ManuallyDrop},
+    num::{
+        NonZeroI128, NonZeroI16, NonZeroI32, NonZeroI64, NonZeroI8,
+        NonZeroU128, NonZeroU16, NonZeroU32, NonZeroU64, NonZeroU8,
+    },
+    ops,
+};
+
+/// A type with a static description of its layout.
+///
+/// This is implemented by `#[derive(CheckBytes)]`.
+pub trait Schema {
+    /// The schema of the type.
+    const SCHEMA: &'static TypeSchema<'static>;
+}
+
+/// Returns the schema of `T`.
+#[inline]
+pub const fn schema_of<T: Schema + ?Sized>() -> &'static TypeSchema<'static> {
+    T::SCHEMA
+}
+
+/// A description of the layout of a type.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TypeSchema<'a> {
+    /// The name of the type.
+    pub name: &'a str,
+    /// The size of the type in bytes, or `None` if it is unsized.
+    pub size: Option<usize>,
+    /// The alignment of the type in bytes.
+    pub align: usize,
+    /// The kind of the type, which determines which values are valid.
+    pub kind: SchemaKind<'a>,
+}
+
+impl<'a> TypeSchema<'a> {
+    /// Returns the schema of a sized type of the given kind.
+    #[inline]
+    pub const fn sized<T>(name: &'a str, kind: SchemaKind<'a>) -> Self {
+        Self {
+            name,
+            size: Some(mem::size_of::<T>()),
+            align: mem::align_of::<T>(),
+            kind,
+        }
+    }
+}
+
+/// The kind of a type described by a [`TypeSchema`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SchemaKind<'a> {
+    /// A type for which all bit patterns are valid, like an integer.
+    Primitive,
+    /// A `bool`, which must be `0` or `1`.
+    Bool,
+    /// A `char`, which must be a Unicode scalar value.
+    Char,
+    /// A non-zero integer, which must not be zero.
+    NonZero,
+    /// An array of `len` elements.
+    Array {
+        /// The schema of the elements.
+        element: &'a TypeSchema<'a>,
+        /// The number of elements.
+        len: usize,
+    },
+    /// A slice of elements.
+    Slice {
+        /// The schema of the elements.
+        element: &'a TypeSchema<'a>,
+    },
+    /// A `str`, which must be valid UTF-8.
+    Str,
+    /// A struct.
+    Struct {
+        /// The repr of the struct, like `"C"` or `"transparent"`.
+        repr: &'a str,
+        /// The fields of the struct, in order.
+        fields: &'a [FieldSchema<'a>],
+    },
+    /// An enum with a primitive repr.
+    Enum {
+        /// The repr of the enum, like `"u8"`.
+        repr: &'a str,
+        /// The schema of the discriminant.
+        tag: &'a TypeSchema<'a>,
+        /// The variants of the enum, in order.
+        variants: &'a [VariantSchema<'a>],
+    },
+    /// A type which is checked in a way that can't be described by a schema.
+    Opaque,
+}
+
+/// A description of a field of a struct or enum variant.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FieldSchema<'a> {
+    /// The name of the field, or `None` if it is a tuple field.
+    pub name: Option<&'a str>,
+    /// The offset of the field from the start of its struct or enum in bytes.
+    pub offset: usize,
+    /// The schema of the type of the field.
+    pub schema: &'a TypeSchema<'a>,
+}
+
+/// A description of a variant of an enum.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct VariantSchema<'a> {
+    /// The name of the variant.
+    pub name: &'a str,
+    /// The discriminant of the variant, converted to a `u128` with `as`.
+    pub discriminant: u128,
+    /// The fields of the variant, in order.
+    pub fields: &'a [FieldSchema<'a>],
+}
+
+macro_rules! impl_schema {
+    ($kind:ident: $($type:ident),* $(,)?) => {
+        $(
+            impl Schema for $type {
+                const SCHEMA: &'static TypeSchema<'static> =
+                    &TypeSchema::sized::<$type>(
+                        stringify!($type),
+                        SchemaKind::$kind,
+                    );
+            }
+        )*
+    };
+}
+
+impl_schema! {
+    Primitive:
+    i8, i16, i32, i64, i128,
+    u8, u16, u32, u64, u128,
+    f32, f64,
+    PhantomPinned,
+}
+impl_schema!(Bool: bool);
+impl_schema!(Char: char);
+impl_schema! {
+    NonZero:
+    NonZeroI8, NonZeroI16, NonZeroI32, NonZeroI64, NonZeroI128,
+    NonZeroU8, NonZeroU16, NonZeroU32, NonZeroU64, NonZeroU128,
+}
+#[cfg(target_has_atomic = "8")]
+impl_schema!(Primitive: AtomicI8, AtomicU8);
+#[cfg(target_has_atomic = "8")]
+impl_schema!(Bool: AtomicBool);
+#[cfg(target_has_atomic = "16")]
+impl_schema!(Primitive: AtomicI16, AtomicU16);
+#[cfg(target_has_atomic = "32")]
+impl_schema!(Primitive: AtomicI32, AtomicU32);
+#[cfg(target_has_atomic = "64")]
+impl_schema!(Primitive: AtomicI64, AtomicU64);
+
+impl Schema for () {
+    const SCHEMA: &'static TypeSchema<'static> =
+        &TypeSchema::sized::<()>("()", SchemaKind::Primitive);
+}
+
+impl<T: ?Sized> Schema for PhantomData<T> {
+    const SCHEMA: &'static TypeSchema<'static> =
+        &TypeSchema::sized::<PhantomData<T>>(
+            "PhantomData",
+            SchemaKind::Primitive,
+        );
+}
+
+impl<T: Schema, const N: usize> Schema for [T; N] {
+    const SCHEMA: &'static TypeSchema<'static> = &TypeSchema::sized::<[T; N]>(
+        "array",
+        SchemaKind::Array {
+            element: T::SCHEMA,
+            len: N,
+        },
+    );
+}
+
+impl<T: Schema> Schema for [T] {
+    const SCHEMA: &'static TypeSchema<'static> = &TypeSchema {
+        name: "slice",
+        size: None,
+        align: mem::align_of::<T>(),
+        kind: SchemaKind::Slice { element: T::SCHEMA },
+    };
+}
+
+impl Schema for str {
+    const SCHEMA: &'static TypeSchema<'static> = &TypeSchema {
+        name: "str",
+        size: None,
+        align: 1,
+        kind: SchemaKind::Str,
+    };
+}
+
+impl<T: Schema> Schema for ManuallyDrop<T> {
+    const SCHEMA: &'static TypeSchema<'static> =
+        &TypeSchema::sized::<ManuallyDrop<T>>(
+            "ManuallyDrop",
+            SchemaKind::Struct {
+                repr: "transparent",
+                fields: &[FieldSchema {
+                    name: None,
+                    offset: 0,
+                    schema: T::SCHEMA,
+                }],
+            },
+        );
+}
+
+impl Schema for ops::RangeFull {
+    const SCHEMA: &'static TypeSchema<'static> =
+        &TypeSchema::sized::<ops::RangeFull>(
+            "RangeFull",
+            SchemaKind::Primitive,
+        );
+}
+
+macro_rules! impl_range {
+    ($type:ident { $($field:ident),* }) => {
+        impl<T: Schema> Schema for ops::$type<T> {
+            const SCHEMA: &'static TypeSchema<'static> =
+                &TypeSchema::sized::<ops::$type<T>>(
+                    stringify!($type),
+                    SchemaKind::Struct {
+                        repr: "",
+                        fields: &[$(
+                            FieldSchema {
+                                name: Some(stringify!($field)),
+                                offset: mem::offset_of!(ops::$type<T>, $field),
+                                schema: T::SCHEMA,
+                            },
+                        )*],
+                    },
+                );
+        }
+    };
+}
+
+impl_range!(Range { start, end });
+impl_range!(RangeFrom { start });
+impl_range!(RangeTo { end });
+impl_range!(RangeToInclusive { end });
+
+macro_rules! impl_tuple {
+    ($($type:ident $index:tt),*) => {
+        impl<$($type: Schema),*> Schema for ($($type,)*) {
+            const SCHEMA: &'static TypeSchema<'static> =
+                &TypeSchema::sized::<($($type,)*)>(
+                    "tuple",
+                    SchemaKind::Struct {
+                        repr: "",
+                        fields: &[$(
+                            FieldSchema {
+                                name: None,
+                                offset: mem::offset_of!(Self, $index),
+                                schema: $type::SCHEMA,
+                            },
+                        )*],
+                    },
+                );
+        }
+    };
+}
+
+impl_tuple!(T0 0);
+impl_tuple!(T0 0, T1 1);
+impl_tuple!(T0 0, T1 1, T2 2);
+impl_tuple!(T0 0, T1 1, T2 2, T3 3);
+impl_tuple!(T0 0, T1 1, T2 2, T3 3, T4 4);
+impl_tuple!(T0 0, T1 1, T2 2, T3 3, T4 4, T5 5);
+impl_tuple!(T0 0, T1 1, T2 2, T3 3, T4 4, T5 5, T6 6);
+impl_tuple!(T0 0, T1 1, T2 2, T3 3, T4 4, T5 5, T6 6, T7 7);
+impl_tuple!(T0 0, T1 1, T2 2, T3 3, T4 4, T5 5, T6 6, T7 7, T8 8);
+impl_tuple!(T0 0, T1 1, T2 2, T3 3, T4 4, T5 5, T6 6, T7 7, T8 8, T9 9);
+impl_tuple!(
+    T0 0, T1 1, T2 2, T3 3, T4 4, T5 5, T6 6, T7 7, T8 8, T9 9, T10 10
+);
+impl_tuple!(
+    T0 0, T1 1, T2 2, T3 3, T4 4, T5 5, T6 6, T7 7, T8 8, T9 9, T10 10,
+    T11 11
+);
+impl_tuple!(
+    T0 0, T1 1, T2 2, T3 3, T4 4, T5 5, T6 6, T7 7, T8 8, T9 9, T10 10,
+    T11 11, T12 12
+);
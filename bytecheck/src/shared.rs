@@ -0,0 +1,592 @@
+//! Checking values which are shared by multiple pointers.
+//!
+//! When several pointers in a buffer point to the same value, checking each
+//! pointer would check the value again. In the worst case, this makes the
+//! work done by validation exponential in the size of the buffer. A
+//! [`SharedContext`] records which values have been checked so that each
+//! shared value is only checked once.
+//!
+//! Shared values are identified by their address and type. A value which
+//! contains a pointer to itself, or which is checked as two different types,
+//! is invalid.
+//!
+//! Values can be recorded with one of:
+//!
+//! - [`SharedMap`], which uses a hash map and requires `std`.
+//! - [`SharedArray`], which stores up to a fixed number of values inline.
+//! - [`SharedSlice`], which stores up to a fixed number of values in a
+//!   borrowed slice.
+//!
+//! [`WithShared`] combines one of these with another context.
+
+use core::{alloc::Layout, any::TypeId, fmt};
+
+use rancor::{fail, Fallible, Source, Strategy};
+
+use crate::{
+    bounds::{check_subtree, BoundsContext},
+    budget::{BudgetContext, Budgeted},
+    depth::DepthContext,
+    CheckBytes,
+};
+
+/// The state of a shared value after starting to check it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SharedState {
+    /// The value has not been checked before, and should be checked now. Once
+    /// it has been checked, [`SharedContext::finish_shared`] must be called.
+    Started,
+    /// The value has already been checked.
+    Finished,
+}
+
+/// A context which records the shared values that have been checked.
+pub trait SharedContext<E = <Self as Fallible>::Error> {
+    /// Starts checking the shared value of type `type_id` at `address`.
+    ///
+    /// Returns an error if the value is currently being checked, since it must
+    /// then contain a pointer to itself, or if it was checked as a different
+    /// type.
+    fn start_shared(
+        &mut self,
+        address: usize,
+        type_id: TypeId,
+    ) -> Result<SharedState, E>;
+
+    /// Finishes checking the shared value of type `type_id` at `address`.
+    fn finish_shared(
+        &mut self,
+        address: usize,
+        type_id: TypeId,
+    ) -> Result<(), E>;
+}
+
+impl<T, E> SharedContext<E> for Strategy<T, E>
+where
+    T: SharedContext<E> + ?Sized,
+{
+    #[inline]
+    fn start_shared(
+        &mut self,
+        address: usize,
+        type_id: TypeId,
+    ) -> Result<SharedState, E> {
+        T::start_shared(self, address, type_id)
+    }
+
+    #[inline]
+    fn finish_shared(
+        &mut self,
+        address: usize,
+        type_id: TypeId,
+    ) -> Result<(), E> {
+        T::finish_shared(self, address, type_id)
+    }
+}
+
+impl<C: SharedContext<E>, E> SharedContext<E> for Budgeted<C> {
+    #[inline]
+    fn start_shared(
+        &mut self,
+        address: usize,
+        type_id: TypeId,
+    ) -> Result<SharedState, E> {
+        self.inner_mut().start_shared(address, type_id)
+    }
+
+    #[inline]
+    fn finish_shared(
+        &mut self,
+        address: usize,
+        type_id: TypeId,
+    ) -> Result<(), E> {
+        self.inner_mut().finish_shared(address, type_id)
+    }
+}
+
+/// Checks a value which may be shared by multiple pointers.
+///
+/// The first time the value is checked, its bytes are claimed as with
+/// [`check_subtree`] and then it is checked. Later calls for the same value and
+/// type return `Ok` immediately.
+///
+/// # Safety
+///
+/// `value` must satisfy the same requirements as for [`check_subtree`].
+pub unsafe fn check_shared<T, C>(
+    value: *const T,
+    layout: Layout,
+    context: &mut C,
+) -> Result<(), C::Error>
+where
+    T: CheckBytes<C> + ?Sized + 'static,
+    C: Fallible + BoundsContext + SharedContext + ?Sized,
+{
+    let address = value as *const u8 as usize;
+    let type_id = TypeId::of::<T>();
+    match context.start_shared(address, type_id)? {
+        SharedState::Started => {
+            // SAFETY: The caller has guaranteed that `value` meets the
+            // requirements of `check_subtree`.
+            unsafe {
+                check_subtree(value, layout, context)?;
+            }
+            context.finish_shared(address, type_id)
+        }
+        SharedState::Finished => Ok(()),
+    }
+}
+
+/// A shared value which has been recorded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+struct Entry {
+    address: usize,
+    type_id: TypeId,
+    finished: bool,
+}
+
+/// Starts checking a shared value recorded in `entries`, the first `len` of
+/// which are in use.
+fn start_in<E: Source>(
+    entries: &mut [Option<Entry>],
+    len: &mut usize,
+    address: usize,
+    type_id: TypeId,
+) -> Result<SharedState, E> {
+    let used = &entries[..*len];
+    if let Some(entry) = used.iter().flatten().find(|e| e.address == address) {
+        return check_entry(entry, type_id);
+    }
+    match entries.get_mut(*len) {
+        Some(slot) => {
+            *slot = Some(Entry {
+                address,
+                type_id,
+                finished: false,
+            });
+            *len += 1;
+            Ok(SharedState::Started)
+        }
+        None => fail!(SharedCapacityError {
+            capacity: entries.len(),
+        }),
+    }
+}
+
+/// Finishes checking a shared value recorded in `entries`, the first `len` of
+/// which are in use.
+fn finish_in<E: Source>(
+    entries: &mut [Option<Entry>],
+    len: usize,
+    address: usize,
+    type_id: TypeId,
+) -> Result<(), E> {
+    let entry = entries[..len]
+        .iter_mut()
+        .flatten()
+        .find(|e| e.address == address && e.type_id == type_id);
+    match entry {
+        Some(entry) => {
+            entry.finished = true;
+            Ok(())
+        }
+        None => fail!(NotStartedError { address }),
+    }
+}
+
+fn check_entry<E: Source>(
+    entry: &Entry,
+    type_id: TypeId,
+) -> Result<SharedState, E> {
+    if entry.type_id != type_id {
+        fail!(SharedTypeMismatchError {
+            address: entry.address,
+        });
+    }
+    if !entry.finished {
+        fail!(CyclicSharedError {
+            address: entry.address,
+        });
+    }
+    Ok(SharedState::Finished)
+}
+
+/// A [`SharedContext`] which records up to `N` shared values inline.
+#[derive(Clone, Debug)]
+pub struct SharedArray<const N: usize> {
+    entries: [Option<Entry>; N],
+    len: usize,
+}
+
+impl<const N: usize> SharedArray<N> {
+    /// Returns a new empty `SharedArray`.
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            entries: [None; N],
+            len: 0,
+        }
+    }
+
+    /// Returns the number of shared values which have been recorded.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns whether no shared values have been recorded.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl<const N: usize> Default for SharedArray<N> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize, E: Source> SharedContext<E> for SharedArray<N> {
+    fn start_shared(
+        &mut self,
+        address: usize,
+        type_id: TypeId,
+    ) -> Result<SharedState, E> {
+        start_in(&mut self.entries, &mut self.len, address, type_id)
+    }
+
+    fn finish_shared(
+        &mut self,
+        address: usize,
+        type_id: TypeId,
+    ) -> Result<(), E> {
+        finish_in(&mut self.entries, self.len, address, type_id)
+    }
+}
+
+/// Storage for one shared value recorded by a [`SharedSlice`].
+#[derive(Clone, Copy, Debug, Default)]
+#[repr(transparent)]
+pub struct SharedSlot(Option<Entry>);
+
+impl SharedSlot {
+    /// An empty slot.
+    pub const EMPTY: Self = Self(None);
+}
+
+/// A [`SharedContext`] which records shared values in a borrowed slice.
+///
+/// The number of shared values which can be recorded is limited by the length
+/// of the slice.
+#[derive(Debug)]
+pub struct SharedSlice<'a> {
+    slots: &'a mut [SharedSlot],
+    len: usize,
+}
+
+impl<'a> SharedSlice<'a> {
+    /// Returns a new `SharedSlice` which records shared values in `slots`.
+    #[inline]
+    pub fn new(slots: &'a mut [SharedSlot]) -> Self {
+        Self { slots, len: 0 }
+    }
+
+    /// Returns the number of shared values which have been recorded.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns whether no shared values have been recorded.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn entries(&mut self) -> &mut [Option<Entry>] {
+        // SAFETY: `SharedSlot` is a newtype around `Option<Entry>`, so the
+        // slices have the same layout.
+        unsafe {
+            &mut *(self.slots as *mut [SharedSlot] as *mut [Option<Entry>])
+        }
+    }
+}
+
+impl<E: Source> SharedContext<E> for SharedSlice<'_> {
+    fn start_shared(
+        &mut self,
+        address: usize,
+        type_id: TypeId,
+    ) -> Result<SharedState, E> {
+        let mut len = self.len;
+        let result = start_in(self.entries(), &mut len, address, type_id);
+        self.len = len;
+        result
+    }
+
+    fn finish_shared(
+        &mut self,
+        address: usize,
+        type_id: TypeId,
+    ) -> Result<(), E> {
+        let len = self.len;
+        finish_in(self.entries(), len, address, type_id)
+    }
+}
+
+/// A [`SharedContext`] which records shared values in a hash map.
+#[cfg(feature = "std")]
+#[derive(Clone, Debug, Default)]
+pub struct SharedMap {
+    // Maps addresses to the type they were checked as and whether checking
+    // them has finished.
+    entries: std::collections::HashMap<usize, (TypeId, bool)>,
+}
+
+#[cfg(feature = "std")]
+impl SharedMap {
+    /// Returns a new empty `SharedMap`.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of shared values which have been recorded.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns whether no shared values have been recorded.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[cfg(feature = "std")]
+impl<E: Source> SharedContext<E> for SharedMap {
+    fn start_shared(
+        &mut self,
+        address: usize,
+        type_id: TypeId,
+    ) -> Result<SharedState, E> {
+        match self.entries.entry(address) {
+            std::collections::hash_map::Entry::Occupied(entry) => {
+                let &(existing, finished) = entry.get();
+                check_entry(
+                    &Entry {
+                        address,
+                        type_id: existing,
+                        finished,
+                    },
+                    type_id,
+                )
+            }
+            std::collections::hash_map::Entry::Vacant(entry) => {
+                entry.insert((type_id, false));
+                Ok(SharedState::Started)
+            }
+        }
+    }
+
+    fn finish_shared(
+        &mut self,
+        address: usize,
+        type_id: TypeId,
+    ) -> Result<(), E> {
+        match self.entries.get_mut(&address) {
+            Some((existing, finished)) if *existing == type_id => {
+                *finished = true;
+                Ok(())
+            }
+            _ => fail!(NotStartedError { address }),
+        }
+    }
+}
+
+/// A context which adds a [`SharedContext`] to another context.
+///
+/// Shared values are recorded by `S`, and all other capabilities are forwarded
+/// to the inner context.
+#[derive(Clone, Debug, Default)]
+pub struct WithShared<C, S> {
+    inner: C,
+    shared: S,
+}
+
+impl<C, S> WithShared<C, S> {
+    /// Returns a new `WithShared` context which records the shared values
+    /// checked with `inner` in `shared`.
+    #[inline]
+    pub fn new(inner: C, shared: S) -> Self {
+        Self { inner, shared }
+    }
+
+    /// Returns a reference to the inner context.
+    #[inline]
+    pub fn inner(&self) -> &C {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the inner context.
+    #[inline]
+    pub fn inner_mut(&mut self) -> &mut C {
+        &mut self.inner
+    }
+
+    /// Returns a reference to the recorded shared values.
+    #[inline]
+    pub fn shared(&self) -> &S {
+        &self.shared
+    }
+
+    /// Returns the inner context and the recorded shared values.
+    #[inline]
+    pub fn into_parts(self) -> (C, S) {
+        (self.inner, self.shared)
+    }
+}
+
+impl<C, S: SharedContext<E>, E> SharedContext<E> for WithShared<C, S> {
+    #[inline]
+    fn start_shared(
+        &mut self,
+        address: usize,
+        type_id: TypeId,
+    ) -> Result<SharedState, E> {
+        self.shared.start_shared(address, type_id)
+    }
+
+    #[inline]
+    fn finish_shared(
+        &mut self,
+        address: usize,
+        type_id: TypeId,
+    ) -> Result<(), E> {
+        self.shared.finish_shared(address, type_id)
+    }
+}
+
+impl<C: BoundsContext<E>, S, E> BoundsContext<E> for WithShared<C, S> {
+    #[inline]
+    fn check_subtree_ptr(
+        &mut self,
+        ptr: *const u8,
+        layout: &Layout,
+    ) -> Result<(), E> {
+        self.inner.check_subtree_ptr(ptr, layout)
+    }
+
+    #[inline]
+    fn claim_subtree(
+        &mut self,
+        ptr: *const u8,
+        layout: &Layout,
+    ) -> Result<(), E> {
+        self.inner.claim_subtree(ptr, layout)
+    }
+}
+
+impl<C: BudgetContext<E>, S, E> BudgetContext<E> for WithShared<C, S> {
+    #[inline]
+    fn charge(&mut self, units: usize) -> Result<(), E> {
+        self.inner.charge(units)
+    }
+}
+
+impl<C: DepthContext<E>, S, E> DepthContext<E> for WithShared<C, S> {
+    #[inline]
+    fn enter(&mut self) -> Result<(), E> {
+        self.inner.enter()
+    }
+
+    #[inline]
+    fn exit(&mut self) {
+        self.inner.exit()
+    }
+}
+
+/// An error resulting from a shared value which contains a pointer to itself.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct CyclicSharedError {
+    /// The address of the shared value.
+    pub address: usize,
+}
+
+impl fmt::Display for CyclicSharedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "shared value at address {:#x} contains a pointer to itself",
+            self.address,
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for CyclicSharedError {}
+
+/// An error resulting from a shared value which is checked as two different
+/// types.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SharedTypeMismatchError {
+    /// The address of the shared value.
+    pub address: usize,
+}
+
+impl fmt::Display for SharedTypeMismatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "shared value at address {:#x} was checked as two different types",
+            self.address,
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for SharedTypeMismatchError {}
+
+/// An error resulting from checking more shared values than a fixed-capacity
+/// [`SharedContext`] can record.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SharedCapacityError {
+    /// The maximum number of shared values which can be recorded.
+    pub capacity: usize,
+}
+
+impl fmt::Display for SharedCapacityError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "checked more than the maximum of {} shared values",
+            self.capacity,
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for SharedCapacityError {}
+
+/// An error resulting from finishing a shared value which was not started.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct NotStartedError {
+    /// The address of the shared value.
+    pub address: usize,
+}
+
+impl fmt::Display for NotStartedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "finished checking shared value at address {:#x} without starting",
+            self.address,
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for NotStartedError {}
@@ -0,0 +1,329 @@
+//! Validating copies of memory that other processes can write to.
+//!
+//! Checking a value in place is only sound if its bytes can't change until
+//! it's no longer used. When the bytes live in memory shared with an untrusted
+//! process, the other process can change them at any time: a `bool` could be
+//! set to 2 right after it was checked.
+//!
+//! The functions in this module copy the bytes of a value into local storage
+//! one byte at a time with atomic reads, and then check the copy instead. The
+//! copy can't be changed by the other process, so the returned value stays
+//! valid.
+//!
+//! Because the value is moved out of the shared memory, it must not point to
+//! other data in it. Self-relative pointers like
+//! [`RelPtr`](crate::rel_ptr::RelPtr) and
+//! [`RelSlice`](crate::rel_ptr::RelSlice) would be checked relative to the copy
+//! instead, and may read outside of it. Types which contain them must not be
+//! read with these functions.
+//!
+//! For shared memory guarded by a sequence counter, [`read_seqlock`] retries
+//! the copy until it reads a consistent snapshot of the value.
+//!
+//! ```
+//! use bytecheck::{rancor::Failure, snapshot};
+//!
+//! let shared = [1u8, 0, 1];
+//! let values = unsafe {
+//!     snapshot::read_slice::<bool, Failure>(shared.as_ptr().cast(), 3)
+//! };
+//! assert_eq!(values.unwrap(), [true, false, true]);
+//!
+//! let shared = 2u8;
+//! let value = unsafe {
+//!     snapshot::read::<bool, Failure>((&shared as *const u8).cast())
+//! };
+//! assert!(value.is_err());
+//! ```
+
+use core::{
+    fmt,
+    mem::{self, MaybeUninit},
+    ptr,
+    sync::atomic::{fence, AtomicU32, AtomicU8, Ordering},
+};
+
+use rancor::{fail, Source, Strategy};
+
+use crate::CheckBytes;
+
+/// Copies `len` bytes from `src` to `dst` using relaxed atomic byte reads.
+///
+/// # Safety
+///
+/// `src` must be valid for reads of `len` bytes, and `dst` must be valid for
+/// writes of `len` bytes.
+unsafe fn copy_bytes(src: *const u8, dst: *mut u8, len: usize) {
+    for i in 0..len {
+        // SAFETY: The caller has guaranteed that `src` is valid for reads of
+        // `len` bytes. `AtomicU8` has the same size and alignment as `u8`.
+        let byte =
+            unsafe { (*src.add(i).cast::<AtomicU8>()).load(Ordering::Relaxed) };
+        // SAFETY: The caller has guaranteed that `dst` is valid for writes of
+        // `len` bytes.
+        unsafe {
+            dst.add(i).write(byte);
+        }
+    }
+}
+
+/// Copies the bytes of the `T` at `src` and checks the copy.
+///
+/// `src` does not need to be aligned.
+///
+/// # Safety
+///
+/// `src` must be valid for reads of `size_of::<T>()` bytes.
+///
+/// `T` must not contain self-relative pointers or other values which point to
+/// data outside of themselves.
+#[inline]
+pub unsafe fn read<T, E>(src: *const T) -> Result<T, E>
+where
+    T: CheckBytes<Strategy<(), E>>,
+{
+    // SAFETY: The safety conditions of `read_with_context` are the same as
+    // the safety conditions of this function.
+    unsafe { read_with_context(src, &mut ()) }
+}
+
+/// Copies the bytes of the `T` at `src` and checks the copy within the given
+/// context.
+///
+/// `src` does not need to be aligned.
+///
+/// # Safety
+///
+/// `src` must be valid for reads of `size_of::<T>()` bytes.
+///
+/// `T` must not contain self-relative pointers or other values which point to
+/// data outside of themselves.
+pub unsafe fn read_with_context<T, C, E>(
+    src: *const T,
+    context: &mut C,
+) -> Result<T, E>
+where
+    T: CheckBytes<Strategy<C, E>>,
+    C: ?Sized,
+{
+    let mut value = MaybeUninit::<T>::uninit();
+    // SAFETY: The caller has guaranteed that `src` is valid for reads of
+    // `size_of::<T>()` bytes, and `value` is valid for writes of the same
+    // number of bytes.
+    unsafe {
+        copy_bytes(src.cast(), value.as_mut_ptr().cast(), mem::size_of::<T>());
+    }
+    // SAFETY: All of the bytes of `value` were initialized by the copy, and
+    // `value` is aligned for `T`.
+    unsafe {
+        CheckBytes::check_bytes(value.as_ptr(), Strategy::wrap(context))?;
+    }
+    // SAFETY: `check_bytes` returned `Ok`, so `value` is a valid `T`.
+    Ok(unsafe { value.assume_init() })
+}
+
+/// Copies the bytes of `out.len()` elements of `T` at `src` into `out` and
+/// checks the copy within the given context.
+///
+/// `src` does not need to be aligned. Returns the checked elements.
+///
+/// # Safety
+///
+/// `src` must be valid for reads of `out.len() * size_of::<T>()` bytes.
+///
+/// `T` must not contain self-relative pointers or other values which point to
+/// data outside of themselves.
+pub unsafe fn read_slice_into<'a, T, C, E>(
+    src: *const T,
+    out: &'a mut [MaybeUninit<T>],
+    context: &mut C,
+) -> Result<&'a mut [T], E>
+where
+    [T]: CheckBytes<Strategy<C, E>>,
+    C: ?Sized,
+{
+    let len = out.len();
+    let dst = out.as_mut_ptr().cast::<T>();
+    // SAFETY: The caller has guaranteed that `src` is valid for reads of
+    // `len` elements, and `out` is valid for writes of `len` elements.
+    unsafe {
+        copy_bytes(src.cast(), dst.cast(), len * mem::size_of::<T>());
+    }
+    let slice = ptr::slice_from_raw_parts_mut(dst, len);
+    // SAFETY: All of the bytes of `out` were initialized by the copy, and
+    // `out` is aligned for `T`.
+    unsafe {
+        CheckBytes::check_bytes(slice.cast_const(), Strategy::wrap(context))?;
+    }
+    // SAFETY: `check_bytes` returned `Ok`, so `slice` is a valid `[T]`. It
+    // borrows from `out`, so it can't outlive the copied elements.
+    Ok(unsafe { &mut *slice })
+}
+
+/// Copies the bytes of `len` elements of `T` at `src` and checks the copy.
+///
+/// `src` does not need to be aligned.
+///
+/// # Safety
+///
+/// `src` must be valid for reads of `len * size_of::<T>()` bytes.
+///
+/// `T` must not contain self-relative pointers or other values which point to
+/// data outside of themselves.
+#[cfg(feature = "std")]
+#[inline]
+pub unsafe fn read_slice<T, E>(src: *const T, len: usize) -> Result<Vec<T>, E>
+where
+    [T]: CheckBytes<Strategy<(), E>>,
+{
+    // SAFETY: The safety conditions of `read_slice_with_context` are the same
+    // as the safety conditions of this function.
+    unsafe { read_slice_with_context(src, len, &mut ()) }
+}
+
+/// Copies the bytes of `len` elements of `T` at `src` and checks the copy
+/// within the given context.
+///
+/// `src` does not need to be aligned.
+///
+/// # Safety
+///
+/// `src` must be valid for reads of `len * size_of::<T>()` bytes.
+///
+/// `T` must not contain self-relative pointers or other values which point to
+/// data outside of themselves.
+#[cfg(feature = "std")]
+pub unsafe fn read_slice_with_context<T, C, E>(
+    src: *const T,
+    len: usize,
+    context: &mut C,
+) -> Result<Vec<T>, E>
+where
+    [T]: CheckBytes<Strategy<C, E>>,
+    C: ?Sized,
+{
+    let mut values = Vec::with_capacity(len);
+    // SAFETY: The safety conditions of `read_slice_into` are the same as the
+    // safety conditions of this function.
+    unsafe {
+        read_slice_into(src, &mut values.spare_capacity_mut()[..len], context)?;
+    }
+    // SAFETY: `read_slice_into` returned `Ok`, so the first `len` elements
+    // were initialized with valid values.
+    unsafe {
+        values.set_len(len);
+    }
+    Ok(values)
+}
+
+/// Reads a consistent copy of a `T` guarded by a sequence counter, and checks
+/// it.
+///
+/// The writer must make `version` odd before it starts writing the value and
+/// even once it's done. A copy is consistent if `version` was even and didn't
+/// change while the value was copied. Inconsistent copies are retried up to
+/// `max_retries` times before failing.
+///
+/// Only consistent copies are checked, so a value being written is never
+/// reported as invalid.
+///
+/// # Safety
+///
+/// `payload` must be valid for reads of `size_of::<T>()` bytes.
+///
+/// `T` must not contain self-relative pointers or other values which point to
+/// data outside of themselves.
+#[inline]
+pub unsafe fn read_seqlock<T, E>(
+    version: &AtomicU32,
+    payload: *const T,
+    max_retries: usize,
+) -> Result<T, E>
+where
+    T: CheckBytes<Strategy<(), E>>,
+    E: Source,
+{
+    // SAFETY: The safety conditions of `read_seqlock_with_context` are the
+    // same as the safety conditions of this function.
+    unsafe { read_seqlock_with_context(version, payload, max_retries, &mut ()) }
+}
+
+/// Reads a consistent copy of a `T` guarded by a sequence counter, and checks
+/// it within the given context.
+///
+/// See [`read_seqlock`] for more details.
+///
+/// # Safety
+///
+/// `payload` must be valid for reads of `size_of::<T>()` bytes.
+///
+/// `T` must not contain self-relative pointers or other values which point to
+/// data outside of themselves.
+pub unsafe fn read_seqlock_with_context<T, C, E>(
+    version: &AtomicU32,
+    payload: *const T,
+    max_retries: usize,
+    context: &mut C,
+) -> Result<T, E>
+where
+    T: CheckBytes<Strategy<C, E>>,
+    C: ?Sized,
+    E: Source,
+{
+    let mut value = MaybeUninit::<T>::uninit();
+    for _ in 0..=max_retries {
+        let before = version.load(Ordering::Acquire);
+        if before & 1 != 0 {
+            core::hint::spin_loop();
+            continue;
+        }
+        // SAFETY: The caller has guaranteed that `payload` is valid for reads
+        // of `size_of::<T>()` bytes, and `value` is valid for writes of the
+        // same number of bytes.
+        unsafe {
+            copy_bytes(
+                payload.cast(),
+                value.as_mut_ptr().cast(),
+                mem::size_of::<T>(),
+            );
+        }
+        // Keeps the reads of the payload from being reordered after the
+        // second read of the version.
+        fence(Ordering::Acquire);
+        if version.load(Ordering::Relaxed) != before {
+            continue;
+        }
+
+        // SAFETY: All of the bytes of `value` were initialized by the copy,
+        // and `value` is aligned for `T`.
+        unsafe {
+            CheckBytes::check_bytes(value.as_ptr(), Strategy::wrap(context))?;
+        }
+        // SAFETY: `check_bytes` returned `Ok`, so `value` is a valid `T`.
+        return Ok(unsafe { value.assume_init() });
+    }
+    fail!(SeqlockRetryError {
+        attempts: max_retries.saturating_add(1),
+    })
+}
+
+/// An error resulting from failing to read a consistent copy of a value
+/// guarded by a sequence counter.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SeqlockRetryError {
+    /// The number of times a copy was attempted.
+    pub attempts: usize,
+}
+
+impl fmt::Display for SeqlockRetryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "failed to read a consistent copy of a value after {} attempts",
+            self.attempts,
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for SeqlockRetryError {}
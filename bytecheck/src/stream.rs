@@ -0,0 +1,228 @@
+//! Checking slices which arrive in chunks.
+//!
+//! A [`SliceValidator`] checks a `[T]` whose bytes are received in pieces,
+//! for example from a socket. Chunks may be split at any byte, and each
+//! element is checked as soon as all of its bytes have been pushed. Checking
+//! stops at the first invalid element, so a bad payload can be rejected
+//! without waiting for the rest of it.
+//!
+//! Each element is copied into aligned storage before it is checked, so
+//! chunks don't need to be aligned. Because of this, the element type must
+//! not point to other data in the buffer.
+//!
+//! ```
+//! use bytecheck::{rancor::Failure, stream::SliceValidator};
+//!
+//! let bytes = [1, 0, 1, 1, 0];
+//! let mut validator = SliceValidator::<bool, ()>::new(());
+//!
+//! for chunk in bytes.chunks(2) {
+//!     validator.push::<Failure>(chunk).unwrap();
+//! }
+//! assert_eq!(validator.checked(), 5);
+//! assert!(validator.finish::<Failure>().is_ok());
+//!
+//! let mut validator = SliceValidator::<bool, ()>::new(());
+//! assert!(validator.push::<Failure>(&[1, 0]).is_ok());
+//! assert!(validator.push::<Failure>(&[2, 0]).is_err());
+//! assert_eq!(validator.checked(), 2);
+//! ```
+
+use core::{fmt, mem, mem::MaybeUninit};
+
+use rancor::{fail, ResultExt as _, Source, Strategy};
+
+use crate::{BufferContext, BufferLengthError, CheckBytes, SliceCheckContext};
+
+/// A validator for a slice of `T` whose bytes are pushed in chunks.
+///
+/// Elements are checked with the context `C`, which is kept for the whole
+/// slice.
+pub struct SliceValidator<T, C> {
+    context: C,
+    pending: MaybeUninit<T>,
+    filled: usize,
+    checked: usize,
+    failed: bool,
+}
+
+impl<T, C> SliceValidator<T, C> {
+    /// Returns a new `SliceValidator` which checks elements with `context`.
+    #[inline]
+    pub fn new(context: C) -> Self {
+        Self {
+            context,
+            pending: MaybeUninit::uninit(),
+            filled: 0,
+            checked: 0,
+            failed: false,
+        }
+    }
+
+    /// Returns the number of elements which have been checked.
+    #[inline]
+    pub fn checked(&self) -> usize {
+        self.checked
+    }
+
+    /// Returns the number of bytes which have been pushed, not including any
+    /// bytes after an invalid element.
+    #[inline]
+    pub fn consumed(&self) -> usize {
+        self.checked * mem::size_of::<T>() + self.filled
+    }
+
+    /// Returns the number of bytes of the next element which have been
+    /// pushed.
+    #[inline]
+    pub fn pending(&self) -> usize {
+        self.filled
+    }
+
+    /// Returns whether an element failed to validate.
+    #[inline]
+    pub fn is_failed(&self) -> bool {
+        self.failed
+    }
+
+    /// Returns a reference to the context.
+    #[inline]
+    pub fn context(&self) -> &C {
+        &self.context
+    }
+
+    /// Pushes the next chunk of bytes and checks each element completed by
+    /// it.
+    ///
+    /// Returns the number of elements checked. If an element is invalid,
+    /// the rest of the chunk is ignored and every later call to `push` or
+    /// [`finish`](SliceValidator::finish) fails.
+    ///
+    /// Errors report locations relative to the start of the slice.
+    pub fn push<E>(&mut self, mut chunk: &[u8]) -> Result<usize, E>
+    where
+        T: CheckBytes<Strategy<C, E>>,
+        E: Source,
+    {
+        if self.failed {
+            fail!(StreamFailedError {
+                checked: self.checked,
+            });
+        }
+
+        let size = mem::size_of::<T>();
+        if size == 0 {
+            // Zero-sized elements take up no bytes, so like `slice_from_bytes`
+            // the slice is always empty and any bytes are an error.
+            if !chunk.is_empty() {
+                self.failed = true;
+                fail!(BufferLengthError {
+                    expected: 0,
+                    found: chunk.len(),
+                });
+            }
+            return Ok(0);
+        }
+
+        let start = self.checked;
+        while !chunk.is_empty() {
+            let count = usize::min(size - self.filled, chunk.len());
+            let pending = self.pending.as_mut_ptr().cast::<u8>();
+            // SAFETY: `filled + count` is at most `size`, so the copied bytes
+            // are within `pending`. `pending` is owned by `self`, so it can't
+            // overlap `chunk`.
+            unsafe {
+                pending
+                    .add(self.filled)
+                    .copy_from_nonoverlapping(chunk.as_ptr(), count);
+            }
+            self.filled += count;
+            chunk = &chunk[count..];
+
+            if self.filled == size {
+                self.filled = 0;
+                if let Err(error) = self.check_pending() {
+                    self.failed = true;
+                    return Err(error);
+                }
+                self.checked += 1;
+            }
+        }
+        Ok(self.checked - start)
+    }
+
+    /// Finishes checking the slice and returns the context.
+    ///
+    /// Fails if an element failed to validate, or if only some of the bytes
+    /// of the last element were pushed.
+    pub fn finish<E: Source>(self) -> Result<C, E> {
+        if self.failed {
+            fail!(StreamFailedError {
+                checked: self.checked,
+            });
+        }
+        if self.filled != 0 {
+            fail!(BufferLengthError {
+                expected: (self.checked + 1) * mem::size_of::<T>(),
+                found: self.consumed(),
+            });
+        }
+        Ok(self.context)
+    }
+
+    fn check_pending<E>(&mut self) -> Result<(), E>
+    where
+        T: CheckBytes<Strategy<C, E>>,
+        E: Source,
+    {
+        let size = mem::size_of::<T>();
+        let ptr = self.pending.as_ptr();
+        let offset = self.checked * size;
+        // SAFETY: All `size_of::<T>()` bytes of `pending` have been written,
+        // and `MaybeUninit<T>` is aligned for `T`.
+        unsafe {
+            T::check_bytes(ptr, Strategy::wrap(&mut self.context))
+                .trace(SliceCheckContext {
+                    index: self.checked,
+                })
+                // The element is checked in `pending`, so the buffer is placed
+                // such that locations are relative to the start of the slice.
+                .trace(BufferContext {
+                    address: (ptr as usize).wrapping_sub(offset),
+                    len: offset + size,
+                })
+        }
+    }
+}
+
+impl<T, C: fmt::Debug> fmt::Debug for SliceValidator<T, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SliceValidator")
+            .field("context", &self.context)
+            .field("checked", &self.checked)
+            .field("pending", &self.filled)
+            .field("failed", &self.failed)
+            .finish()
+    }
+}
+
+/// An error resulting from using a [`SliceValidator`] after an element failed
+/// to validate.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct StreamFailedError {
+    /// The number of elements which were checked before the invalid element.
+    pub checked: usize,
+}
+
+impl fmt::Display for StreamFailedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "element {} of the stream already failed to validate",
+            self.checked,
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for StreamFailedError {}
@@ -0,0 +1,463 @@
+//! Checking streams of tagged records.
+//!
+//! A TLV (type-length-value) stream is a sequence of records, each of which
+//! is a numeric tag, a length prefix, and a value of that many bytes. Unlike
+//! [frames](crate::frame), records in the same stream can contain values of
+//! different types.
+//!
+//! The type of each value is looked up in a registry of [`RecordType`]s by
+//! its tag. Records with tags that aren't in the registry are handled
+//! according to an [`UnknownTagPolicy`].
+//!
+//! ```
+//! use bytecheck::{
+//!     frame::FrameConfig,
+//!     rancor::Failure,
+//!     tlv::{RecordType, TlvConfig},
+//! };
+//!
+//! static REGISTRY: [RecordType<Failure>; 2] =
+//!     [RecordType::new::<bool>(1), RecordType::new::<char>(2)];
+//!
+//! // Records with a one-byte tag and a one-byte length.
+//! let config = TlvConfig::new()
+//!     .with_tag(1)
+//!     .with_frame(FrameConfig::new().with_prefix(1));
+//! let bytes = [1, 1, 1, 1, 1, 2];
+//! let mut records = config.records(&bytes, &REGISTRY);
+//!
+//! let record = records.next().unwrap().unwrap();
+//! assert_eq!(record.tag(), 1);
+//! assert_eq!(record.get::<bool>(), Some(&true));
+//! assert!(records.next().unwrap().is_err());
+//! assert!(records.next().is_none());
+//! ```
+
+use core::{alloc::Layout, any::TypeId, fmt};
+
+use rancor::{fail, ResultExt as _, Source, Strategy};
+
+use crate::{
+    frame::{FrameConfig, TruncatedFrameError},
+    from_bytes_with_context, BufferContext, CheckBytes,
+};
+
+/// How records with unregistered tags are handled.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum UnknownTagPolicy {
+    /// Yield an [`UnknownTagError`] and continue with the next record.
+    #[default]
+    Error,
+    /// Skip the record.
+    Skip,
+    /// Yield the record without checking its value.
+    Collect,
+}
+
+/// A registered record type, which associates a tag with the type of its
+/// values.
+///
+/// Record types are also used to register the types of the sections of a
+/// [container](crate::container).
+pub struct RecordType<E, C = ()> {
+    tag: u64,
+    layout: Layout,
+    type_id: fn() -> TypeId,
+    type_name: fn() -> &'static str,
+    check: fn(&[u8], &mut C) -> Result<(), E>,
+}
+
+impl<E, C> RecordType<E, C> {
+    /// Returns a new `RecordType` which checks the values of records with
+    /// `tag` as `T`.
+    #[inline]
+    pub const fn new<T>(tag: u64) -> Self
+    where
+        T: CheckBytes<Strategy<C, E>> + 'static,
+        E: Source,
+    {
+        Self {
+            tag,
+            layout: Layout::new::<T>(),
+            type_id: TypeId::of::<T>,
+            type_name: core::any::type_name::<T>,
+            check: check_value::<T, C, E>,
+        }
+    }
+
+    /// Returns the tag of the record type.
+    #[inline]
+    pub fn tag(&self) -> u64 {
+        self.tag
+    }
+
+    /// Returns the layout of the type of the record's values.
+    #[inline]
+    pub fn layout(&self) -> Layout {
+        self.layout
+    }
+
+    /// Returns the name of the type of the record's values.
+    #[inline]
+    pub fn type_name(&self) -> &'static str {
+        (self.type_name)()
+    }
+
+    /// Checks `bytes` as a value of this type, and returns a view of it.
+    pub(crate) fn check<'a>(
+        &self,
+        offset: usize,
+        bytes: &'a [u8],
+        context: &mut C,
+    ) -> Result<Record<'a>, E> {
+        (self.check)(bytes, context)?;
+        Ok(Record {
+            tag: self.tag,
+            offset,
+            value: bytes,
+            type_id: Some((self.type_id)()),
+            type_name: Some(self.type_name()),
+        })
+    }
+}
+
+impl<E, C> Clone for RecordType<E, C> {
+    #[inline]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<E, C> Copy for RecordType<E, C> {}
+
+impl<E, C> fmt::Debug for RecordType<E, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RecordType")
+            .field("tag", &self.tag)
+            .field("type_name", &self.type_name())
+            .finish()
+    }
+}
+
+fn check_value<T, C, E>(bytes: &[u8], context: &mut C) -> Result<(), E>
+where
+    T: CheckBytes<Strategy<C, E>>,
+    E: Source,
+{
+    from_bytes_with_context::<T, C, E>(bytes, context).map(|_| ())
+}
+
+/// A record whose value has been checked as its registered type.
+#[derive(Clone, Copy, Debug)]
+pub struct Record<'a> {
+    tag: u64,
+    offset: usize,
+    value: &'a [u8],
+    type_id: Option<TypeId>,
+    type_name: Option<&'static str>,
+}
+
+impl<'a> Record<'a> {
+    /// Returns the tag of the record.
+    #[inline]
+    pub fn tag(&self) -> u64 {
+        self.tag
+    }
+
+    /// Returns the offset of the start of the record from the start of the
+    /// stream.
+    #[inline]
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the bytes of the record's value.
+    #[inline]
+    pub fn bytes(&self) -> &'a [u8] {
+        self.value
+    }
+
+    /// Returns whether the record's tag was registered.
+    ///
+    /// Records with unregistered tags are only yielded with
+    /// [`UnknownTagPolicy::Collect`], and their values are not checked.
+    #[inline]
+    pub fn is_known(&self) -> bool {
+        self.type_id.is_some()
+    }
+
+    /// Returns the name of the registered type of the record's value.
+    #[inline]
+    pub fn type_name(&self) -> Option<&'static str> {
+        self.type_name
+    }
+
+    /// Returns the record's value if it was checked as a `T`.
+    #[inline]
+    pub fn get<T: 'static>(&self) -> Option<&'a T> {
+        if self.type_id != Some(TypeId::of::<T>()) {
+            return None;
+        }
+        // SAFETY: The value was checked as a `T` by `from_bytes`, so it is
+        // aligned, has the size of a `T`, and is a valid `T`. The returned
+        // reference borrows from the stream, so it can't outlive the bytes.
+        Some(unsafe { &*self.value.as_ptr().cast::<T>() })
+    }
+}
+
+/// The layout of a TLV stream.
+///
+/// The length prefix and value are laid out as described by a
+/// [`FrameConfig`], with the tag at the start of each record before the
+/// length prefix. The tag has the same byte order as the length prefix.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TlvConfig {
+    frame: FrameConfig,
+    tag: usize,
+    unknown_tags: UnknownTagPolicy,
+}
+
+impl TlvConfig {
+    /// Returns a new `TlvConfig` with a four-byte tag, the default
+    /// [`FrameConfig`], and [`UnknownTagPolicy::Error`].
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            frame: FrameConfig::new(),
+            tag: 4,
+            unknown_tags: UnknownTagPolicy::Error,
+        }
+    }
+
+    /// Sets the width of the tag in bytes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `width` is not 1, 2, 4, or 8.
+    #[inline]
+    pub const fn with_tag(mut self, width: usize) -> Self {
+        assert!(
+            matches!(width, 1 | 2 | 4 | 8),
+            "tag width must be 1, 2, 4, or 8 bytes",
+        );
+        self.tag = width;
+        self
+    }
+
+    /// Sets the layout of the length prefix and value.
+    #[inline]
+    pub const fn with_frame(mut self, frame: FrameConfig) -> Self {
+        self.frame = frame;
+        self
+    }
+
+    /// Sets how records with unregistered tags are handled.
+    #[inline]
+    pub const fn with_unknown_tags(mut self, policy: UnknownTagPolicy) -> Self {
+        self.unknown_tags = policy;
+        self
+    }
+
+    /// Returns the width of the tag in bytes.
+    #[inline]
+    pub const fn tag(&self) -> usize {
+        self.tag
+    }
+
+    /// Returns the layout of the length prefix and value.
+    #[inline]
+    pub const fn frame(&self) -> FrameConfig {
+        self.frame
+    }
+
+    /// Returns how records with unregistered tags are handled.
+    #[inline]
+    pub const fn unknown_tags(&self) -> UnknownTagPolicy {
+        self.unknown_tags
+    }
+
+    /// Returns an iterator which checks the records in `bytes` with the types
+    /// in `registry`.
+    ///
+    /// Values are checked in place, so `bytes` must be aligned for each
+    /// registered type. If a tag is registered more than once, the first
+    /// registration is used.
+    #[inline]
+    pub fn records<'a, 'r, E>(
+        self,
+        bytes: &'a [u8],
+        registry: &'r [RecordType<E>],
+    ) -> Records<'a, 'r, E> {
+        self.records_with_context(bytes, registry, ())
+    }
+
+    /// Returns an iterator which checks the records in `bytes` with the types
+    /// in `registry` within the given context.
+    ///
+    /// See [`records`](TlvConfig::records) for more details.
+    #[inline]
+    pub fn records_with_context<'a, 'r, E, C>(
+        self,
+        bytes: &'a [u8],
+        registry: &'r [RecordType<E, C>],
+        context: C,
+    ) -> Records<'a, 'r, E, C> {
+        Records {
+            config: self,
+            registry,
+            bytes,
+            offset: 0,
+            index: 0,
+            done: false,
+            context,
+        }
+    }
+}
+
+impl Default for TlvConfig {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// An iterator over the checked records in a byte slice.
+///
+/// Returned by [`TlvConfig::records`].
+pub struct Records<'a, 'r, E, C = ()> {
+    config: TlvConfig,
+    registry: &'r [RecordType<E, C>],
+    bytes: &'a [u8],
+    offset: usize,
+    index: usize,
+    done: bool,
+    context: C,
+}
+
+impl<E, C> Records<'_, '_, E, C> {
+    /// Returns the number of bytes which have been consumed.
+    #[inline]
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the context.
+    #[inline]
+    pub fn into_context(self) -> C {
+        self.context
+    }
+}
+
+impl<'a, E: Source, C> Records<'a, '_, E, C> {
+    // Returns `None` if the record was skipped.
+    fn next_record(&mut self, start: usize) -> Result<Option<Record<'a>>, E> {
+        let frame = self.config.frame;
+        // Offsets saturate so that records which would end past `usize::MAX`
+        // fail as truncated instead of overflowing.
+        let tag_end = start.saturating_add(self.config.tag);
+        let prefix_end = tag_end.saturating_add(frame.prefix());
+        let Some(header) = self.bytes.get(start..prefix_end) else {
+            fail!(TruncatedFrameError {
+                expected: prefix_end,
+                found: self.bytes.len(),
+            });
+        };
+        let tag = frame.decode_uint(&header[..self.config.tag]);
+        let len = frame.decode_len(&header[self.config.tag..])?;
+        let value_start = prefix_end.saturating_add(frame.padding(prefix_end));
+        let value_end = value_start.saturating_add(len);
+        let Some(value) = self.bytes.get(value_start..value_end) else {
+            fail!(TruncatedFrameError {
+                expected: value_end,
+                found: self.bytes.len(),
+            });
+        };
+        // The record is well-formed, so checking can continue with the next
+        // record even if the value is invalid.
+        self.offset = value_end;
+
+        match self.registry.iter().find(|t| t.tag == tag) {
+            Some(record_type) => record_type
+                .check(start, value, &mut self.context)
+                .trace(BufferContext::new(self.bytes))
+                .map(Some),
+            None => match self.config.unknown_tags {
+                UnknownTagPolicy::Error => fail!(UnknownTagError { tag }),
+                UnknownTagPolicy::Skip => Ok(None),
+                UnknownTagPolicy::Collect => Ok(Some(Record {
+                    tag,
+                    offset: start,
+                    value,
+                    type_id: None,
+                    type_name: None,
+                })),
+            },
+        }
+    }
+}
+
+impl<'a, E: Source, C> Iterator for Records<'a, '_, E, C> {
+    type Item = Result<Record<'a>, E>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            let start = self.offset + self.config.frame.padding(self.offset);
+            if start >= self.bytes.len() {
+                return None;
+            }
+            let offset = self.offset;
+            let result = self.next_record(start);
+            let index = self.index;
+            self.index += 1;
+            match result {
+                Ok(Some(record)) => return Some(Ok(record)),
+                Ok(None) => (),
+                Err(error) => {
+                    if self.offset == offset {
+                        self.done = true;
+                    }
+                    return Some(Err(error.trace(RecordContext {
+                        index,
+                        offset: start,
+                    })));
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Context for errors resulting from invalid records.
+#[derive(Debug)]
+pub struct RecordContext {
+    /// The index of the record.
+    pub index: usize,
+    /// The offset of the start of the record from the start of the stream.
+    pub offset: usize,
+}
+
+impl fmt::Display for RecordContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "while checking record {} at offset {}",
+            self.index, self.offset,
+        )
+    }
+}
+
+/// An error resulting from a record with a tag that isn't registered.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct UnknownTagError {
+    /// The tag of the record.
+    pub tag: u64,
+}
+
+impl fmt::Display for UnknownTagError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "record has unknown tag {}", self.tag)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for UnknownTagError {}
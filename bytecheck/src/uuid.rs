@@ -2,7 +2,12 @@
 
 use uuid::Uuid;
 
-use crate::{rancor::Fallible, CheckBytes};
+use crate::{
+    fingerprint::{Fingerprint, FingerprintHasher},
+    rancor::Fallible,
+    schema::{Schema, SchemaKind, TypeSchema},
+    CheckBytes,
+};
 
 // SAFETY: `Uuid` is `#[repr(transparent)]` around an inner `Bytes`, which is a
 // simple byte array. Byte arrays are always valid.
@@ -13,6 +18,16 @@ unsafe impl<C: Fallible + ?Sized> CheckBytes<C> for Uuid {
     }
 }
 
+impl Fingerprint for Uuid {
+    const FINGERPRINT: u64 =
+        FingerprintHasher::new().write_str("Uuid").finish();
+}
+
+impl Schema for Uuid {
+    const SCHEMA: &'static TypeSchema<'static> =
+        &TypeSchema::sized::<Uuid>("Uuid", SchemaKind::Primitive);
+}
+
 #[cfg(test)]
 mod bytecheck_tests {
     use uuid::Uuid;
@@ -15,9 +15,9 @@ use proc_macro2::TokenStream;
 use quote::{quote, ToTokens};
 use syn::{
     meta::ParseNestedMeta, parenthesized, parse::Parse, parse_macro_input,
-    parse_quote, punctuated::Punctuated, spanned::Spanned, AttrStyle, Data,
-    DeriveInput, Error, Field, Fields, Ident, Index, LitStr, Path, Token,
-    WherePredicate,
+    parse_quote, parse_quote_spanned, punctuated::Punctuated, spanned::Spanned,
+    AttrStyle, Data, DeriveInput, Error, Field, Fields, FieldsNamed, Ident,
+    Index, LitInt, LitStr, Path, Token, Type, WhereClause, WherePredicate,
 };
 
 use repr::Repr;
@@ -30,6 +30,7 @@ struct Attributes {
     pub bounds: Option<Punctuated<WherePredicate, Token![,]>>,
     pub crate_path: Option<Path>,
     pub verify: Option<Path>,
+    pub size_field: Option<Ident>,
 }
 
 fn try_set_attribute<T: ToTokens>(
@@ -67,11 +68,104 @@ fn parse_check_bytes_attributes(
         }
 
         try_set_attribute(&mut attributes.verify, meta.path, "verify")
+    } else if meta.path.is_ident("size_field") {
+        let size_field = meta.value()?.parse::<Ident>()?;
+        try_set_attribute(&mut attributes.size_field, size_field, "size_field")
     } else {
         Err(meta.error("unrecognized check_bytes argument"))
     }
 }
 
+fn parse_added(field: &Field) -> Result<Option<LitInt>, Error> {
+    let mut added = None;
+    for attr in field.attrs.iter() {
+        if attr.path().is_ident("check_bytes") {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("added") {
+                    let version = meta.value()?.parse::<LitInt>()?;
+                    version.base10_parse::<u32>()?;
+                    try_set_attribute(&mut added, version, "added")
+                } else {
+                    Err(meta.error("unrecognized check_bytes field argument"))
+                }
+            })?;
+        }
+    }
+    Ok(added)
+}
+
+/// Checks that the fields added to an evolving struct come after all of the
+/// required fields, in the order they were added.
+fn check_added(
+    name: &Ident,
+    fields: &Fields,
+    added: &[Option<LitInt>],
+    attributes: &Attributes,
+) -> Result<(), Error> {
+    let Some(ref size_field) = attributes.size_field else {
+        return match added.iter().flatten().next() {
+            Some(version) => Err(Error::new_spanned(
+                version,
+                "fields can only be added to structs with a size_field",
+            )),
+            None => Ok(()),
+        };
+    };
+
+    if !matches!(fields, Fields::Named(_)) {
+        return Err(Error::new_spanned(
+            size_field,
+            "size_field is only supported for structs with named fields",
+        ));
+    }
+    if !matches!(attributes.repr.base_repr, Some((BaseRepr::C, _))) {
+        return Err(Error::new_spanned(
+            name,
+            "structs with a size_field must be repr(C)",
+        ));
+    }
+    let size_field_index = fields
+        .iter()
+        .position(|f| f.ident.as_ref() == Some(size_field))
+        .ok_or_else(|| {
+            Error::new_spanned(size_field, "size_field must name a field")
+        })?;
+    if added[size_field_index].is_some() {
+        return Err(Error::new_spanned(
+            size_field,
+            "size_field cannot name an added field",
+        ));
+    }
+
+    let mut last = None;
+    for (field, version) in fields.iter().zip(added) {
+        let version = version
+            .as_ref()
+            .map(|v| Ok::<_, Error>((v, v.base10_parse::<u32>()?)))
+            .transpose()?;
+        match (version, last) {
+            (Some((version, n)), Some(last)) if n < last => {
+                return Err(Error::new_spanned(
+                    version,
+                    "fields must be added in order of version",
+                ));
+            }
+            (None, Some(_)) => {
+                return Err(Error::new_spanned(
+                    field,
+                    "required fields must come before added fields",
+                ));
+            }
+            _ => (),
+        }
+        if let Some((_, n)) = version {
+            last = Some(n);
+        }
+    }
+
+    Ok(())
+}
+
 fn parse_attributes(input: &DeriveInput) -> Result<Attributes, Error> {
     let mut result = Attributes::default();
 
@@ -104,6 +198,10 @@ fn parse_attributes(input: &DeriveInput) -> Result<Attributes, Error> {
 ///   where bounds may need to be omitted to prevent recursive type definitions.
 ///   In the context of the added bounds, `__C` is the name of the context
 ///   generic (e.g. `__C: MyContext`).
+/// - `size_field = ...`: Names the field of a `repr(C)` struct which stores its
+///   size in bytes. Fields marked with `#[check_bytes(added = N)]` are only
+///   checked if they fit in the stored size, and the struct implements
+///   `Evolving`. See `bytecheck::evolve` for details.
 ///
 /// This derive macro automatically adds a type bound `field: CheckBytes<__C>`
 /// for each field type. This can cause an overflow while evaluating trait
@@ -113,6 +211,28 @@ fn parse_attributes(input: &DeriveInput) -> Result<Attributes, Error> {
 /// will suppress this trait bound and allow recursive structures. This may be
 /// too coarse for some types, in which case additional type bounds may be
 /// required with `bounds(...)`.
+///
+/// Recursive structures which follow pointers can recurse as deeply as their
+/// input describes. Their pointer types should check the pointee with
+/// `bytecheck::depth::check_nested` so that a `DepthLimit` context can bound
+/// the recursion.
+///
+/// For enums, this also implements `EnumVariants` so that the names and
+/// discriminants of the variants are available statically. They're included in
+/// the error returned for an invalid discriminant.
+///
+/// This also implements `Fingerprint` with a hash of the type's name, repr,
+/// field names, field fingerprints, and discriminants. Every field type must
+/// implement `Fingerprint`, and deriving fails with an error naming the field
+/// type if one doesn't. Fields marked with `#[omit_bounds]` contribute the name
+/// of their type instead of its fingerprint, so that recursive types still
+/// have fingerprints.
+///
+/// It also implements `Schema` with the size, alignment, and field offsets of
+/// the type as the compiler laid it out. Like `Fingerprint`, every field type
+/// must implement `Schema`, and fields marked with `#[omit_bounds]` are
+/// described as opaque. Types with a `verify` hook are described as opaque too,
+/// since the hook can reject values that the layout allows.
 #[proc_macro_derive(CheckBytes, attributes(check_bytes, omit_bounds))]
 pub fn check_bytes_derive(
     input: proc_macro::TokenStream,
@@ -126,6 +246,28 @@ pub fn check_bytes_derive(
 fn derive_check_bytes(mut input: DeriveInput) -> Result<TokenStream, Error> {
     let attributes = parse_attributes(&input)?;
 
+    // Fields may only be added to the end of structs with a size field.
+    let added = match input.data {
+        Data::Struct(ref data) => {
+            let added = data
+                .fields
+                .iter()
+                .map(parse_added)
+                .collect::<Result<Vec<_>, _>>()?;
+            check_added(&input.ident, &data.fields, &added, &attributes)?;
+            added
+        }
+        _ => {
+            if let Some(ref size_field) = attributes.size_field {
+                return Err(Error::new_spanned(
+                    size_field,
+                    "size_field is only supported for structs",
+                ));
+            }
+            Vec::new()
+        }
+    };
+
     let crate_path = attributes.crate_path.unwrap_or(parse_quote!(::bytecheck));
 
     let name = &input.ident;
@@ -147,6 +289,13 @@ fn derive_check_bytes(mut input: DeriveInput) -> Result<TokenStream, Error> {
     // We add context error bounds to the where clause for the trait impl.
     let trait_where_clause = trait_generics.make_where_clause();
     trait_where_clause.predicates.push(match &input.data {
+        // Structs with a size field may error while checking the stored size,
+        // so they need `Source` just like enums.
+        Data::Struct(_) if attributes.size_field.is_some() => parse_quote! {
+            <
+                __C as #crate_path::rancor::Fallible
+            >::Error: #crate_path::rancor::Source
+        },
         // Structs and unions just propagate any errors from checking their
         // fields, so the error type of the context just needs to be `Trace`.
         Data::Struct(_) | Data::Union(_) => parse_quote! {
@@ -180,7 +329,18 @@ fn derive_check_bytes(mut input: DeriveInput) -> Result<TokenStream, Error> {
             <#name #type_ty_generics as #crate_path::Verify<__C>>::verify(
                 unsafe { &*value },
                 context,
-            )?;
+            ).map_err(|e| {
+                <
+                    <
+                        __C as #crate_path::rancor::Fallible
+                    >::Error as #crate_path::rancor::Trace
+                >::trace(
+                    e,
+                    #crate_path::VerifyContext {
+                        type_name: ::core::stringify!(#name),
+                    },
+                )
+            })?;
         })
     } else {
         None
@@ -191,6 +351,15 @@ fn derive_check_bytes(mut input: DeriveInput) -> Result<TokenStream, Error> {
         trait_generics.split_for_impl();
     let trait_where_clause = trait_where_clause.unwrap();
 
+    let fingerprint_impl =
+        derive_fingerprint(&input, &attributes.repr, &crate_path);
+    let schema_impl = derive_schema(
+        &input,
+        &attributes.repr,
+        attributes.verify.is_some(),
+        &crate_path,
+    );
+
     // Build CheckBytes impl
     let check_bytes_impl = match input.data {
         Data::Struct(ref data) => match data.fields {
@@ -205,7 +374,7 @@ fn derive_check_bytes(mut input: DeriveInput) -> Result<TokenStream, Error> {
                     );
                 }
 
-                let field_checks = fields.named.iter().map(|f| {
+                let check_field = |f: &Field| {
                     let field = &f.ident;
                     let ty = &f.ty;
                     quote! {
@@ -226,7 +395,27 @@ fn derive_check_bytes(mut input: DeriveInput) -> Result<TokenStream, Error> {
                             )
                         })?;
                     }
-                });
+                };
+
+                // Added fields are only checked if they fit in the stored
+                // size, after checking the required fields.
+                let field_checks = fields
+                    .named
+                    .iter()
+                    .zip(&added)
+                    .filter(|(_, added)| added.is_none())
+                    .map(|(f, _)| check_field(f));
+                let trailing_checks =
+                    attributes.size_field.as_ref().map(|size_field| {
+                        check_trailing(
+                            fields,
+                            &added,
+                            size_field,
+                            &crate_path,
+                            name,
+                            check_field,
+                        )
+                    });
 
                 quote! {
                     #[automatically_derived]
@@ -245,6 +434,7 @@ fn derive_check_bytes(mut input: DeriveInput) -> Result<TokenStream, Error> {
                             <__C as #crate_path::rancor::Fallible>::Error,
                         > {
                             #(#field_checks)*
+                            #trailing_checks
                             #verify
                             ::core::result::Result::Ok(())
                         }
@@ -405,6 +595,16 @@ fn derive_check_bytes(mut input: DeriveInput) -> Result<TokenStream, Error> {
                 }
             });
 
+            let enum_variants = data.variants.iter().map(|v| {
+                let variant = &v.ident;
+                quote! {
+                    #crate_path::EnumVariant {
+                        name: ::core::stringify!(#variant),
+                        discriminant: Discriminant::#variant,
+                    }
+                }
+            });
+
             let tag_variant_values = data.variants.iter().map(|v| {
                 let name = &v.ident;
                 quote! { Discriminant::#name }
@@ -495,12 +695,25 @@ fn derive_check_bytes(mut input: DeriveInput) -> Result<TokenStream, Error> {
                     <
                         <
                             __C as #crate_path::rancor::Fallible
-                        >::Error as #crate_path::rancor::Source
-                    >::new(
-                        #crate_path::InvalidEnumDiscriminantError {
-                            enum_name: ::core::stringify!(#name),
-                            invalid_discriminant: tag,
-                        }
+                        >::Error as #crate_path::rancor::Trace
+                    >::trace(
+                        <
+                            <
+                                __C as #crate_path::rancor::Fallible
+                            >::Error as #crate_path::rancor::Source
+                        >::new(
+                            #crate_path::InvalidEnumDiscriminantError {
+                                enum_name: ::core::stringify!(#name),
+                                invalid_discriminant: tag,
+                                variants: <
+                                    #name #type_ty_generics
+                                        as #crate_path::EnumVariants
+                                >::VARIANTS,
+                            }
+                        ),
+                        #crate_path::LocationContext::new(
+                            value.cast::<#repr>(),
+                        ),
                     )
                 )
             };
@@ -521,6 +734,18 @@ fn derive_check_bytes(mut input: DeriveInput) -> Result<TokenStream, Error> {
 
                     #(#variant_structs)*
 
+                    #[automatically_derived]
+                    impl #type_impl_generics #crate_path::EnumVariants
+                        for #name #type_ty_generics
+                    #type_where_clause
+                    {
+                        type Repr = #repr;
+
+                        const VARIANTS: &'static [
+                            #crate_path::EnumVariant<#repr>
+                        ] = &[#(#enum_variants,)*];
+                    }
+
                     #[automatically_derived]
                     // SAFETY: `check_bytes` only returns `Ok` if:
                     // - The discriminant is valid for some variant of the enum,
@@ -549,6 +774,9 @@ fn derive_check_bytes(mut input: DeriveInput) -> Result<TokenStream, Error> {
                             ::core::result::Result::Ok(())
                         }
                     }
+
+                    #fingerprint_impl
+                    #schema_impl
                 };
             }
         }
@@ -560,7 +788,375 @@ fn derive_check_bytes(mut input: DeriveInput) -> Result<TokenStream, Error> {
         }
     };
 
-    Ok(check_bytes_impl)
+    let evolving_impl = attributes.size_field.as_ref().map(|size_field| {
+        derive_evolving(&input, &added, size_field, &crate_path)
+    });
+
+    match input.data {
+        Data::Enum(_) => Ok(check_bytes_impl),
+        _ => Ok(quote! {
+            #check_bytes_impl
+            #fingerprint_impl
+            #schema_impl
+            #evolving_impl
+        }),
+    }
+}
+
+fn schema_fields(
+    fields: &Fields,
+    container: TokenStream,
+    index_offset: usize,
+    crate_path: &Path,
+    where_clause: &mut WhereClause,
+) -> TokenStream {
+    let mut schemas = Vec::with_capacity(fields.len());
+    for (i, field) in fields.iter().enumerate() {
+        let ty = &field.ty;
+        let (name, member) = match field.ident {
+            Some(ref ident) => (
+                quote! {
+                    ::core::option::Option::Some(::core::stringify!(#ident))
+                },
+                quote! { #ident },
+            ),
+            None => {
+                let index = Index::from(i + index_offset);
+                (quote! { ::core::option::Option::None }, quote! { #index })
+            }
+        };
+        let schema = if has_omit_bounds(field) {
+            quote! {
+                &#crate_path::schema::TypeSchema::sized::<#ty>(
+                    ::core::stringify!(#ty),
+                    #crate_path::schema::SchemaKind::Opaque,
+                )
+            }
+        } else {
+            let schema = quote! { #crate_path::schema::Schema };
+            require_field_impl(where_clause, ty, &schema);
+            quote! { <#ty as #crate_path::schema::Schema>::SCHEMA }
+        };
+        schemas.push(quote! {
+            #crate_path::schema::FieldSchema {
+                name: #name,
+                offset: ::core::mem::offset_of!(#container, #member),
+                schema: #schema,
+            }
+        });
+    }
+    quote! { &[#(#schemas,)*] }
+}
+
+fn derive_schema(
+    input: &DeriveInput,
+    repr: &Repr,
+    verify: bool,
+    crate_path: &Path,
+) -> TokenStream {
+    let name = &input.ident;
+    let repr_name = repr.name();
+    let (impl_generics, ty_generics, where_clause) =
+        input.generics.split_for_impl();
+    let mut where_clause = where_clause.unwrap().clone();
+
+    let kind = match input.data {
+        // A verify hook can reject values that the layout allows, which a
+        // schema can't describe.
+        Data::Struct(_) | Data::Enum(_) if verify => {
+            quote! { #crate_path::schema::SchemaKind::Opaque }
+        }
+        Data::Struct(ref data) => {
+            let fields = schema_fields(
+                &data.fields,
+                quote! { Self },
+                0,
+                crate_path,
+                &mut where_clause,
+            );
+            quote! {
+                #crate_path::schema::SchemaKind::Struct {
+                    repr: #repr_name,
+                    fields: #fields,
+                }
+            }
+        }
+        Data::Enum(ref data) => {
+            let Some((BaseRepr::Int(tag), _)) = repr.base_repr else {
+                return TokenStream::new();
+            };
+            let variants = data
+                .variants
+                .iter()
+                .map(|v| {
+                    let variant = &v.ident;
+                    let variant_name =
+                        Ident::new(&format!("Variant{variant}"), v.span());
+                    // Fields of tuple variants come after the tag.
+                    let fields = schema_fields(
+                        &v.fields,
+                        quote! { #variant_name #ty_generics },
+                        1,
+                        crate_path,
+                        &mut where_clause,
+                    );
+                    quote! {
+                        #crate_path::schema::VariantSchema {
+                            name: ::core::stringify!(#variant),
+                            discriminant: Discriminant::#variant as u128,
+                            fields: #fields,
+                        }
+                    }
+                })
+                .collect::<Vec<_>>();
+            quote! {
+                #crate_path::schema::SchemaKind::Enum {
+                    repr: #repr_name,
+                    tag: <#tag as #crate_path::schema::Schema>::SCHEMA,
+                    variants: &[#(#variants,)*],
+                }
+            }
+        }
+        Data::Union(_) => return TokenStream::new(),
+    };
+
+    quote! {
+        #[automatically_derived]
+        impl #impl_generics #crate_path::schema::Schema
+            for #name #ty_generics
+        #where_clause
+        {
+            const SCHEMA: &'static #crate_path::schema::TypeSchema<'static> =
+                &#crate_path::schema::TypeSchema::sized::<Self>(
+                    ::core::stringify!(#name),
+                    #kind,
+                );
+        }
+    }
+}
+
+fn check_trailing(
+    fields: &FieldsNamed,
+    added: &[Option<LitInt>],
+    size_field: &Ident,
+    crate_path: &Path,
+    name: &Ident,
+    check_field: impl Fn(&Field) -> TokenStream,
+) -> TokenStream {
+    let trailing_checks = fields
+        .named
+        .iter()
+        .zip(added)
+        .enumerate()
+        .filter(|(_, (_, added))| added.is_some())
+        .map(|(i, (f, _))| {
+            let field = &f.ident;
+            let check = check_field(f);
+            quote! {
+                if #crate_path::evolve::EvolvingField::is_present(
+                    &<
+                        Self as #crate_path::evolve::Evolving
+                    >::FIELDS[#i],
+                    stored_size,
+                ) {
+                    let _: *const #crate_path::evolve::Trailing<_> =
+                        ::core::ptr::addr_of!((*value).#field);
+                    #check
+                }
+            }
+        });
+
+    quote! {
+        let stored_size = ::core::ptr::read_unaligned(
+            ::core::ptr::addr_of!((*value).#size_field),
+        ) as u64;
+        #crate_path::evolve::check_stored_size::<
+            Self,
+            <__C as #crate_path::rancor::Fallible>::Error,
+        >(stored_size).map_err(|e| {
+            <
+                <
+                    __C as #crate_path::rancor::Fallible
+                >::Error as #crate_path::rancor::Trace
+            >::trace(
+                e,
+                #crate_path::StructCheckContext {
+                    struct_name: ::core::stringify!(#name),
+                    field_name: ::core::stringify!(#size_field),
+                },
+            )
+        })?;
+        #(#trailing_checks)*
+    }
+}
+
+fn derive_evolving(
+    input: &DeriveInput,
+    added: &[Option<LitInt>],
+    size_field: &Ident,
+    crate_path: &Path,
+) -> TokenStream {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) =
+        input.generics.split_for_impl();
+    let Data::Struct(ref data) = input.data else {
+        return TokenStream::new();
+    };
+
+    let fields = data.fields.iter().zip(added).map(|(f, added)| {
+        let field = &f.ident;
+        let ty = &f.ty;
+        let added = match added {
+            Some(version) => quote! { ::core::option::Option::Some(#version) },
+            None => quote! { ::core::option::Option::None },
+        };
+        quote! {
+            #crate_path::evolve::EvolvingField {
+                name: ::core::stringify!(#field),
+                offset: ::core::mem::offset_of!(Self, #field),
+                size: ::core::mem::size_of::<#ty>(),
+                added: #added,
+            }
+        }
+    });
+
+    quote! {
+        #[automatically_derived]
+        // SAFETY: `FIELDS` describes every field of the struct in order, and
+        // the derived `CheckBytes` implementation checks every added field
+        // which is present according to the stored size.
+        unsafe impl #impl_generics #crate_path::evolve::Evolving
+            for #name #ty_generics
+        #where_clause
+        {
+            const FIELDS: &'static [#crate_path::evolve::EvolvingField] =
+                &[#(#fields,)*];
+
+            #[inline]
+            fn stored_size(&self) -> u64 {
+                self.#size_field as u64
+            }
+        }
+    }
+}
+
+fn has_omit_bounds(field: &Field) -> bool {
+    field.attrs.iter().any(|a| a.path().is_ident("omit_bounds"))
+}
+
+/// Bounds a field type by `trait_path`.
+///
+/// The bound is a plain bound rather than a higher-ranked one, so a field type
+/// which doesn't implement the trait is a compile error that names it instead
+/// of silently leaving the trait unimplemented.
+fn require_field_impl(
+    where_clause: &mut WhereClause,
+    ty: &Type,
+    trait_path: &TokenStream,
+) {
+    where_clause
+        .predicates
+        .push(parse_quote_spanned! { ty.span() => #ty: #trait_path });
+}
+
+fn fingerprint_fields(
+    fields: &Fields,
+    crate_path: &Path,
+    where_clause: &mut WhereClause,
+) -> TokenStream {
+    let len = fields.len() as u64;
+    let mut writes = Vec::with_capacity(fields.len());
+    for (i, field) in fields.iter().enumerate() {
+        let name = match field.ident {
+            Some(ref ident) => ident.to_string(),
+            None => i.to_string(),
+        };
+        let ty = &field.ty;
+        if has_omit_bounds(field) {
+            writes.push(quote! {
+                .write_str(#name)
+                .write_str(::core::stringify!(#ty))
+            });
+        } else {
+            let fingerprint = quote! { #crate_path::fingerprint::Fingerprint };
+            require_field_impl(where_clause, ty, &fingerprint);
+            writes.push(quote! {
+                .write_str(#name)
+                .write_u64(
+                    <
+                        #ty as #crate_path::fingerprint::Fingerprint
+                    >::FINGERPRINT
+                )
+            });
+        }
+    }
+    quote! { .write_u64(#len) #(#writes)* }
+}
+
+fn derive_fingerprint(
+    input: &DeriveInput,
+    repr: &Repr,
+    crate_path: &Path,
+) -> TokenStream {
+    let name = &input.ident;
+    let repr = repr.name();
+    let (impl_generics, ty_generics, where_clause) =
+        input.generics.split_for_impl();
+    let mut where_clause = where_clause.unwrap().clone();
+
+    let writes = match input.data {
+        Data::Struct(ref data) => {
+            let fields =
+                fingerprint_fields(&data.fields, crate_path, &mut where_clause);
+            quote! {
+                .write_str("struct")
+                .write_str(::core::stringify!(#name))
+                .write_str(#repr)
+                #fields
+            }
+        }
+        Data::Enum(ref data) => {
+            let len = data.variants.len() as u64;
+            let variants = data
+                .variants
+                .iter()
+                .map(|v| {
+                    let variant = &v.ident;
+                    let fields = fingerprint_fields(
+                        &v.fields,
+                        crate_path,
+                        &mut where_clause,
+                    );
+                    quote! {
+                        .write_str(::core::stringify!(#variant))
+                        .write_u128(Discriminant::#variant as u128)
+                        #fields
+                    }
+                })
+                .collect::<Vec<_>>();
+            quote! {
+                .write_str("enum")
+                .write_str(::core::stringify!(#name))
+                .write_str(#repr)
+                .write_u64(#len)
+                #(#variants)*
+            }
+        }
+        Data::Union(_) => return TokenStream::new(),
+    };
+
+    quote! {
+        #[automatically_derived]
+        impl #impl_generics #crate_path::fingerprint::Fingerprint
+            for #name #ty_generics
+        #where_clause
+        {
+            const FINGERPRINT: u64 =
+                #crate_path::fingerprint::FingerprintHasher::new()
+                    #writes
+                    .finish();
+        }
+    }
 }
 
 fn check_arm_named_field(
@@ -161,6 +161,24 @@ impl Repr {
 
         self.try_set_base_repr(parsed_repr, meta.path)
     }
+
+    /// Returns a stable description of the repr, like `"C, align(4)"`.
+    pub fn name(&self) -> String {
+        let base_repr = self.base_repr.as_ref().map(|(b, _)| match b {
+            BaseRepr::C => "C".to_string(),
+            BaseRepr::Transparent => "transparent".to_string(),
+            BaseRepr::Int(i) => i.to_token_stream().to_string(),
+        });
+        let modifier = self.modifier.as_ref().map(|(m, _)| match m {
+            Modifier::Packed => "packed".to_string(),
+            Modifier::Align(n) => format!("align({})", n.base10_digits()),
+        });
+        base_repr
+            .into_iter()
+            .chain(modifier)
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
 }
 
 impl ToTokens for Repr {
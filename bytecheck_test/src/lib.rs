@@ -12,7 +12,9 @@
 mod tests {
     use bytecheck::{
         check_bytes, check_bytes_with_context,
+        fingerprint::{Fingerprint, FingerprintHasher},
         rancor::{Source, Failure, Fallible, Infallible},
+        schema::{Schema, SchemaKind, TypeSchema},
         CheckBytes, Verify,
     };
     use rancor::Strategy;
@@ -56,6 +58,16 @@ mod tests {
         }
     }
 
+    impl Fingerprint for CharLE {
+        const FINGERPRINT: u64 =
+            FingerprintHasher::new().write_str("CharLE").finish();
+    }
+
+    impl Schema for CharLE {
+        const SCHEMA: &'static TypeSchema<'static> =
+            &TypeSchema::sized::<CharLE>("CharLE", SchemaKind::Opaque);
+    }
+
     #[repr(C, align(16))]
     struct Aligned<const N: usize>([u8; N]);
 
@@ -451,6 +463,70 @@ mod tests {
         }
     }
 
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_enum_variants() {
+        use bytecheck::{
+            error::CheckError, EnumVariant, EnumVariants,
+            InvalidEnumDiscriminantError,
+        };
+
+        #[derive(CheckBytes, Debug)]
+        #[repr(u8)]
+        #[allow(dead_code)]
+        enum Test {
+            A,
+            B,
+            C,
+            D = 7,
+        }
+
+        assert_eq!(
+            <Test as EnumVariants>::VARIANTS,
+            &[
+                EnumVariant {
+                    name: "A",
+                    discriminant: 0,
+                },
+                EnumVariant {
+                    name: "B",
+                    discriminant: 1,
+                },
+                EnumVariant {
+                    name: "C",
+                    discriminant: 2,
+                },
+                EnumVariant {
+                    name: "D",
+                    discriminant: 7,
+                },
+            ],
+        );
+
+        let message = |tag: u8| unsafe {
+            check_bytes::<Test, CheckError>((&tag as *const u8).cast())
+                .unwrap_err()
+                .to_string()
+        };
+        assert_eq!(
+            message(3),
+            "invalid discriminant '3' for enum 'Test', expected one of 0 (A), \
+             1 (B), 2 (C), 7 (D); closest known variant is 'C' (2)",
+        );
+        assert_eq!(
+            message(200),
+            "invalid discriminant '200' for enum 'Test', expected one of 0 \
+             (A), 1 (B), 2 (C), 7 (D)",
+        );
+
+        let error = InvalidEnumDiscriminantError {
+            enum_name: "Test",
+            invalid_discriminant: 9u8,
+            variants: <Test as EnumVariants>::VARIANTS,
+        };
+        assert_eq!(error.closest_variant().unwrap().name, "D");
+    }
+
     #[test]
     fn test_unsized() {
         unsafe {
@@ -494,6 +570,464 @@ mod tests {
         }
     }
 
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_error_path() {
+        use bytecheck::{
+            error::CheckError,
+            path::{Path, Segment},
+            StructCheckContext,
+        };
+
+        #[derive(CheckBytes, Debug)]
+        #[repr(u8)]
+        #[allow(dead_code)]
+        enum Kind {
+            Unnamed(u32),
+            Named { id: u16, label: bool },
+        }
+
+        #[derive(CheckBytes, Debug)]
+        struct Header {
+            magic: u32,
+            entries: [Kind; 4],
+        }
+
+        let mut value = Header {
+            magic: 0,
+            entries: [
+                Kind::Unnamed(0),
+                Kind::Unnamed(1),
+                Kind::Named { id: 2, label: true },
+                Kind::Named { id: 3, label: true },
+            ],
+        };
+        unsafe {
+            check_bytes::<_, CheckError>(&value).unwrap();
+        }
+
+        if let Kind::Named { label, .. } = &mut value.entries[3] {
+            unsafe {
+                *(label as *mut bool).cast::<u8>() = 7;
+            }
+        }
+        let error =
+            unsafe { check_bytes::<_, CheckError>(&value).unwrap_err() };
+        assert_eq!(
+            error.path().to_string(),
+            "Header.entries[3].Kind::Named.label",
+        );
+        assert_eq!(
+            error.path().segments().copied().collect::<Vec<_>>(),
+            [
+                Segment::Field {
+                    parent: "Header",
+                    name: "entries",
+                },
+                Segment::Index(3),
+                Segment::Variant {
+                    enum_name: "Kind",
+                    variant_name: "Named",
+                },
+                Segment::Field {
+                    parent: "Named",
+                    name: "label",
+                },
+            ],
+        );
+
+        value.entries[3] = Kind::Unnamed(3);
+        unsafe {
+            *(&mut value.entries[1] as *mut Kind).cast::<u8>() = 2;
+        }
+        let error =
+            unsafe { check_bytes::<_, CheckError>(&value).unwrap_err() };
+        assert_eq!(error.path().to_string(), "Header.entries[1]");
+
+        let error = unsafe {
+            check_bytes::<(u8, (bool,)), CheckError>(bytes![0u8, 2u8].cast())
+                .unwrap_err()
+        };
+        assert_eq!(error.path().to_string(), ".1.0");
+
+        let mut path = Path::new();
+        assert!(!path.push_trace(&"while checking something"));
+        assert!(path.is_empty());
+        path.push_outer(Segment::Field {
+            parent: "Named",
+            name: "label",
+        });
+        path.push_outer(Segment::Variant {
+            enum_name: "Kind",
+            variant_name: "Named",
+        });
+        path.push_outer(Segment::Index(3));
+        assert!(path.push_trace(&StructCheckContext {
+            struct_name: "Header",
+            field_name: "entries",
+        }));
+        assert_eq!(path.len(), 4);
+        assert_eq!(path.to_string(), "Header.entries[3].Kind::Named.label");
+        assert_eq!(
+            path.json().to_string(),
+            concat!(
+                "{\"path\":\"Header.entries[3].Kind::Named.label\",",
+                "\"segments\":[",
+                "{\"field\":\"entries\",\"parent\":\"Header\"},",
+                "{\"index\":3},",
+                "{\"variant\":\"Named\",\"enum\":\"Kind\"},",
+                "{\"field\":\"label\",\"parent\":\"Named\"}",
+                "]}",
+            ),
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_inline_error() {
+        use bytecheck::{
+            error::{ErrorKind, InlineCheckError, Location},
+            from_bytes,
+            path::Segment,
+        };
+
+        #[derive(CheckBytes, Debug)]
+        #[repr(C)]
+        struct Inner {
+            a: u8,
+            b: [bool; 2],
+        }
+
+        #[derive(CheckBytes, Debug)]
+        #[repr(C)]
+        struct Outer {
+            inner: Inner,
+        }
+
+        let aligned = Aligned([0u8, 1, 3]);
+        let bytes = &aligned.0[..];
+
+        let error = from_bytes::<Outer, InlineCheckError>(bytes).unwrap_err();
+        assert_eq!(error.kind(), Some(ErrorKind::InvalidBool { byte: 3 }));
+        assert_eq!(error.truncated(), 0);
+        assert_eq!(error.location(), Some(Location { offset: 2, len: 1 }));
+        assert_eq!(
+            error.to_string(),
+            "bool set to invalid byte 3, expected either 0 or 1\n\
+             at Outer.inner.b[1]\n\
+             at bytes 2..3",
+        );
+
+        // Only the innermost segments are kept
+        let error =
+            from_bytes::<Outer, InlineCheckError<2>>(bytes).unwrap_err();
+        assert_eq!(error.truncated(), 1);
+        assert_eq!(
+            error.segments().copied().collect::<Vec<_>>(),
+            [
+                Segment::Field {
+                    parent: "Inner",
+                    name: "b",
+                },
+                Segment::Index(1),
+            ],
+        );
+        assert_eq!(
+            error.to_string(),
+            "bool set to invalid byte 3, expected either 0 or 1\n\
+             at ....b[1]\n\
+             at bytes 2..3",
+        );
+    }
+
+    #[test]
+    fn test_from_bytes() {
+        use bytecheck::{from_bytes, slice_from_bytes};
+
+        #[derive(CheckBytes, Debug)]
+        #[repr(C)]
+        struct Test {
+            a: u32,
+            b: bool,
+        }
+
+        let aligned =
+            Aligned([1u8, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0]);
+        let bytes = &aligned.0[..];
+
+        let value = from_bytes::<Test, Failure>(&bytes[..8]).unwrap();
+        assert_eq!(value.a, 1);
+        assert!(value.b);
+        from_bytes::<Test, Failure>(&bytes[8..]).unwrap_err();
+        // Wrong length
+        from_bytes::<Test, Failure>(&bytes[..4]).unwrap_err();
+        // Unaligned
+        from_bytes::<u32, Failure>(&bytes[1..5]).unwrap_err();
+
+        assert_eq!(slice_from_bytes::<u32, Failure>(bytes).unwrap().len(), 4);
+        slice_from_bytes::<Test, Failure>(&bytes[..8]).unwrap();
+        slice_from_bytes::<Test, Failure>(bytes).unwrap_err();
+        slice_from_bytes::<u32, Failure>(&bytes[..6]).unwrap_err();
+
+        // Zero-sized elements only fit in an empty buffer
+        assert!(slice_from_bytes::<(), Failure>(&bytes[..0])
+            .unwrap()
+            .is_empty());
+        slice_from_bytes::<(), Failure>(&bytes[..1]).unwrap_err();
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_error_location() {
+        use bytecheck::{
+            error::{CheckError, Location},
+            from_bytes, slice_from_bytes,
+        };
+
+        #[derive(CheckBytes, Debug)]
+        #[repr(C)]
+        struct Test {
+            a: u32,
+            b: bool,
+            c: CharLE,
+        }
+
+        let aligned = Aligned([
+            0u8, 0, 0, 0, 1, 0, 0, 0, 0x78, 0, 0, 0, // valid
+            0, 0, 0, 0, 7, 0, 0, 0, 0x78, 0, 0, 0, // invalid bool
+            0, 0, 0, 0, 1, 0, 0, 0, 0, 0xd8, 0, 0, // invalid char
+        ]);
+        let bytes = &aligned.0[..];
+
+        let error = from_bytes::<Test, CheckError>(&bytes[12..24]).unwrap_err();
+        assert_eq!(error.location(), Some(Location { offset: 4, len: 1 }));
+
+        let error = slice_from_bytes::<Test, CheckError>(bytes).unwrap_err();
+        assert_eq!(error.location(), Some(Location { offset: 16, len: 1 }));
+        assert_eq!(error.path().to_string(), "[1].b");
+
+        let error =
+            slice_from_bytes::<Test, CheckError>(&bytes[24..]).unwrap_err();
+        assert_eq!(error.location(), Some(Location { offset: 8, len: 4 }));
+
+        #[derive(CheckBytes, Debug)]
+        #[repr(u16)]
+        #[allow(dead_code)]
+        enum Unit {
+            A,
+        }
+
+        let error =
+            slice_from_bytes::<Unit, CheckError>(&bytes[2..6]).unwrap_err();
+        assert_eq!(error.location(), Some(Location { offset: 2, len: 2 }));
+
+        // Checking through a raw pointer can't determine the offset
+        let error = unsafe {
+            check_bytes::<Test, CheckError>(bytes[12..].as_ptr().cast())
+                .unwrap_err()
+        };
+        assert_eq!(error.location(), None);
+    }
+
+    #[test]
+    #[cfg(feature = "hexdump")]
+    fn test_hexdump() {
+        use core::fmt;
+
+        use bytecheck::{
+            error::{CheckError, Diagnostic, Location},
+            hexdump::Hexdump,
+            slice_from_bytes,
+        };
+
+        #[derive(CheckBytes, Debug)]
+        #[repr(C)]
+        struct Test {
+            a: u32,
+            b: bool,
+            c: CharLE,
+        }
+
+        let mut aligned = Aligned([0u8; 36]);
+        aligned.0[..4].copy_from_slice(b"abcd");
+        aligned.0[8] = b'x';
+        aligned.0[16] = 7;
+        let bytes = &aligned.0[..];
+
+        let error = slice_from_bytes::<Test, CheckError>(bytes).unwrap_err();
+        assert_eq!(
+            Hexdump::new(&error, bytes).context_rows(1).to_string(),
+            concat!(
+                "00000000  61 62 63 64 00 00 00 00  78 00 00 00 00 00 00 00  |abcd....x.......|\n",
+                "00000010  07 00 00 00 00 00 00 00  00 00 00 00 00 00 00 00  |................|\n",
+                "          ^^\n",
+                "          [1].b: bool set to invalid byte 7, expected either 0 or 1\n",
+                "00000020  00 00 00 00                                       |....|\n",
+            ),
+        );
+
+        struct TestError {
+            location: Option<Location>,
+        }
+
+        impl Diagnostic for TestError {
+            fn location(&self) -> Option<Location> {
+                self.location
+            }
+
+            fn fmt_path(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("Test.b")
+            }
+
+            fn fmt_reason(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("invalid")
+            }
+        }
+
+        // Spans that cross rows are marked on each row
+        let error = TestError {
+            location: Some(Location { offset: 14, len: 4 }),
+        };
+        assert_eq!(
+            Hexdump::new(&error, bytes).context_rows(0).to_string(),
+            concat!(
+                "00000000  61 62 63 64 00 00 00 00  78 00 00 00 00 00 00 00  |abcd....x.......|\n",
+                "                                                     ^^ ^^\n",
+                "00000010  07 00 00 00 00 00 00 00  00 00 00 00 00 00 00 00  |................|\n",
+                "          ^^ ^^\n",
+                "          Test.b: invalid\n",
+            ),
+        );
+
+        // Without a location, only the message is written
+        let error = TestError { location: None };
+        assert_eq!(
+            Hexdump::new(&error, bytes).context_rows(2).to_string(),
+            "Test.b: invalid\n",
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_error_kind() {
+        use core::num::NonZeroU32;
+        use std::ffi::CStr;
+
+        use bytecheck::{
+            error::{CheckError, ErrorKind},
+            from_bytes, slice_from_bytes,
+        };
+
+        fn kind_of<T>(result: Result<T, CheckError>) -> ErrorKind {
+            result.map(|_| ()).unwrap_err().kind().unwrap()
+        }
+
+        let aligned = Aligned([2u8, 0, 0xd8, 0, 0xf0, 0x28, 0x8c, 0x28]);
+        let bytes = &aligned.0[..];
+
+        assert_eq!(
+            kind_of(from_bytes::<bool, CheckError>(&bytes[..1])),
+            ErrorKind::InvalidBool { byte: 2 },
+        );
+        assert_eq!(
+            kind_of(from_bytes::<CharLE, CheckError>(&bytes[..4])),
+            ErrorKind::InvalidChar { value: 0xd80002 },
+        );
+        assert_eq!(
+            kind_of(
+                from_bytes::<NonZeroU32, CheckError>(&Aligned([0u8; 4]).0,)
+            ),
+            ErrorKind::ZeroNonZero,
+        );
+
+        let str_bytes = b"ab\xf0\x28";
+        let error = unsafe {
+            check_bytes::<str, CheckError>(::ptr_meta::from_raw_parts(
+                str_bytes.as_ptr().cast(),
+                str_bytes.len(),
+            ))
+            .unwrap_err()
+        };
+        assert_eq!(
+            error.kind(),
+            Some(ErrorKind::InvalidUtf8 { valid_up_to: 2 })
+        );
+
+        for (bytes, kind) in [
+            (&b"hello"[..], ErrorKind::MissingNul),
+            (&b""[..], ErrorKind::MissingNul),
+            (&b"he\0llo\0"[..], ErrorKind::InteriorNul { position: 2 }),
+        ] {
+            let error = unsafe {
+                check_bytes::<CStr, CheckError>(::ptr_meta::from_raw_parts(
+                    bytes.as_ptr().cast(),
+                    bytes.len(),
+                ))
+                .unwrap_err()
+            };
+            assert_eq!(error.kind(), Some(kind));
+        }
+
+        #[derive(CheckBytes, Debug)]
+        #[repr(i8)]
+        #[allow(dead_code)]
+        enum Signed {
+            A = -1,
+            B = 1,
+        }
+
+        assert_eq!(
+            kind_of(slice_from_bytes::<Signed, CheckError>(
+                &Aligned([1u8, 0xfe]).0,
+            )),
+            ErrorKind::InvalidDiscriminant {
+                enum_name: "Signed",
+                discriminant: -2,
+            },
+        );
+
+        #[derive(Debug)]
+        struct OddError;
+
+        impl core::fmt::Display for OddError {
+            fn fmt(
+                &self,
+                f: &mut core::fmt::Formatter<'_>,
+            ) -> core::fmt::Result {
+                write!(f, "value is odd")
+            }
+        }
+
+        impl std::error::Error for OddError {}
+
+        #[derive(CheckBytes)]
+        #[check_bytes(verify)]
+        struct Even(u32);
+
+        unsafe impl<C> Verify<C> for Even
+        where
+            C: Fallible + ?Sized,
+            C::Error: Source,
+        {
+            fn verify(&self, _: &mut C) -> Result<(), C::Error> {
+                if self.0 & 1 != 0 {
+                    rancor::fail!(OddError);
+                }
+                Ok(())
+            }
+        }
+
+        from_bytes::<Even, CheckError>(&Aligned([2u8, 0, 0, 0]).0).unwrap();
+        let error = from_bytes::<Even, CheckError>(&Aligned([3u8, 0, 0, 0]).0)
+            .map(|_| ())
+            .unwrap_err();
+        assert_eq!(
+            error.kind(),
+            Some(ErrorKind::VerifyFailed { type_name: "Even" }),
+        );
+        assert_eq!(ErrorKind::find(&error), error.kind());
+    }
+
     #[test]
     fn test_recursive() {
         struct MyBox<T: ?Sized> {
@@ -530,6 +1064,2071 @@ mod tests {
         }
     }
 
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_depth_limit() {
+        use bytecheck::{
+            check_bytes_with_context,
+            depth::{check_nested, DepthContext, DepthLimit},
+            error::{CheckError, ErrorKind},
+        };
+
+        struct MyBox<T: ?Sized> {
+            inner: *const T,
+        }
+
+        unsafe impl<T, C> CheckBytes<C> for MyBox<T>
+        where
+            T: CheckBytes<C>,
+            C: Fallible + DepthContext + ?Sized,
+        {
+            unsafe fn check_bytes(
+                value: *const Self,
+                context: &mut C,
+            ) -> Result<(), C::Error> {
+                check_nested((*value).inner, context)
+            }
+        }
+
+        #[derive(CheckBytes)]
+        #[check_bytes(bounds(__C: DepthContext))]
+        #[repr(u8)]
+        #[allow(dead_code)]
+        enum Node {
+            Nil,
+            Cons(#[omit_bounds] MyBox<Node>),
+        }
+
+        // Reserve up front so that pushing doesn't move the linked nodes.
+        let mut nodes = Vec::with_capacity(11);
+        nodes.push(Node::Nil);
+        for i in 0..10 {
+            let inner = &nodes[i] as *const Node;
+            nodes.push(Node::Cons(MyBox { inner }));
+        }
+        let head = nodes.last().unwrap();
+
+        unsafe {
+            let mut context = DepthLimit::new(10);
+            check_bytes_with_context::<_, _, CheckError>(head, &mut context)
+                .unwrap();
+            assert_eq!(context.depth(), 0);
+
+            let error = check_bytes_with_context::<_, _, CheckError>(
+                head,
+                &mut DepthLimit::new(4),
+            )
+            .unwrap_err();
+            assert_eq!(
+                error.kind(),
+                Some(ErrorKind::DepthLimitExceeded {
+                    depth: 5,
+                    max_depth: 4,
+                }),
+            );
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_budget() {
+        use bytecheck::{
+            budget::{Budget, BudgetContext, Budgeted},
+            depth::DepthLimit,
+            error::{CheckError, ErrorKind},
+            slice_from_bytes_with_context,
+        };
+
+        #[derive(CheckBytes, Debug)]
+        struct Test {
+            a: [u8; 4],
+            b: bool,
+        }
+
+        let values = [
+            Test { a: [0; 4], b: true },
+            Test {
+                a: [1; 4],
+                b: false,
+            },
+        ];
+
+        unsafe {
+            // Slices cost one unit per element. Arrays and derived types have
+            // a fixed size, so they don't cost anything.
+            let mut context = Budgeted::new((), Budget::new(2));
+            check_bytes_with_context::<[Test], _, CheckError>(
+                &values[..],
+                &mut context,
+            )
+            .unwrap();
+            assert_eq!(context.budget().remaining(), 0);
+
+            let error = check_bytes_with_context::<[Test], _, CheckError>(
+                &values[..],
+                &mut Budgeted::new((), Budget::new(1)),
+            )
+            .unwrap_err();
+            assert_eq!(
+                error.kind(),
+                Some(ErrorKind::BudgetExceeded { limit: 1 }),
+            );
+
+            check_bytes_with_context::<[Test; 2], _, CheckError>(
+                &values,
+                &mut Budgeted::new((), Budget::new(0)),
+            )
+            .unwrap();
+
+            // A huge length fails before any elements are checked.
+            let huge = ::ptr_meta::from_raw_parts::<str>(
+                values.as_ptr().cast(),
+                usize::MAX,
+            );
+            check_bytes_with_context::<str, _, CheckError>(
+                huge,
+                &mut Budgeted::new((), Budget::new(1024)),
+            )
+            .unwrap_err();
+
+            let s = "hello world";
+            let mut context =
+                Budgeted::new(DepthLimit::default(), Budget::new(1024));
+            check_bytes_with_context::<str, _, CheckError>(s, &mut context)
+                .unwrap();
+            assert_eq!(context.budget().used(), s.len());
+
+            // Other contexts don't limit work by default.
+            struct Unlimited;
+
+            impl<E> BudgetContext<E> for Unlimited {}
+
+            check_bytes_with_context::<str, _, CheckError>(s, &mut Unlimited)
+                .unwrap();
+        }
+
+        let bytes = [1, 0, 1];
+        let test = slice_from_bytes_with_context::<bool, _, CheckError>(
+            &bytes,
+            &mut Budgeted::new((), Budget::new(3)),
+        )
+        .unwrap();
+        assert_eq!(test, &[true, false, true]);
+        let error = slice_from_bytes_with_context::<bool, _, CheckError>(
+            &bytes,
+            &mut Budgeted::new((), Budget::new(2)),
+        )
+        .unwrap_err();
+        assert_eq!(error.kind(), Some(ErrorKind::BudgetExceeded { limit: 2 }));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_bounds() {
+        use core::alloc::Layout;
+
+        use bytecheck::{
+            bounds::{check_subtree, Bounds, BoundsContext},
+            error::{CheckError, ErrorKind},
+        };
+
+        let aligned = Aligned([0u8, 0, 0, 0, 1, 0, 0, 0, 2, 1, 0, 0]);
+        let bytes = &aligned.0[..];
+        let base = bytes.as_ptr();
+        let layout = Layout::new::<u32>();
+
+        let mut bounds = Bounds::new(bytes);
+        let context = Strategy::<_, CheckError>::wrap(&mut bounds);
+        unsafe {
+            // Unaligned
+            let error =
+                context.check_subtree_ptr(base.add(2), &layout).unwrap_err();
+            assert_eq!(
+                error.kind(),
+                Some(ErrorKind::UnalignedPointer { align: 4 }),
+            );
+
+            // Out of bounds
+            let error = context
+                .check_subtree_ptr(base.add(12), &layout)
+                .unwrap_err();
+            assert_eq!(error.kind(), Some(ErrorKind::OutOfBounds { len: 4 }));
+            context
+                .check_subtree_ptr(base.wrapping_sub(4), &layout)
+                .unwrap_err();
+
+            // Claims must be in order and not overlap
+            check_subtree(base.add(4).cast::<u32>(), layout, context).unwrap();
+            let error =
+                check_subtree(base.cast::<u32>(), layout, context).unwrap_err();
+            assert_eq!(
+                error.kind(),
+                Some(ErrorKind::OverlappingClaim { len: 4 }),
+            );
+            check_subtree(base.add(4).cast::<u32>(), layout, context)
+                .unwrap_err();
+
+            // The pointee is checked after it's claimed
+            let error = check_subtree(
+                base.add(8).cast::<[bool; 2]>(),
+                Layout::new::<[bool; 2]>(),
+                context,
+            )
+            .unwrap_err();
+            assert_eq!(error.kind(), Some(ErrorKind::InvalidBool { byte: 2 }));
+        }
+        assert_eq!(bounds.claimed(), 10);
+        assert_eq!(bounds.remaining(), 2);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_rel_ptr() {
+        use core::{alloc::Layout, mem::offset_of};
+
+        use bytecheck::{
+            bounds::{check_subtree, Bounds},
+            error::{CheckError, ErrorKind},
+            rel_ptr::{RelPtr, RelSlice, RelStr},
+        };
+
+        #[derive(CheckBytes, Debug)]
+        #[repr(C)]
+        struct Root {
+            value: RelPtr<u32>,
+            items: RelSlice<CharLE>,
+            name: RelStr,
+        }
+
+        #[repr(C)]
+        struct Buffer {
+            root: Root,
+            value: u32,
+            items: [u32; 2],
+            name: [u8; 5],
+        }
+
+        fn offset(to: usize, from: usize) -> i32 {
+            (to as isize - from as isize) as i32
+        }
+
+        fn buffer() -> Buffer {
+            Buffer {
+                root: Root {
+                    value: RelPtr::from_offset(offset(
+                        offset_of!(Buffer, value),
+                        offset_of!(Root, value),
+                    )),
+                    items: RelSlice::from_offset(
+                        offset(
+                            offset_of!(Buffer, items),
+                            offset_of!(Root, items),
+                        ),
+                        2,
+                    ),
+                    name: RelStr::from_offset(
+                        offset(
+                            offset_of!(Buffer, name),
+                            offset_of!(Root, name),
+                        ),
+                        5,
+                    ),
+                },
+                value: 42,
+                items: ['a' as u32, 'b' as u32],
+                name: *b"hello",
+            }
+        }
+
+        fn check(buffer: &Buffer) -> Result<&Root, Box<CheckError>> {
+            let bytes = unsafe {
+                core::slice::from_raw_parts(
+                    (buffer as *const Buffer).cast::<u8>(),
+                    core::mem::size_of::<Buffer>(),
+                )
+            };
+            let mut bounds = Bounds::new(bytes);
+            let root = bytes.as_ptr().cast::<Root>();
+            unsafe {
+                check_subtree(
+                    root,
+                    Layout::new::<Root>(),
+                    Strategy::<_, CheckError>::wrap(&mut bounds),
+                )
+                .map_err(Box::new)?;
+                Ok(&*root)
+            }
+        }
+
+        let valid = buffer();
+        let root = check(&valid).unwrap();
+        unsafe {
+            assert_eq!(*root.value.get(), 42);
+            assert_eq!(root.items.get().len(), 2);
+            assert_eq!(root.name.get(), "hello");
+        }
+
+        // Invalid pointee
+        let mut invalid = buffer();
+        invalid.name[1] = 0xff;
+        let error = check(&invalid).unwrap_err();
+        assert_eq!(
+            error.kind(),
+            Some(ErrorKind::InvalidUtf8 { valid_up_to: 1 })
+        );
+        assert_eq!(error.path().to_string(), "Root.name");
+
+        // Out of bounds
+        let mut invalid = buffer();
+        invalid.root.items =
+            RelSlice::from_offset(invalid.root.items.offset(), u32::MAX);
+        let error = check(&invalid).unwrap_err();
+        assert!(matches!(error.kind(), Some(ErrorKind::OutOfBounds { .. })));
+
+        // Unaligned
+        let mut invalid = buffer();
+        invalid.root.value =
+            RelPtr::from_offset(invalid.root.value.offset() + 1);
+        let error = check(&invalid).unwrap_err();
+        assert_eq!(
+            error.kind(),
+            Some(ErrorKind::UnalignedPointer { align: 4 })
+        );
+
+        // Overlapping the root
+        let mut invalid = buffer();
+        invalid.root.value = RelPtr::from_offset(0);
+        let error = check(&invalid).unwrap_err();
+        assert_eq!(error.kind(), Some(ErrorKind::OverlappingClaim { len: 4 }));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_shared() {
+        use core::{alloc::Layout, mem::offset_of};
+
+        use bytecheck::{
+            bounds::{check_subtree, Bounds, BoundsContext},
+            budget::{Budget, BudgetContext, Budgeted},
+            error::{CheckError, ErrorKind},
+            rel_ptr::RelShared,
+            shared::{
+                SharedArray, SharedContext, SharedMap, SharedSlice, SharedSlot,
+                WithShared,
+            },
+        };
+
+        #[derive(CheckBytes, Debug)]
+        #[check_bytes(bounds(__C: BoundsContext + SharedContext))]
+        #[repr(C)]
+        struct Node {
+            value: u32,
+            #[omit_bounds]
+            next: RelShared<Node>,
+        }
+
+        #[derive(CheckBytes, Debug)]
+        #[repr(C)]
+        struct Root {
+            a: RelShared<Leaf>,
+            b: RelShared<Leaf>,
+        }
+
+        #[derive(CheckBytes, Debug)]
+        #[check_bytes(verify)]
+        #[repr(C)]
+        struct Leaf {
+            value: u32,
+        }
+
+        // Leaves charge for each time they're checked.
+        unsafe impl<C> Verify<C> for Leaf
+        where
+            C: Fallible + BudgetContext + ?Sized,
+        {
+            fn verify(&self, context: &mut C) -> Result<(), C::Error> {
+                context.charge(1)
+            }
+        }
+
+        #[repr(C)]
+        struct Buffer {
+            root: Root,
+            leaf: Leaf,
+        }
+
+        fn rel<T>(to: usize, from: usize) -> RelShared<T> {
+            RelShared::from_offset((to as isize - from as isize) as i32)
+        }
+
+        fn bytes_of<T>(value: &T) -> &[u8] {
+            unsafe {
+                core::slice::from_raw_parts(
+                    (value as *const T).cast::<u8>(),
+                    core::mem::size_of::<T>(),
+                )
+            }
+        }
+
+        fn check<T, C>(
+            bytes: &[u8],
+            context: &mut C,
+        ) -> Result<(), Box<CheckError>>
+        where
+            T: CheckBytes<Strategy<C, CheckError>>,
+            C: BoundsContext<CheckError>,
+        {
+            unsafe {
+                check_subtree(
+                    bytes.as_ptr().cast::<T>(),
+                    Layout::new::<T>(),
+                    Strategy::wrap(context),
+                )
+                .map_err(Box::new)
+            }
+        }
+
+        let leaf = offset_of!(Buffer, leaf);
+        let buffer = Buffer {
+            root: Root {
+                a: rel(leaf, offset_of!(Root, a)),
+                b: rel(leaf, offset_of!(Root, b)),
+            },
+            leaf: Leaf { value: 1 },
+        };
+        let bytes = bytes_of(&buffer);
+
+        // The shared leaf is only checked once, so the budget is only charged
+        // once.
+        fn check_dag<S>(bytes: &[u8], shared: S)
+        where
+            S: SharedContext<CheckError>,
+        {
+            let mut context = Budgeted::new(
+                WithShared::new(Bounds::new(bytes), shared),
+                Budget::new(1),
+            );
+            check::<Root, _>(bytes, &mut context).unwrap();
+            assert_eq!(context.budget().remaining(), 0);
+        }
+        check_dag(bytes, SharedArray::<2>::new());
+        check_dag(bytes, SharedSlice::new(&mut [SharedSlot::EMPTY; 2]));
+        check_dag(bytes, SharedMap::new());
+
+        let mut context =
+            WithShared::new(Bounds::new(bytes), SharedArray::<0>::new());
+        let error = check::<Root, _>(bytes, &mut context).unwrap_err();
+        assert_eq!(
+            error.kind(),
+            Some(ErrorKind::SharedCapacityExceeded { capacity: 0 }),
+        );
+
+        // A value which points to itself is cyclic.
+        #[repr(C)]
+        struct CycleBuffer {
+            root: RelShared<Node>,
+            node: Node,
+        }
+        let node = offset_of!(CycleBuffer, node);
+        let cycle = CycleBuffer {
+            root: rel(node, 0),
+            node: Node {
+                value: 0,
+                next: rel(node, node + offset_of!(Node, next)),
+            },
+        };
+        let bytes = bytes_of(&cycle);
+        let mut context = WithShared::new(Bounds::new(bytes), SharedMap::new());
+        let error =
+            check::<RelShared<Node>, _>(bytes, &mut context).unwrap_err();
+        assert_eq!(error.kind(), Some(ErrorKind::CyclicShared));
+
+        // A value can't be shared as two different types.
+        #[repr(C)]
+        struct MismatchBuffer {
+            a: RelShared<Leaf>,
+            b: RelShared<u32>,
+            leaf: Leaf,
+        }
+        #[derive(CheckBytes)]
+        #[repr(C)]
+        struct MismatchRoot {
+            a: RelShared<Leaf>,
+            b: RelShared<u32>,
+        }
+        let leaf = offset_of!(MismatchBuffer, leaf);
+        let mismatch = MismatchBuffer {
+            a: rel(leaf, offset_of!(MismatchBuffer, a)),
+            b: rel(leaf, offset_of!(MismatchBuffer, b)),
+            leaf: Leaf { value: 0 },
+        };
+        let bytes = bytes_of(&mismatch);
+        let mut context = WithShared::new(Bounds::new(bytes), SharedMap::new());
+        let error = check::<MismatchRoot, _>(bytes, &mut context).unwrap_err();
+        assert_eq!(error.kind(), Some(ErrorKind::SharedTypeMismatch));
+    }
+
+    #[test]
+    #[cfg(feature = "rayon")]
+    fn test_parallel() {
+        use bytecheck::{
+            budget::{Budget, BudgetContext, Budgeted},
+            depth::DepthLimit,
+            error::{CheckError, ErrorKind, Location},
+            parallel::{
+                check_slice, slice_from_bytes, slice_from_bytes_with_context,
+                SplitContext, DEFAULT_THRESHOLD,
+            },
+        };
+
+        let mut bytes = vec![1u8; 100_000];
+        assert_eq!(
+            slice_from_bytes::<bool, CheckError>(&bytes).unwrap().len(),
+            bytes.len(),
+        );
+
+        // The lowest failing index is reported, regardless of which chunk
+        // fails first.
+        bytes[90_000] = 2;
+        bytes[70_000] = 3;
+        bytes[99_999] = 4;
+        for _ in 0..8 {
+            let error = slice_from_bytes_with_context::<bool, _, CheckError>(
+                &bytes,
+                &mut DepthLimit::default(),
+            )
+            .unwrap_err();
+            assert_eq!(error.path().to_string(), "[70000]");
+            assert_eq!(
+                error.location(),
+                Some(Location {
+                    offset: 70_000,
+                    len: 1,
+                }),
+            );
+        }
+
+        // Small slices are checked sequentially.
+        let small = &bytes[69_990..70_010];
+        assert!(small.len() < DEFAULT_THRESHOLD);
+        let error = unsafe {
+            check_slice::<bool, _, CheckError>(
+                small as *const [u8] as *const [bool],
+                &mut (),
+                DEFAULT_THRESHOLD,
+            )
+            .unwrap_err()
+        };
+        assert_eq!(error.path().to_string(), "[10]");
+
+        #[derive(CheckBytes, Debug)]
+        #[check_bytes(verify)]
+        #[repr(transparent)]
+        struct Costly(u8);
+
+        unsafe impl<C> Verify<C> for Costly
+        where
+            C: Fallible + BudgetContext + ?Sized,
+        {
+            fn verify(&self, context: &mut C) -> Result<(), C::Error> {
+                context.charge(1)
+            }
+        }
+
+        // The budget is charged for the slice up front, and the rest of it is
+        // split between the chunks.
+        let bytes = vec![1u8; 50_000];
+        let mut context = Budgeted::new((), Budget::new(100_000));
+        slice_from_bytes_with_context::<Costly, _, CheckError>(
+            &bytes,
+            &mut context,
+        )
+        .unwrap();
+        assert_eq!(context.budget().remaining(), 0);
+        for limit in [49_999, 99_999] {
+            let error = slice_from_bytes_with_context::<Costly, _, CheckError>(
+                &bytes,
+                &mut Budgeted::new((), Budget::new(limit)),
+            )
+            .unwrap_err();
+            assert_eq!(error.kind(), Some(ErrorKind::BudgetExceeded { limit }));
+        }
+
+        // A context which splits into too few parts can't skip any chunks.
+        struct ShortSplit;
+
+        impl<E> BudgetContext<E> for ShortSplit {}
+
+        impl SplitContext for ShortSplit {
+            fn split(&mut self, parts: usize) -> Vec<Self> {
+                (1..parts).map(|_| ShortSplit).collect()
+            }
+
+            fn merge(&mut self, _: Self) {}
+        }
+
+        let mut bytes = vec![1u8; 100_000];
+        bytes[99_999] = 2;
+        let result = std::panic::catch_unwind(|| {
+            slice_from_bytes_with_context::<bool, _, CheckError>(
+                &bytes,
+                &mut ShortSplit,
+            )
+            .is_ok()
+        });
+        assert!(result.is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_stream() {
+        use bytecheck::{
+            error::{CheckError, ErrorKind, Location},
+            stream::SliceValidator,
+        };
+
+        #[derive(CheckBytes, Debug)]
+        #[repr(C)]
+        struct Pair {
+            a: u32,
+            b: bool,
+        }
+
+        let mut bytes = Vec::new();
+        for i in 0..4u32 {
+            bytes.extend_from_slice(&i.to_ne_bytes());
+            bytes.extend_from_slice(&[1, 0, 0, 0]);
+        }
+
+        // Elements can be split across chunks of any size.
+        for chunk_len in [1, 3, 8, 13, bytes.len()] {
+            let mut validator = SliceValidator::<Pair, ()>::new(());
+            let mut checked = 0;
+            for chunk in bytes.chunks(chunk_len) {
+                checked += validator.push::<CheckError>(chunk).unwrap();
+                assert_eq!(checked, validator.checked());
+            }
+            assert_eq!(validator.checked(), 4);
+            assert_eq!(validator.consumed(), bytes.len());
+            validator.finish::<CheckError>().unwrap();
+        }
+
+        // A partial element is an error when finishing.
+        let mut validator = SliceValidator::<Pair, ()>::new(());
+        validator.push::<CheckError>(&bytes[..10]).unwrap();
+        assert_eq!(validator.checked(), 1);
+        assert_eq!(validator.pending(), 2);
+        assert!(validator.finish::<CheckError>().is_err());
+
+        // Checking stops at the first invalid element.
+        bytes[20] = 2;
+        let mut validator = SliceValidator::<Pair, ()>::new(());
+        validator.push::<CheckError>(&bytes[..16]).unwrap();
+        let error = validator.push::<CheckError>(&bytes[16..]).unwrap_err();
+        assert_eq!(error.path().to_string(), "[2].b");
+        assert_eq!(error.kind(), Some(ErrorKind::InvalidBool { byte: 2 }));
+        assert_eq!(error.location(), Some(Location { offset: 20, len: 1 }),);
+        assert!(validator.is_failed());
+        assert_eq!(validator.checked(), 2);
+        assert!(validator.push::<CheckError>(&[]).is_err());
+        assert!(validator.finish::<CheckError>().is_err());
+
+        // Zero-sized elements only accept empty chunks.
+        let mut validator = SliceValidator::<(), ()>::new(());
+        assert_eq!(validator.push::<CheckError>(&[]).unwrap(), 0);
+        let error = validator.push::<CheckError>(&[0]).unwrap_err();
+        assert_eq!(error.to_string(), "buffer has length 1, expected 0");
+        assert!(validator.is_failed());
+        assert!(validator.finish::<CheckError>().is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_snapshot() {
+        use core::{
+            mem::MaybeUninit,
+            sync::atomic::{AtomicU32, Ordering},
+        };
+
+        use bytecheck::{
+            error::{CheckError, ErrorKind},
+            snapshot::{read, read_seqlock, read_slice, read_slice_into},
+        };
+
+        #[derive(CheckBytes, Debug, PartialEq)]
+        #[repr(C)]
+        struct Message {
+            id: u32,
+            ok: bool,
+        }
+
+        // Unaligned sources are copied into aligned storage.
+        let mut shared = [0u8; 9];
+        shared[1..5].copy_from_slice(&7u32.to_ne_bytes());
+        shared[5] = 1;
+        let src = shared[1..].as_ptr().cast::<Message>();
+        let value = unsafe { read::<Message, CheckError>(src).unwrap() };
+        assert_eq!(value, Message { id: 7, ok: true });
+
+        // The returned value is a copy, so changing the source afterward
+        // doesn't affect it.
+        shared[5] = 2;
+        assert!(value.ok);
+        let src = shared[1..].as_ptr().cast::<Message>();
+        let error = unsafe { read::<Message, CheckError>(src).unwrap_err() };
+        assert_eq!(error.kind(), Some(ErrorKind::InvalidBool { byte: 2 }));
+
+        let bools = [1u8, 0, 1, 3];
+        let values = unsafe {
+            read_slice::<bool, CheckError>(bools.as_ptr().cast(), 3).unwrap()
+        };
+        assert_eq!(values, [true, false, true]);
+        let error = unsafe {
+            read_slice::<bool, CheckError>(bools.as_ptr().cast(), 4)
+                .unwrap_err()
+        };
+        assert_eq!(error.path().to_string(), "[3]");
+
+        let mut out = [MaybeUninit::uninit(); 2];
+        let values = unsafe {
+            read_slice_into::<bool, _, CheckError>(
+                bools.as_ptr().cast(),
+                &mut out,
+                &mut (),
+            )
+            .unwrap()
+        };
+        assert_eq!(values, [true, false]);
+
+        // Consistent copies are checked.
+        let version = AtomicU32::new(2);
+        let payload = Message { id: 3, ok: false };
+        let value = unsafe {
+            read_seqlock::<Message, CheckError>(&version, &payload, 0).unwrap()
+        };
+        assert_eq!(value, payload);
+
+        // A value which is being written is retried until it runs out of
+        // attempts.
+        version.store(3, Ordering::Relaxed);
+        let error = unsafe {
+            read_seqlock::<Message, CheckError>(&version, &payload, 4)
+                .unwrap_err()
+        };
+        assert_eq!(
+            error.to_string(),
+            "failed to read a consistent copy of a value after 5 attempts",
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "mmap")]
+    fn test_mmap() {
+        use std::fs;
+
+        use bytecheck::{
+            error::{CheckError, ErrorKind, Location},
+            mmap::{open, open_slice},
+        };
+
+        #[derive(CheckBytes, Debug, PartialEq)]
+        #[repr(C)]
+        struct Header {
+            magic: u32,
+            ok: bool,
+        }
+
+        let path = std::env::temp_dir()
+            .join(format!("bytecheck_test_mmap_{}", std::process::id()));
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&0xfeedu32.to_ne_bytes());
+        bytes.extend_from_slice(&[1, 0, 0, 0, 1, 0, 1, 2]);
+        fs::write(&path, &bytes).unwrap();
+
+        let header = unsafe { open::<Header, CheckError>(&path, 0).unwrap() };
+        assert_eq!(
+            *header,
+            Header {
+                magic: 0xfeed,
+                ok: true,
+            },
+        );
+
+        let values =
+            unsafe { open_slice::<bool, CheckError>(&path, 8, 3).unwrap() };
+        assert_eq!(*values, [true, false, true]);
+        let empty =
+            unsafe { open_slice::<u32, CheckError>(&path, 12, 0).unwrap() };
+        assert!(empty.is_empty());
+
+        // Locations are relative to the start of the value.
+        let error =
+            unsafe { open_slice::<bool, CheckError>(&path, 8, 4).unwrap_err() };
+        assert_eq!(error.kind(), Some(ErrorKind::InvalidBool { byte: 2 }));
+        assert_eq!(error.location(), Some(Location { offset: 3, len: 1 }));
+
+        // The value must be within the file and aligned.
+        let error =
+            unsafe { open_slice::<bool, CheckError>(&path, 8, 5).unwrap_err() };
+        assert_eq!(
+            error.to_string(),
+            "5 bytes at offset 8 extend past the end of the file of 12 bytes",
+        );
+        assert!(unsafe { open::<u32, CheckError>(&path, 2) }.is_err());
+        assert!(unsafe {
+            open::<u32, CheckError>(path.with_extension("missing"), 0)
+        }
+        .is_err());
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_io() {
+        use std::{error::Error, io::ErrorKind as IoErrorKind};
+
+        use bytecheck::{
+            depth::DepthLimit,
+            error::{CheckError, ErrorKind},
+            io::{read, read_slice, read_with_context, ReadError},
+        };
+
+        #[derive(CheckBytes, Debug, PartialEq)]
+        #[repr(C)]
+        struct Record {
+            id: u32,
+            ok: bool,
+        }
+
+        let mut bytes = Vec::new();
+        for (id, ok) in [(1u32, 1u8), (2, 0), (3, 2)] {
+            bytes.extend_from_slice(&id.to_ne_bytes());
+            bytes.extend_from_slice(&[ok, 0, 0, 0]);
+        }
+
+        let mut reader = &bytes[..];
+        let record = read::<Record, CheckError, _>(&mut reader).unwrap();
+        assert_eq!(record, Record { id: 1, ok: true });
+        let records =
+            read_slice::<Record, CheckError, _>(&mut reader, 1).unwrap();
+        assert_eq!(records, [Record { id: 2, ok: false }]);
+
+        // Validation errors and I/O errors are reported separately.
+        let error = read::<Record, CheckError, _>(&mut reader).unwrap_err();
+        assert_eq!(
+            error.validation_error().unwrap().kind(),
+            Some(ErrorKind::InvalidBool { byte: 2 }),
+        );
+        let error = read::<Record, CheckError, _>(&mut reader).unwrap_err();
+        assert_eq!(
+            error.io_error().unwrap().kind(),
+            IoErrorKind::UnexpectedEof,
+        );
+
+        let mut reader = &bytes[..];
+        let error =
+            read_slice::<Record, CheckError, _>(&mut reader, 3).unwrap_err();
+        match error {
+            ReadError::Invalid(error) => {
+                assert_eq!(error.path().to_string(), "[2].ok")
+            }
+            ReadError::Io(error) => panic!("unexpected I/O error: {error}"),
+        }
+        let mut reader = &bytes[..];
+        assert!(read_slice::<Record, CheckError, _>(&mut reader, usize::MAX)
+            .unwrap_err()
+            .io_error()
+            .is_some());
+        let mut reader = &bytes[..];
+        let error = read_slice::<Record, CheckError, _>(
+            &mut reader,
+            isize::MAX as usize / 8 + 1,
+        )
+        .unwrap_err();
+        assert_eq!(error.io_error().unwrap().kind(), IoErrorKind::OutOfMemory,);
+
+        // The validation error is the source of a read error.
+        let mut reader = &bytes[16..];
+        let error = read::<Record, CheckError, _>(&mut reader).unwrap_err();
+        let source = Error::source(&error).unwrap();
+        assert_eq!(
+            source.downcast_ref::<CheckError>().unwrap().kind(),
+            Some(ErrorKind::InvalidBool { byte: 2 }),
+        );
+
+        let mut reader = &bytes[..];
+        let record = read_with_context::<Record, _, CheckError, _>(
+            &mut reader,
+            &mut DepthLimit::default(),
+        )
+        .unwrap();
+        assert_eq!(record, Record { id: 1, ok: true });
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_frame() {
+        use bytecheck::{
+            error::{CheckError, ErrorKind, Location},
+            frame::{Endianness, FrameConfig},
+            io::ReadError,
+        };
+
+        #[derive(CheckBytes, Debug, PartialEq)]
+        #[repr(C)]
+        struct Message {
+            id: u32,
+            ok: bool,
+        }
+
+        // Frames with a two-byte big-endian prefix, with the prefix and
+        // payload aligned to four bytes.
+        let config = FrameConfig::new()
+            .with_prefix(2)
+            .with_endianness(Endianness::Big)
+            .with_align(4)
+            .with_max_len(16);
+        let mut stream = Aligned([0; 48]);
+        let mut offset = 0;
+        for (id, ok, len) in [(1u32, 1u8, 8u8), (2, 2, 8), (3, 0, 4), (4, 1, 8)]
+        {
+            stream.0[offset + 1] = len;
+            let payload = &mut stream.0[offset + 4..];
+            payload[..4].copy_from_slice(&id.to_ne_bytes());
+            payload[4] = ok;
+            offset += 4 + len as usize;
+        }
+        assert_eq!(offset, 44);
+        let bytes = &stream.0[..offset];
+
+        let mut frames = config.frames::<Message, CheckError>(bytes);
+        assert_eq!(
+            *frames.next().unwrap().unwrap(),
+            Message { id: 1, ok: true }
+        );
+        // Invalid payloads don't stop checking.
+        let error = frames.next().unwrap().unwrap_err();
+        assert_eq!(error.kind(), Some(ErrorKind::InvalidBool { byte: 2 }));
+        assert_eq!(error.location(), Some(Location { offset: 20, len: 1 }));
+        assert!(error
+            .traces()
+            .any(|t| t == "while checking frame 1 at offset 12"));
+        // Neither do payloads with the wrong length.
+        assert!(frames.next().unwrap().is_err());
+        assert_eq!(
+            *frames.next().unwrap().unwrap(),
+            Message { id: 4, ok: true }
+        );
+        assert!(frames.next().is_none());
+        assert_eq!(frames.offset(), 44);
+
+        let mut frames = config.read_frames::<Message, CheckError, _>(bytes);
+        assert_eq!(
+            frames.next().unwrap().unwrap(),
+            Message { id: 1, ok: true }
+        );
+        let error = frames.next().unwrap().unwrap_err();
+        let error = error.validation_error().unwrap();
+        assert_eq!(error.location(), Some(Location { offset: 20, len: 1 }));
+        assert!(frames.next().unwrap().is_err());
+        assert_eq!(
+            frames.next().unwrap().unwrap(),
+            Message { id: 4, ok: true }
+        );
+        assert!(frames.next().is_none());
+
+        // Malformed frames end the stream.
+        let truncated = &stream.0[..40];
+        let results = config
+            .frames::<Message, CheckError>(truncated)
+            .collect::<Vec<_>>();
+        assert_eq!(results.len(), 4);
+        assert!(results[3]
+            .as_ref()
+            .unwrap_err()
+            .to_string()
+            .starts_with("stream ended after 40 bytes"));
+        let results = config
+            .read_frames::<Message, CheckError, _>(truncated)
+            .collect::<Vec<_>>();
+        assert_eq!(results.len(), 4);
+        assert!(matches!(results[3], Err(ReadError::Invalid(_))));
+
+        let config = config.with_max_len(4);
+        let results = config
+            .read_frames::<Message, CheckError, _>(bytes)
+            .collect::<Vec<_>>();
+        assert_eq!(results.len(), 1);
+        assert!(results[0]
+            .as_ref()
+            .unwrap_err()
+            .to_string()
+            .starts_with("frame has length 8"));
+
+        // Lengths which would overflow the offset are truncated frames.
+        let config = FrameConfig::new().with_prefix(8).with_max_len(usize::MAX);
+        let mut frames = config.frames::<Message, CheckError>(&[0xff; 9]);
+        assert!(frames
+            .next()
+            .unwrap()
+            .unwrap_err()
+            .to_string()
+            .starts_with("stream ended after 9 bytes"));
+        assert!(frames.next().is_none());
+        let mut frames =
+            config.read_frames::<Message, CheckError, _>(&[0xff; 9][..]);
+        assert!(matches!(frames.next(), Some(Err(ReadError::Invalid(_)))));
+        assert!(frames.next().is_none());
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_tlv() {
+        use bytecheck::{
+            error::{CheckError, ErrorKind, Location},
+            frame::FrameConfig,
+            tlv::{RecordType, TlvConfig, UnknownTagPolicy},
+        };
+
+        #[derive(CheckBytes, Debug, PartialEq)]
+        #[repr(C)]
+        struct Reading {
+            sensor: u16,
+            valid: bool,
+            level: u8,
+        }
+
+        let registry = [
+            RecordType::<CheckError>::new::<Reading>(1),
+            RecordType::new::<u32>(2),
+            RecordType::new::<bool>(3),
+        ];
+        assert_eq!(registry[1].type_name(), "u32");
+
+        // Records with a two-byte tag and a two-byte length, aligned to four
+        // bytes.
+        let config = TlvConfig::new()
+            .with_tag(2)
+            .with_frame(FrameConfig::new().with_prefix(2).with_align(4));
+        let mut stream = Aligned([0; 40]);
+        let mut offset = 0;
+        for (tag, value) in [
+            (1u16, &[7, 0, 1, 9][..]),
+            (9, &[0; 4][..]),
+            (3, &[2][..]),
+            (2, &[5, 0, 0, 0][..]),
+        ] {
+            stream.0[offset..offset + 2].copy_from_slice(&tag.to_le_bytes());
+            stream.0[offset + 2] = value.len() as u8;
+            stream.0[offset + 4..offset + 4 + value.len()]
+                .copy_from_slice(value);
+            offset += 4 + value.len().next_multiple_of(4);
+        }
+        let bytes = &stream.0[..offset];
+
+        let mut records = config.records(bytes, &registry);
+        let record = records.next().unwrap().unwrap();
+        assert_eq!(record.tag(), 1);
+        assert_eq!(record.type_name(), Some(core::any::type_name::<Reading>()));
+        assert_eq!(
+            record.get::<Reading>(),
+            Some(&Reading {
+                sensor: u16::from_le_bytes([7, 0]),
+                valid: true,
+                level: 9,
+            }),
+        );
+        assert_eq!(record.get::<u32>(), None);
+
+        // Unknown tags are errors by default, but don't stop checking.
+        let error = records.next().unwrap().unwrap_err();
+        assert!(error.to_string().starts_with("record has unknown tag 9"));
+        let error = records.next().unwrap().unwrap_err();
+        assert_eq!(error.kind(), Some(ErrorKind::InvalidBool { byte: 2 }));
+        assert_eq!(error.location(), Some(Location { offset: 20, len: 1 }));
+        assert!(error
+            .traces()
+            .any(|t| t == "while checking record 2 at offset 16"));
+        let record = records.next().unwrap().unwrap();
+        assert_eq!(
+            record.get::<u32>(),
+            Some(&u32::from_le_bytes([5, 0, 0, 0]))
+        );
+        assert!(records.next().is_none());
+
+        let tags = config
+            .with_unknown_tags(UnknownTagPolicy::Skip)
+            .records(bytes, &registry)
+            .map(|r| r.map(|r| r.tag()).ok())
+            .collect::<Vec<_>>();
+        assert_eq!(tags, [Some(1), None, Some(2)]);
+
+        let records = config
+            .with_unknown_tags(UnknownTagPolicy::Collect)
+            .records(bytes, &registry)
+            .filter_map(Result::ok)
+            .collect::<Vec<_>>();
+        assert_eq!(records.len(), 3);
+        assert!(!records[1].is_known());
+        assert_eq!(records[1].tag(), 9);
+        assert_eq!(records[1].bytes(), [0; 4]);
+
+        // Malformed records end the stream.
+        let results = config
+            .records(&bytes[..bytes.len() - 1], &registry)
+            .collect::<Vec<_>>();
+        assert_eq!(results.len(), 4);
+        assert!(results[3].is_err());
+
+        // Lengths which would overflow the offset are truncated records.
+        let config = config.with_frame(
+            FrameConfig::new().with_prefix(8).with_max_len(usize::MAX),
+        );
+        let mut records = config.records(&[0xff; 11], &registry);
+        assert!(records
+            .next()
+            .unwrap()
+            .unwrap_err()
+            .to_string()
+            .starts_with("stream ended after 11 bytes"));
+        assert!(records.next().is_none());
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_container() {
+        use bytecheck::{
+            container::{ContainerConfig, ContainerHeader, SectionEntry},
+            error::{CheckError, ErrorKind, Location},
+            tlv::RecordType,
+        };
+
+        #[derive(CheckBytes, Debug, PartialEq)]
+        #[repr(C)]
+        struct Meta {
+            id: u32,
+            ok: bool,
+        }
+
+        const MAGIC: [u8; 8] = *b"TESTARCH";
+
+        fn write(
+            version: u32,
+            sections: &[(u64, u64, u64)],
+            values: &[(usize, &[u8])],
+        ) -> Aligned<128> {
+            let mut buffer = Aligned([0; 128]);
+            let header = ContainerHeader {
+                magic: MAGIC,
+                version,
+                section_count: sections.len() as u32,
+            };
+            let ptr = buffer.0.as_mut_ptr();
+            unsafe {
+                ptr.cast::<ContainerHeader>().write(header);
+                for (i, &(offset, len, tag)) in sections.iter().enumerate() {
+                    ptr.add(16 + i * 24)
+                        .cast::<SectionEntry>()
+                        .write(SectionEntry { offset, len, tag });
+                }
+            }
+            for (offset, value) in values {
+                buffer.0[*offset..*offset + value.len()].copy_from_slice(value);
+            }
+            buffer
+        }
+
+        let registry = [
+            RecordType::<CheckError>::new::<Meta>(1),
+            RecordType::new::<u64>(2),
+            RecordType::new::<[bool; 3]>(3),
+        ];
+        let config = ContainerConfig::new(MAGIC, 2);
+
+        let sections = [(88, 8, 1), (96, 8, 2), (104, 3, 3)];
+        let mut meta = [0; 8];
+        meta[..4].copy_from_slice(&5u32.to_ne_bytes());
+        meta[4] = 1;
+        let buffer = write(
+            2,
+            &sections,
+            &[(88, &meta), (96, &9u64.to_ne_bytes()), (104, &[1, 0, 2])],
+        );
+        let bytes = &buffer.0[..107];
+
+        let mut container = config.open(bytes, &registry).unwrap();
+        assert_eq!(container.header().version, 2);
+        assert_eq!(container.len(), 3);
+        assert_eq!(
+            container.section(0).unwrap().get::<Meta>(),
+            Some(&Meta { id: 5, ok: true }),
+        );
+        assert_eq!(container.section(1).unwrap().get::<u64>(), Some(&9));
+
+        // Sections are checked lazily, so the invalid section is only found
+        // when it's checked.
+        let error = container.section(2).unwrap_err();
+        assert_eq!(error.kind(), Some(ErrorKind::InvalidBool { byte: 2 }));
+        assert_eq!(
+            error.location(),
+            Some(Location {
+                offset: 106,
+                len: 1
+            })
+        );
+        assert!(error
+            .traces()
+            .any(|t| t == "while checking section 2 with tag 3"));
+        assert!(container.check_all().is_err());
+
+        let open = |version, sections: &[(u64, u64, u64)]| {
+            let buffer = write(version, sections, &[]);
+            config
+                .open(&buffer.0, &registry)
+                .map(|_| ())
+                .map_err(|e| e.to_string())
+        };
+        assert!(open(2, &[(64, 8, 1)]).is_ok());
+        assert!(open(1, &[(64, 8, 1)])
+            .unwrap_err()
+            .starts_with("found version 1, expected 2"));
+        assert!(open(2, &[(64, 8, 4)])
+            .unwrap_err()
+            .starts_with("section has unknown type tag 4"));
+        // Sections must be in bounds, aligned, and not overlap.
+        assert!(open(2, &[(124, 8, 1)]).is_err());
+        assert!(open(2, &[(66, 8, 1)]).is_err());
+        assert!(open(2, &[(64, 8, 1), (68, 8, 2)]).is_err());
+        assert!(open(2, &[(16, 8, 1)]).is_err());
+        assert!(open(2, &[(u64::MAX, 8, 1)]).is_err());
+        assert!(open(2, &[(64, u64::MAX, 1)]).is_err());
+
+        let mut bad_magic = write(2, &[], &[]);
+        bad_magic.0[0] = b'X';
+        assert!(config.open(&bad_magic.0, &registry).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_envelope() {
+        use bytecheck::{
+            envelope::{Envelope, EnvelopeFormat},
+            error::{CheckError, ErrorKind, Location},
+            from_bytes,
+        };
+
+        struct Format;
+
+        impl EnvelopeFormat for Format {
+            const MAGIC: [u8; 8] = *b"TESTENVL";
+            const VERSION: u32 = 1;
+        }
+
+        #[derive(CheckBytes, Debug, PartialEq)]
+        #[repr(C)]
+        struct Record {
+            id: u32,
+            flags: [bool; 4],
+        }
+
+        #[derive(CheckBytes, Debug)]
+        #[repr(C)]
+        struct Reordered {
+            flags: [bool; 4],
+            id: u32,
+        }
+
+        let envelope = Envelope::<Record, Format>::new(Record {
+            id: 7,
+            flags: [true, false, true, false],
+        });
+        let mut buffer = Aligned([0; 40]);
+        unsafe {
+            core::ptr::copy_nonoverlapping(
+                (&envelope as *const Envelope<Record, Format>).cast::<u8>(),
+                buffer.0.as_mut_ptr(),
+                40,
+            );
+        }
+
+        let check = |bytes: &[u8]| {
+            from_bytes::<Envelope<Record, Format>, CheckError>(bytes)
+                .map(|_| ())
+                .map_err(|e| e.to_string())
+        };
+
+        let checked =
+            from_bytes::<Envelope<Record, Format>, CheckError>(&buffer.0)
+                .unwrap();
+        assert_eq!(checked.value(), envelope.value());
+        assert_eq!(checked.value().id, 7);
+
+        // The header must match the expected type.
+        let error =
+            from_bytes::<Envelope<Reordered, Format>, CheckError>(&buffer.0)
+                .unwrap_err();
+        assert!(error
+            .to_string()
+            .starts_with("envelope has type fingerprint"));
+        assert_eq!(error.location(), Some(Location { offset: 16, len: 8 }));
+
+        // Invalid contents are only found after the header is checked.
+        let mut bad = Aligned(buffer.0);
+        bad.0[37] = 2;
+        let error = from_bytes::<Envelope<Record, Format>, CheckError>(&bad.0)
+            .unwrap_err();
+        assert_eq!(error.kind(), Some(ErrorKind::InvalidBool { byte: 2 }));
+        assert_eq!(error.location(), Some(Location { offset: 37, len: 1 }));
+        bad.0[8] = 2;
+        assert!(check(&bad.0)
+            .unwrap_err()
+            .starts_with("found version 2, expected 1"));
+
+        let mut bad = Aligned(buffer.0);
+        bad.0[0] = b'X';
+        assert!(check(&bad.0).unwrap_err().starts_with("found magic bytes"));
+
+        let mut bad = Aligned(buffer.0);
+        bad.0[24] = 9;
+        assert!(check(&bad.0)
+            .unwrap_err()
+            .starts_with("envelope has a value of 9 bytes aligned to 4"));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_evolve() {
+        use bytecheck::{
+            error::{CheckError, ErrorKind},
+            evolve::{Evolving, EvolvingField, Trailing},
+            fingerprint::Fingerprint,
+            from_bytes,
+        };
+
+        #[derive(CheckBytes, Debug)]
+        #[check_bytes(size_field = size)]
+        #[repr(C)]
+        struct V1 {
+            size: u32,
+            id: u32,
+        }
+
+        #[derive(CheckBytes, Debug)]
+        #[check_bytes(size_field = size)]
+        #[repr(C)]
+        struct V2<T> {
+            size: u32,
+            id: u32,
+            #[check_bytes(added = 2)]
+            score: Trailing<T>,
+            #[check_bytes(added = 3)]
+            flag: Trailing<bool>,
+        }
+
+        fn write(size: u32, flag: u8) -> Aligned<16> {
+            let mut buffer = Aligned([0; 16]);
+            buffer.0[..4].copy_from_slice(&size.to_ne_bytes());
+            buffer.0[4..8].copy_from_slice(&1u32.to_ne_bytes());
+            buffer.0[8..12].copy_from_slice(&9u32.to_ne_bytes());
+            buffer.0[12] = flag;
+            buffer
+        }
+
+        let check = |size, flag| {
+            let buffer = write(size, flag);
+            from_bytes::<V2<u32>, CheckError>(&buffer.0)
+                .map(|v| v.present_fields().len())
+                .map_err(|e| e.to_string())
+        };
+
+        assert_eq!(
+            <V2<u32> as Evolving>::FIELDS[2],
+            EvolvingField {
+                name: "score",
+                offset: 8,
+                size: 4,
+                added: Some(2),
+            }
+        );
+
+        // Old data is read by a new reader, and trailing fields which weren't
+        // written aren't checked.
+        assert_eq!(check(8, 2), Ok(2));
+        assert_eq!(check(12, 2), Ok(3));
+        assert_eq!(check(13, 1), Ok(4));
+        // New data is read by an old reader.
+        assert_eq!(check(64, 1), Ok(4));
+        let buffer = write(13, 1);
+        let v1 = from_bytes::<V1, CheckError>(&buffer.0[..8]).unwrap();
+        assert_eq!(v1.present_fields().len(), 2);
+
+        let buffer = write(12, 2);
+        let v2 = from_bytes::<V2<u32>, CheckError>(&buffer.0).unwrap();
+        unsafe {
+            assert_eq!(v2.get(&v2.score), Some(&9));
+            assert_eq!(v2.get(&v2.flag), None);
+        }
+        assert!(v2.is_present("score"));
+        assert!(!v2.is_present("flag"));
+
+        // Records which are shorter than the reader's type are padded, and
+        // the padding is never checked.
+        let old = write(8, 0);
+        from_bytes::<V2<u32>, CheckError>(&old.0[..8]).unwrap_err();
+        let mut padded = Aligned([0; 16]);
+        padded.0[..8].copy_from_slice(&old.0[..8]);
+        padded.0[12] = 2;
+        let v2 = from_bytes::<V2<u32>, CheckError>(&padded.0).unwrap();
+        assert_eq!(v2.present_fields().len(), 2);
+        unsafe {
+            assert_eq!(v2.get(&v2.score), None);
+        }
+
+        // Present trailing fields are checked.
+        let buffer = write(13, 2);
+        let error = from_bytes::<V2<u32>, CheckError>(&buffer.0).unwrap_err();
+        assert_eq!(error.kind(), Some(ErrorKind::InvalidBool { byte: 2 }));
+        assert_eq!(error.path().to_string(), "V2.flag");
+
+        // Stored sizes must not cut off any field.
+        assert!(check(10, 1)
+            .unwrap_err()
+            .starts_with("stored size 10 cuts off field 'score'"));
+        assert!(check(4, 1)
+            .unwrap_err()
+            .starts_with("stored size 4 cuts off field 'id'"));
+
+        assert_ne!(V2::<u32>::FINGERPRINT, V2::<i32>::FINGERPRINT);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_integrity() {
+        use bytecheck::{
+            error::{CheckError, ErrorKind, Location},
+            from_bytes,
+            integrity::{crc32c, xxhash64, Crc32c, Integrity, XxHash64},
+        };
+
+        assert_eq!(crc32c(b""), 0);
+        assert_eq!(crc32c(b"123456789"), 0xe306_9283);
+        assert_eq!(xxhash64(b"", 0), 0xef46_db37_51d8_e999);
+        assert_eq!(xxhash64(b"a", 0), 0xd24e_c4f1_a98c_6e5b);
+        assert_eq!(xxhash64(b"abc", 0), 0x44bc_2cf5_ad77_0999);
+        let long = (0..100u8).collect::<Vec<_>>();
+        assert_ne!(xxhash64(&long, 0), xxhash64(&long[..99], 0));
+        assert_ne!(xxhash64(&long, 0), xxhash64(&long, 1));
+
+        #[derive(CheckBytes, Debug, PartialEq)]
+        #[repr(C)]
+        struct Record {
+            id: u32,
+            flags: [bool; 4],
+        }
+
+        fn write(id: u32, flags: [u8; 4], checksum: u64) -> Aligned<16> {
+            let mut buffer = Aligned([0; 16]);
+            buffer.0[..8].copy_from_slice(&checksum.to_ne_bytes());
+            buffer.0[8..12].copy_from_slice(&id.to_ne_bytes());
+            buffer.0[12..].copy_from_slice(&flags);
+            buffer
+        }
+
+        type Checked = Integrity<Record, XxHash64>;
+
+        let mut payload = [0; 8];
+        payload[..4].copy_from_slice(&7u32.to_ne_bytes());
+        payload[4..].copy_from_slice(&[1, 0, 1, 0]);
+        let buffer = write(7, [1, 0, 1, 0], xxhash64(&payload, 0));
+        assert_eq!(
+            from_bytes::<Checked, CheckError>(&buffer.0)
+                .unwrap()
+                .value()
+                .id,
+            7
+        );
+
+        // A corrupted value which is still valid fails the checksum.
+        let corrupted = write(6, [1, 0, 1, 0], xxhash64(&payload, 0));
+        let error =
+            from_bytes::<Checked, CheckError>(&corrupted.0).unwrap_err();
+        assert!(error.to_string().starts_with("xxHash64 checksum"));
+        assert_eq!(error.kind(), None);
+        assert_eq!(error.location(), Some(Location { offset: 8, len: 8 }));
+
+        // An invalid value with a matching checksum fails its own check.
+        payload[5] = 2;
+        let invalid = write(7, [1, 2, 1, 0], xxhash64(&payload, 0));
+        let error = from_bytes::<Checked, CheckError>(&invalid.0).unwrap_err();
+        assert_eq!(error.kind(), Some(ErrorKind::InvalidBool { byte: 2 }));
+        assert_eq!(error.path().to_string(), "Integrity.value.flags[1]");
+
+        let value = unsafe { Integrity::<u64, Crc32c>::new(3) };
+        assert_eq!(value.checksum(), crc32c(&3u64.to_ne_bytes()) as u64);
+        let bytes = unsafe {
+            core::slice::from_raw_parts(
+                (&value as *const Integrity<u64, Crc32c>).cast::<u8>(),
+                16,
+            )
+        };
+        assert_eq!(
+            from_bytes::<Integrity<u64, Crc32c>, CheckError>(bytes)
+                .unwrap()
+                .value(),
+            &3,
+        );
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        use bytecheck::fingerprint::{fingerprint_of, Fingerprint};
+
+        mod v1 {
+            use bytecheck::CheckBytes;
+
+            #[derive(CheckBytes)]
+            #[repr(C)]
+            pub struct Record {
+                pub a: u32,
+                pub b: u16,
+            }
+
+            #[derive(CheckBytes)]
+            #[allow(dead_code)]
+            #[repr(u8)]
+            pub enum Kind {
+                A,
+                B(u32),
+            }
+        }
+
+        mod v2 {
+            use bytecheck::CheckBytes;
+
+            #[derive(CheckBytes)]
+            #[repr(C)]
+            pub struct Record {
+                pub b: u16,
+                pub a: u32,
+            }
+
+            #[derive(CheckBytes)]
+            #[allow(dead_code)]
+            #[repr(u8)]
+            pub enum Kind {
+                A = 1,
+                B(u32),
+            }
+        }
+
+        mod v3 {
+            use bytecheck::CheckBytes;
+
+            #[derive(CheckBytes)]
+            #[repr(C)]
+            pub struct Record {
+                pub a: u32,
+                pub b: i16,
+            }
+
+            #[derive(CheckBytes)]
+            #[repr(C, packed)]
+            pub struct Packed {
+                pub a: u32,
+                pub b: u16,
+            }
+
+            #[derive(CheckBytes)]
+            #[repr(C)]
+            pub struct Packed2 {
+                pub a: u32,
+                pub b: u16,
+            }
+        }
+
+        mod v4 {
+            use bytecheck::CheckBytes;
+
+            #[derive(CheckBytes)]
+            #[repr(C)]
+            pub struct Record {
+                pub a: u32,
+                pub b: u16,
+            }
+        }
+
+        // Identical definitions have identical fingerprints.
+        assert_eq!(v1::Record::FINGERPRINT, v4::Record::FINGERPRINT);
+        // Reordering, retyping, and changing the repr all change it.
+        assert_ne!(v1::Record::FINGERPRINT, v2::Record::FINGERPRINT);
+        assert_ne!(v1::Record::FINGERPRINT, v3::Record::FINGERPRINT);
+        assert_ne!(v3::Packed::FINGERPRINT, v3::Packed2::FINGERPRINT);
+        // So does changing a discriminant.
+        assert_ne!(v1::Kind::FINGERPRINT, v2::Kind::FINGERPRINT);
+
+        #[derive(CheckBytes)]
+        #[repr(C)]
+        struct Generic<T> {
+            value: T,
+        }
+
+        assert_ne!(
+            fingerprint_of::<Generic<u32>>(),
+            fingerprint_of::<Generic<i32>>(),
+        );
+        assert_ne!(fingerprint_of::<[u8; 4]>(), fingerprint_of::<[u8; 5]>());
+        assert_ne!(fingerprint_of::<[u8]>(), fingerprint_of::<str>());
+
+        // Fields with omitted bounds still let recursive types have
+        // fingerprints.
+        #[derive(CheckBytes)]
+        #[check_bytes(bounds(
+            __C: bytecheck::bounds::BoundsContext
+                + bytecheck::shared::SharedContext
+        ))]
+        #[repr(C)]
+        struct Node {
+            value: u32,
+            #[omit_bounds]
+            next: bytecheck::rel_ptr::RelShared<Node>,
+        }
+
+        assert_ne!(Node::FINGERPRINT, 0);
+
+        // Fingerprints are the same on every target, even for types whose
+        // alignment differs between them.
+        assert_eq!(u32::FINGERPRINT, 0x360e_c9f9_d8e4_2c30);
+        assert_eq!(u64::FINGERPRINT, 0x0e88_a82c_1481_c2f3);
+        assert_eq!(u128::FINGERPRINT, 0x0dde_2f32_b32a_c355);
+        assert_eq!(f64::FINGERPRINT, 0xfbc5_7e3e_f22b_2eca);
+        assert_eq!(v1::Record::FINGERPRINT, 0xf228_3155_0814_3252);
+    }
+
+    #[test]
+    fn test_schema() {
+        use core::num::NonZeroU8;
+
+        use bytecheck::schema::{schema_of, Schema, SchemaKind};
+
+        #[derive(CheckBytes)]
+        #[repr(C)]
+        struct Test {
+            a: u8,
+            b: u32,
+            c: [bool; 3],
+        }
+
+        let schema = schema_of::<Test>();
+        assert_eq!(schema.name, "Test");
+        assert_eq!(schema.size, Some(12));
+        assert_eq!(schema.align, 4);
+        let SchemaKind::Struct { repr, fields } = schema.kind else {
+            panic!("expected a struct schema");
+        };
+        assert_eq!(repr, "C");
+        assert_eq!(fields.len(), 3);
+        assert_eq!(fields[0].name, Some("a"));
+        assert_eq!(fields[0].offset, 0);
+        assert_eq!(fields[0].schema, u8::SCHEMA);
+        assert_eq!(fields[1].name, Some("b"));
+        assert_eq!(fields[1].offset, 4);
+        assert_eq!(fields[2].offset, 8);
+        assert_eq!(
+            fields[2].schema.kind,
+            SchemaKind::Array {
+                element: bool::SCHEMA,
+                len: 3,
+            },
+        );
+
+        #[derive(CheckBytes)]
+        #[repr(C)]
+        struct TestTuple(u16, char);
+
+        let SchemaKind::Struct { fields, .. } = TestTuple::SCHEMA.kind else {
+            panic!("expected a struct schema");
+        };
+        assert_eq!(fields[0].name, None);
+        assert_eq!(fields[1].offset, 4);
+        assert_eq!(fields[1].schema.kind, SchemaKind::Char);
+
+        #[derive(CheckBytes)]
+        #[allow(dead_code)]
+        #[repr(u16)]
+        enum TestEnum {
+            A,
+            B(u8, u32) = 4,
+            C { c: NonZeroU8 },
+        }
+
+        let schema = schema_of::<TestEnum>();
+        assert_eq!(schema.size, Some(8));
+        let SchemaKind::Enum {
+            repr,
+            tag,
+            variants,
+        } = schema.kind
+        else {
+            panic!("expected an enum schema");
+        };
+        assert_eq!(repr, "u16");
+        assert_eq!(tag, u16::SCHEMA);
+        assert_eq!(variants.len(), 3);
+        assert_eq!(variants[0].name, "A");
+        assert_eq!(variants[0].discriminant, 0);
+        assert!(variants[0].fields.is_empty());
+        assert_eq!(variants[1].discriminant, 4);
+        assert_eq!(variants[1].fields[0].offset, 2);
+        assert_eq!(variants[1].fields[1].offset, 4);
+        assert_eq!(variants[2].name, "C");
+        assert_eq!(variants[2].discriminant, 5);
+        assert_eq!(variants[2].fields[0].name, Some("c"));
+        assert_eq!(variants[2].fields[0].offset, 2);
+        assert_eq!(variants[2].fields[0].schema.kind, SchemaKind::NonZero);
+
+        #[derive(CheckBytes)]
+        #[repr(C)]
+        struct Generic<T> {
+            tag: u8,
+            value: T,
+        }
+
+        let SchemaKind::Struct { fields, .. } =
+            schema_of::<Generic<u64>>().kind
+        else {
+            panic!("expected a struct schema");
+        };
+        assert_eq!(fields[1].offset, 8);
+        assert_eq!(fields[1].schema, u64::SCHEMA);
+        assert_eq!(schema_of::<str>().size, None);
+        assert_eq!(
+            schema_of::<[u32]>().kind,
+            SchemaKind::Slice {
+                element: u32::SCHEMA,
+            },
+        );
+
+        // Fields with omitted bounds are opaque.
+        #[derive(CheckBytes)]
+        #[check_bytes(bounds(
+            __C: bytecheck::bounds::BoundsContext
+                + bytecheck::shared::SharedContext
+        ))]
+        #[repr(C)]
+        struct Node {
+            value: u32,
+            #[omit_bounds]
+            next: bytecheck::rel_ptr::RelShared<Node>,
+        }
+
+        let SchemaKind::Struct { fields, .. } = Node::SCHEMA.kind else {
+            panic!("expected a struct schema");
+        };
+        assert_eq!(fields[1].schema.kind, SchemaKind::Opaque);
+        assert_eq!(fields[1].schema.size, Some(4));
+
+        // So are types with a verify hook.
+        #[derive(CheckBytes)]
+        #[check_bytes(verify)]
+        #[repr(C)]
+        struct Verified(u8);
+
+        unsafe impl<C: Fallible + ?Sized> Verify<C> for Verified {
+            fn verify(&self, _: &mut C) -> Result<(), C::Error> {
+                Ok(())
+            }
+        }
+
+        assert_eq!(Verified::SCHEMA.kind, SchemaKind::Opaque);
+        assert_eq!(Verified::SCHEMA.size, Some(1));
+
+        // Every field type has a schema, including tuples and pointers.
+        let SchemaKind::Struct { fields, .. } = <(u8, u32)>::SCHEMA.kind else {
+            panic!("expected a struct schema");
+        };
+        assert_eq!(fields.len(), 2);
+        assert_eq!(fields[1].offset, core::mem::offset_of!((u8, u32), 1));
+        assert_eq!(fields[1].schema, u32::SCHEMA);
+        assert_eq!(
+            <bytecheck::rel_ptr::RelPtr<u32>>::SCHEMA.kind,
+            SchemaKind::Opaque,
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_dynamic() {
+        use core::num::NonZeroU16;
+
+        use bytecheck::{
+            budget::{Budget, Budgeted},
+            dynamic::{check_schema, check_schema_with_context},
+            error::{CheckError, ErrorKind, Location},
+            from_bytes,
+            schema::{FieldSchema, Schema, SchemaKind, TypeSchema},
+            slice_from_bytes, slice_from_bytes_with_context,
+        };
+
+        #[derive(CheckBytes)]
+        #[repr(C)]
+        struct Test {
+            a: u8,
+            b: bool,
+            c: char,
+            d: NonZeroU16,
+            e: [bool; 2],
+        }
+
+        #[derive(CheckBytes)]
+        #[repr(C)]
+        struct Pair(bool, u32);
+
+        #[derive(CheckBytes)]
+        #[allow(dead_code)]
+        #[repr(i8)]
+        enum Kind {
+            A = -1,
+            B(bool, u8),
+            C { c: char },
+        }
+
+        #[derive(CheckBytes)]
+        #[repr(C)]
+        struct Outer {
+            test: Test,
+            kind: Kind,
+            pair: Pair,
+        }
+
+        // Checks `bytes` both statically and dynamically, and returns the
+        // error if they failed the same way.
+        fn parity<T>(bytes: &[u8]) -> Option<CheckError>
+        where
+            T: Schema + CheckBytes<Strategy<(), CheckError>>,
+        {
+            let expected = from_bytes::<T, CheckError>(bytes).err();
+            let found = check_schema::<CheckError>(bytes, T::SCHEMA).err();
+            if T::SCHEMA.kind == SchemaKind::Opaque {
+                // Opaque schemas are never accepted, even for valid values.
+                assert!(found.unwrap().to_string().contains("is opaque"));
+                return expected;
+            }
+            assert_eq!(
+                found.as_ref().map(CheckError::kind),
+                expected.as_ref().map(CheckError::kind),
+            );
+            assert_eq!(
+                found.as_ref().map(ToString::to_string),
+                expected.as_ref().map(ToString::to_string),
+            );
+            found
+        }
+
+        let mut valid = Aligned([0; 28]);
+        valid.0[0] = 7;
+        valid.0[1] = 1;
+        valid.0[4..8].copy_from_slice(&('x' as u32).to_ne_bytes());
+        valid.0[8..10].copy_from_slice(&1u16.to_ne_bytes());
+        valid.0[11] = 1;
+        valid.0[12] = -1i8 as u8;
+        valid.0[20] = 1;
+        valid.0[24..28].copy_from_slice(&42u32.to_ne_bytes());
+        assert!(parity::<Outer>(&valid.0).is_none());
+
+        #[derive(Debug)]
+        struct OddError;
+
+        impl core::fmt::Display for OddError {
+            fn fmt(
+                &self,
+                f: &mut core::fmt::Formatter<'_>,
+            ) -> core::fmt::Result {
+                write!(f, "value is odd")
+            }
+        }
+
+        impl std::error::Error for OddError {}
+
+        #[derive(CheckBytes)]
+        #[check_bytes(verify)]
+        #[repr(C)]
+        struct Even {
+            v: u8,
+        }
+
+        unsafe impl<C> Verify<C> for Even
+        where
+            C: Fallible + ?Sized,
+            C::Error: Source,
+        {
+            fn verify(&self, _: &mut C) -> Result<(), C::Error> {
+                if self.v & 1 != 0 {
+                    rancor::fail!(OddError);
+                }
+                Ok(())
+            }
+        }
+
+        // Values which fail their verify hook are never accepted dynamically.
+        assert!(parity::<Even>(&[2]).is_none());
+        assert_eq!(
+            parity::<Even>(&[3]).unwrap().kind(),
+            Some(ErrorKind::VerifyFailed { type_name: "Even" }),
+        );
+
+        let invalid = |changes: &[(usize, u8)]| {
+            let mut bytes = Aligned(valid.0);
+            for &(index, byte) in changes {
+                bytes.0[index] = byte;
+            }
+            parity::<Outer>(&bytes.0).expect("expected an error")
+        };
+
+        let error = invalid(&[(1, 2)]);
+        assert_eq!(error.kind(), Some(ErrorKind::InvalidBool { byte: 2 }));
+        assert_eq!(error.path().to_string(), "Outer.test.b");
+        assert_eq!(error.location(), Some(Location { offset: 1, len: 1 }));
+
+        let surrogate = 0xd800u32.to_ne_bytes();
+        let error = invalid(&[
+            (4, surrogate[0]),
+            (5, surrogate[1]),
+            (6, surrogate[2]),
+            (7, surrogate[3]),
+        ]);
+        assert_eq!(
+            error.kind(),
+            Some(ErrorKind::InvalidChar { value: 0xd800 }),
+        );
+
+        let error = invalid(&[(8, 0), (9, 0)]);
+        assert_eq!(error.kind(), Some(ErrorKind::ZeroNonZero));
+        assert_eq!(error.location(), Some(Location { offset: 8, len: 2 }));
+
+        let error = invalid(&[(11, 3)]);
+        assert_eq!(error.path().to_string(), "Outer.test.e[1]");
+
+        // Discriminants are compared and displayed with their sign.
+        let error = invalid(&[(12, 5)]);
+        assert_eq!(
+            error.kind(),
+            Some(ErrorKind::InvalidDiscriminant {
+                enum_name: "Kind",
+                discriminant: 5,
+            }),
+        );
+        assert_eq!(error.location(), Some(Location { offset: 12, len: 1 }));
+        let error = invalid(&[(12, 3)]);
+        assert!(error.to_string().contains("closest known variant is 'C'"));
+        let error = invalid(&[(12, -3i8 as u8)]);
+        assert!(error
+            .to_string()
+            .starts_with("invalid discriminant '-3' for enum 'Kind'"));
+        assert!(error.to_string().contains("closest known variant is 'A'"));
+
+        // Fields of tuple variants are indexed after the tag.
+        let error = invalid(&[(12, 0), (13, 2)]);
+        assert_eq!(error.location(), Some(Location { offset: 13, len: 1 }));
+        let error = invalid(&[
+            (12, 1),
+            (16, surrogate[0]),
+            (17, surrogate[1]),
+            (18, surrogate[2]),
+            (19, surrogate[3]),
+        ]);
+        assert_eq!(error.location(), Some(Location { offset: 16, len: 4 }));
+
+        let error = invalid(&[(20, 9)]);
+        assert_eq!(error.location(), Some(Location { offset: 20, len: 1 }));
+
+        // Buffers are checked the same way.
+        assert!(parity::<Pair>(&valid.0[..7]).is_some());
+        assert!(parity::<Pair>(&valid.0[1..9]).is_some());
+
+        // So are slices and strings.
+        let mut pairs = Aligned([0; 20]);
+        pairs.0[8] = 2;
+        for len in [16, 20] {
+            let expected =
+                slice_from_bytes::<Pair, CheckError>(&pairs.0[..len])
+                    .err()
+                    .map(|e| e.to_string());
+            let found =
+                check_schema::<CheckError>(&pairs.0[..len], <[Pair]>::SCHEMA)
+                    .err()
+                    .map(|e| e.to_string());
+            assert!(found.is_some());
+            assert_eq!(found, expected);
+        }
+
+        let error =
+            check_schema::<CheckError>(b"ab\xffc", str::SCHEMA).unwrap_err();
+        assert_eq!(
+            error.kind(),
+            Some(ErrorKind::InvalidUtf8 { valid_up_to: 2 }),
+        );
+        assert_eq!(error.location(), Some(Location { offset: 0, len: 4 }));
+        check_schema::<CheckError>("héllo".as_bytes(), str::SCHEMA).unwrap();
+
+        // Work is charged the same way.
+        let pairs = Aligned([0; 16]);
+        for limit in 0..4 {
+            let expected =
+                slice_from_bytes_with_context::<Pair, _, CheckError>(
+                    &pairs.0,
+                    &mut Budgeted::new((), Budget::new(limit)),
+                )
+                .is_ok();
+            let found = check_schema_with_context::<_, CheckError>(
+                &pairs.0,
+                <[Pair]>::SCHEMA,
+                &mut Budget::new(limit),
+            )
+            .is_ok();
+            assert_eq!(found, expected);
+        }
+
+        // Schemas which don't describe checkable types fail without reading
+        // outside of the buffer.
+        #[derive(CheckBytes)]
+        #[check_bytes(bounds(
+            __C: bytecheck::bounds::BoundsContext
+                + bytecheck::shared::SharedContext
+        ))]
+        #[repr(C)]
+        struct Node {
+            value: u32,
+            #[omit_bounds]
+            next: bytecheck::rel_ptr::RelShared<Node>,
+        }
+
+        let error = check_schema::<CheckError>(&valid.0[..8], Node::SCHEMA)
+            .unwrap_err();
+        assert!(error.to_string().contains("is opaque"));
+        assert_eq!(error.path().to_string(), "Node.next");
+
+        const TRUNCATED: TypeSchema<'static> = TypeSchema {
+            name: "Truncated",
+            size: Some(2),
+            align: 1,
+            kind: SchemaKind::Struct {
+                repr: "C",
+                fields: &[FieldSchema {
+                    name: Some("value"),
+                    offset: 1,
+                    schema: u32::SCHEMA,
+                }],
+            },
+        };
+
+        let error =
+            check_schema::<CheckError>(&[0, 0], &TRUNCATED).unwrap_err();
+        assert!(error
+            .to_string()
+            .starts_with("schema for type 'u32' extends past its parent"));
+    }
+
     #[test]
     fn test_explicit_crate_root() {
         mod bytecheck {}